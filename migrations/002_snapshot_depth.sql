@@ -0,0 +1,6 @@
+-- Orderbuch-Tiefe an der Spitze (Groessen) und optionale Top-N-Level
+ALTER TABLE market_snapshots ADD COLUMN IF NOT EXISTS up_bid_size   DOUBLE PRECISION;
+ALTER TABLE market_snapshots ADD COLUMN IF NOT EXISTS up_ask_size   DOUBLE PRECISION;
+ALTER TABLE market_snapshots ADD COLUMN IF NOT EXISTS down_bid_size DOUBLE PRECISION;
+ALTER TABLE market_snapshots ADD COLUMN IF NOT EXISTS down_ask_size DOUBLE PRECISION;
+ALTER TABLE market_snapshots ADD COLUMN IF NOT EXISTS depth         JSONB;
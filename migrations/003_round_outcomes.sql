@@ -0,0 +1,7 @@
+-- Aufgeloeste Runden (Gewinner-Token) fuer die Abrechnung offener Legs im Backtest
+CREATE TABLE IF NOT EXISTS round_outcomes (
+  market_slug  TEXT         NOT NULL,
+  round_start  TIMESTAMPTZ  NOT NULL,
+  winner       TEXT         NOT NULL CHECK (winner IN ('up', 'down')),
+  PRIMARY KEY (market_slug, round_start)
+);
@@ -0,0 +1,6 @@
+-- Betriebsereignisse (z.B. Kill-Switch-Wechsel) fuer die Nachvollziehbarkeit
+CREATE TABLE IF NOT EXISTS audit_events (
+  ts      TIMESTAMPTZ  NOT NULL,
+  event   TEXT         NOT NULL,
+  detail  JSONB        NOT NULL
+);
@@ -4,7 +4,8 @@ use anyhow::Context;
 use chrono::{DateTime, Utc};
 use serde::Deserialize;
 
-use crate::types::{BotConfig, PostgresConfig};
+use crate::types::{BotConfig, PostgresConfig, StrategyConfig};
+use crate::utils::env::interpolate_toml;
 
 /// Configuration for a single market backtest range.
 #[derive(Clone, Debug, Deserialize)]
@@ -14,6 +15,25 @@ pub struct MarketBacktestRange {
     pub end: DateTime<Utc>,
 }
 
+/// How unhedged legs are valued when their round ends.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolutionMode {
+    /// Mark held shares at the token's last observed mid in the round.
+    #[default]
+    FinalMid,
+    /// The token whose last mid is above 0.5 pays 1, the other 0.
+    FinalMidWinner,
+    /// Recorded winners from the `round_outcomes` table, falling back to the final mid.
+    Outcomes,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ResolutionConfig {
+    #[serde(default)]
+    pub mode: ResolutionMode,
+}
+
 /// Top-level backtest configuration loaded from TOML.
 #[derive(Clone, Debug, Deserialize)]
 pub struct BacktestConfig {
@@ -23,13 +43,24 @@ pub struct BacktestConfig {
     pub initial_capital: f64,
     /// Per-market time ranges to replay.
     pub markets: Vec<MarketBacktestRange>,
+    /// Strategy instances to replay side by side; empty replays a single two-leg engine on `[bot]`.
+    #[serde(default)]
+    pub strategies: Vec<StrategyConfig>,
+    /// Settlement of legs still open at round end.
+    #[serde(default)]
+    pub resolution: ResolutionConfig,
 }
 
 impl BacktestConfig {
     pub fn from_file(path: &str) -> anyhow::Result<Self> {
         let contents = fs::read_to_string(path)
             .with_context(|| format!("failed to read backtest config file at {path}"))?;
-        let cfg: Self = toml::from_str(&contents)
+        let mut raw: toml::Value = toml::from_str(&contents)
+            .with_context(|| format!("failed to parse backtest TOML at {path}"))?;
+        interpolate_toml(&mut raw)
+            .with_context(|| format!("failed to expand environment variables in {path}"))?;
+        let cfg: Self = raw
+            .try_into()
             .with_context(|| format!("failed to deserialize backtest TOML at {path}"))?;
         Ok(cfg)
     }
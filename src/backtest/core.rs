@@ -3,9 +3,16 @@ use std::collections::HashMap;
 use chrono::{DateTime, Utc};
 
 use crate::{
-    strategy::{MarketSnapshot, TwoLegDecision, TwoLegEngine, TwoLegParams},
-    types::BotConfig,
-    utils::math::locked_profit,
+    strategy::{
+        registry::{build_strategy, DEFAULT_STRATEGY_NAME},
+        LegSide, MarketSnapshot, OrderIntent, OrderOutcome, OrderUpdate, StrategyFill,
+        StrategySet, TradeAction,
+    },
+    types::{BotConfig, StrategyKind},
+    utils::{
+        math::locked_profit,
+        time::{round_end, round_start},
+    },
 };
 
 #[derive(Clone, Debug)]
@@ -18,27 +25,95 @@ pub struct BacktestTrade {
     pub locked_profit: f64,
 }
 
+/// An unhedged leg (or the unhedged remainder of one) settled at round end.
+#[derive(Clone, Debug)]
+pub struct NakedLegSettlement {
+    pub strategy: String,
+    pub market_slug: String,
+    pub round_start: DateTime<Utc>,
+    pub side: LegSide,
+    pub entry_price: f64,
+    /// Per-share value the leg settled at (0-1).
+    pub settlement_price: f64,
+    pub shares: f64,
+    pub pnl: f64,
+}
+
 #[derive(Clone, Debug)]
 pub struct BacktestResult {
     pub initial_capital: f64,
     pub final_capital: f64,
     pub total_profit: f64,
+    /// Sum of `locked_profit` over hedged trades.
+    pub locked_profit: f64,
+    /// Sum of `pnl` over naked legs settled at round end.
+    pub naked_profit: f64,
     pub trades: Vec<BacktestTrade>,
+    pub naked_legs: Vec<NakedLegSettlement>,
 }
 
+/// How open legs are valued when their round ends.
+#[derive(Clone, Debug, Default)]
+pub enum ResolutionSource {
+    /// Mark held shares at the token's last observed mid in the round.
+    #[default]
+    FinalMid,
+    /// Binary payoff: the token whose last mid is above 0.5 pays 1, the other 0.
+    FinalMidWinner,
+    /// Recorded winning token per (market slug, round start). Rounds without an entry fall
+    /// back to the final mid.
+    Outcomes(HashMap<RoundId, LegSide>),
+}
+
+impl ResolutionSource {
+    /// Per-share payoff of `side`, given the last (up, down) mids seen in the round.
+    fn payoff(
+        &self,
+        market_slug: &str,
+        round_start: DateTime<Utc>,
+        side: LegSide,
+        final_mids: Option<(f64, f64)>,
+    ) -> f64 {
+        let final_mid = |mids: (f64, f64)| match side {
+            LegSide::Up => mids.0,
+            LegSide::Down => mids.1,
+        };
+        match self {
+            ResolutionSource::FinalMid => final_mids.map(final_mid).unwrap_or(0.0),
+            ResolutionSource::FinalMidWinner => match final_mids {
+                Some(mids) if final_mid(mids) > 0.5 => 1.0,
+                _ => 0.0,
+            },
+            ResolutionSource::Outcomes(outcomes) => {
+                match outcomes.get(&(market_slug.to_string(), round_start)) {
+                    Some(winner) if *winner == side => 1.0,
+                    Some(_) => 0.0,
+                    None => final_mids.map(final_mid).unwrap_or(0.0),
+                }
+            }
+        }
+    }
+}
+
+/// (market slug, round start).
+pub type RoundId = (String, DateTime<Utc>);
+
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
 struct PositionKey {
+    strategy: String,
     market_slug: String,
     round_start: DateTime<Utc>,
 }
 
 #[derive(Clone, Debug)]
 struct OpenPosition {
+    side: LegSide,
     leg1_price: f64,
     shares: f64,
 }
 
-/// Deterministically replay a sequence of normalized snapshots through the strategy engine.
+/// Deterministically replay a sequence of normalized snapshots through the two-leg engine
+/// configured by `bot_cfg`.
 ///
 /// The caller is responsible for providing snapshots in time-ascending order. Given the same
 /// snapshots and config, the result is fully deterministic.
@@ -48,16 +123,49 @@ pub fn run_backtest_on_snapshots(
     initial_capital: f64,
     max_steps: Option<usize>,
 ) -> BacktestResult {
-    let params = TwoLegParams::from(bot_cfg);
-    let mut engine = TwoLegEngine::new(params);
+    let mut strategies = StrategySet::single(build_strategy(
+        StrategyKind::TwoLeg,
+        DEFAULT_STRATEGY_NAME,
+        bot_cfg,
+    ));
+    run_backtest_with_strategies(
+        snapshots,
+        &mut strategies,
+        bot_cfg.fee_rate,
+        &ResolutionSource::default(),
+        initial_capital,
+        max_steps,
+    )
+}
 
+/// Replay snapshots through an arbitrary set of strategies sharing one capital pool.
+///
+/// Marketable intents fill immediately at their limit price. Others are canceled if they are
+/// immediate-or-cancel, or else rest until a later snapshot of the same round trades through
+/// their limit, and are dropped when a replacing intent arrives or the round ends. A sell
+/// closes held shares at its price. A buy of the token opposite an open position pairs off
+/// against it and locks in `1 - (p1 + p2)` per share minus fees. Whatever is still
+/// open when its round ends is settled through `resolution` and reported as a naked leg.
+pub fn run_backtest_with_strategies(
+    snapshots: &[MarketSnapshot],
+    strategies: &mut StrategySet,
+    fee_rate: f64,
+    resolution: &ResolutionSource,
+    initial_capital: f64,
+    max_steps: Option<usize>,
+) -> BacktestResult {
     let mut capital = initial_capital;
     let mut trades = Vec::new();
+    let mut naked_legs = Vec::new();
     let mut open_positions: HashMap<PositionKey, OpenPosition> = HashMap::new();
+    // Last (up, down) mids seen per round that has not been settled yet.
+    let mut final_mids: HashMap<RoundId, (f64, f64)> = HashMap::new();
+    // Intents that were not marketable when emitted.
+    let mut resting: Vec<OrderIntent> = Vec::new();
 
     let mut processed = 0usize;
 
-    for snapshot in snapshots.iter().cloned() {
+    for snapshot in snapshots {
         if let Some(limit) = max_steps {
             if processed >= limit {
                 break;
@@ -65,65 +173,282 @@ pub fn run_backtest_on_snapshots(
         }
         processed += 1;
 
-        let decisions = engine.on_snapshot(snapshot.clone(), capital);
-        for decision in decisions {
-            match decision {
-                TwoLegDecision::OpenLeg1 {
-                    market_slug,
-                    round_start,
-                    shares,
-                    limit_price,
-                    ..
-                } => {
-                    let key = PositionKey {
-                        market_slug,
-                        round_start,
-                    };
-                    // Only record if there isn't already an open position for this round.
-                    open_positions
-                        .entry(key)
-                        .or_insert(OpenPosition { leg1_price: limit_price, shares });
-                }
-                TwoLegDecision::OpenLeg2 {
-                    market_slug,
-                    round_start,
-                    shares,
-                    limit_price,
-                    expected_locked_profit,
-                    ..
-                } => {
-                    let key = PositionKey {
-                        market_slug: market_slug.clone(),
-                        round_start,
-                    };
-                    if let Some(pos) = open_positions.remove(&key) {
-                        let profit = if expected_locked_profit.is_finite() {
-                            expected_locked_profit
-                        } else {
-                            locked_profit(pos.leg1_price, limit_price, pos.shares, bot_cfg.fee_rate)
-                        };
-                        capital += profit;
-                        trades.push(BacktestTrade {
-                            market_slug,
-                            round_start,
-                            leg1_price: pos.leg1_price,
-                            leg2_price: limit_price,
-                            shares,
-                            locked_profit: profit,
-                        });
-                    }
+        // Settle rounds that ended before this snapshot, oldest first.
+        let mut ended: Vec<RoundId> = final_mids
+            .keys()
+            .filter(|(_, start)| round_end(*start) <= snapshot.ts)
+            .cloned()
+            .collect();
+        ended.sort();
+        for round in ended {
+            let mids = final_mids.remove(&round);
+            settle_round(
+                &round,
+                mids,
+                resolution,
+                fee_rate,
+                &mut capital,
+                &mut open_positions,
+                &mut naked_legs,
+            );
+            strategies.on_round_end(&round.0, round.1);
+        }
+        resting.retain(|o| round_end(o.round_start) > snapshot.ts);
+
+        final_mids.insert(
+            (snapshot.market_slug.clone(), round_start(snapshot.ts)),
+            (snapshot.mid_up(), snapshot.mid_down()),
+        );
+
+        // Resting orders fill at their own limit once the book trades through it, before the
+        // strategies see the snapshot.
+        let (crossed, waiting): (Vec<_>, Vec<_>) = resting.into_iter().partition(|o| {
+            o.market_slug == snapshot.market_slug && is_marketable(o, snapshot)
+        });
+        resting = waiting;
+        for intent in crossed {
+            fill_intent(
+                intent,
+                snapshot.ts,
+                fee_rate,
+                &mut capital,
+                &mut open_positions,
+                &mut trades,
+                strategies,
+            );
+        }
+
+        let mut fills = Vec::new();
+        for intent in strategies.on_snapshot(snapshot, |_, allocation| capital * allocation) {
+            if !intent.replaces.is_empty() {
+                let (replaced, kept): (Vec<_>, Vec<_>) =
+                    resting.into_iter().partition(|o| replaces(&intent, o));
+                resting = kept;
+                for order in &replaced {
+                    strategies.on_order_update(&OrderUpdate::from_intent(
+                        order,
+                        OrderOutcome::Canceled,
+                        0.0,
+                    ));
                 }
             }
+            if is_marketable(&intent, snapshot) {
+                fills.push(intent);
+            } else if intent.immediate_or_cancel {
+                strategies.on_order_update(&OrderUpdate::from_intent(
+                    &intent,
+                    OrderOutcome::Canceled,
+                    0.0,
+                ));
+            } else {
+                strategies.on_order_update(&OrderUpdate::from_intent(
+                    &intent,
+                    OrderOutcome::Accepted,
+                    0.0,
+                ));
+                resting.push(intent);
+            }
+        }
+
+        for intent in fills {
+            fill_intent(
+                intent,
+                snapshot.ts,
+                fee_rate,
+                &mut capital,
+                &mut open_positions,
+                &mut trades,
+                strategies,
+            );
         }
     }
 
+    // Rounds still running when the data ends are settled as of their last snapshot.
+    let mut remaining: Vec<RoundId> = final_mids
+        .keys()
+        .cloned()
+        .chain(open_positions.keys().map(|k| (k.market_slug.clone(), k.round_start)))
+        .collect();
+    remaining.sort();
+    remaining.dedup();
+    for round in remaining {
+        let mids = final_mids.remove(&round);
+        settle_round(
+            &round,
+            mids,
+            resolution,
+            fee_rate,
+            &mut capital,
+            &mut open_positions,
+            &mut naked_legs,
+        );
+        strategies.on_round_end(&round.0, round.1);
+    }
+
     let total_profit = capital - initial_capital;
 
     BacktestResult {
         initial_capital,
         final_capital: capital,
         total_profit,
+        locked_profit: trades.iter().map(|t| t.locked_profit).sum(),
+        naked_profit: naked_legs.iter().map(|l| l.pnl).sum(),
         trades,
+        naked_legs,
+    }
+}
+
+/// Settle every strategy's open position in `round` as a naked leg.
+///
+/// Positions are settled in strategy-name order so results stay deterministic.
+fn settle_round(
+    round: &RoundId,
+    final_mids: Option<(f64, f64)>,
+    resolution: &ResolutionSource,
+    fee_rate: f64,
+    capital: &mut f64,
+    open_positions: &mut HashMap<PositionKey, OpenPosition>,
+    naked_legs: &mut Vec<NakedLegSettlement>,
+) {
+    let (market_slug, round_start) = round;
+    let mut keys: Vec<PositionKey> = open_positions
+        .keys()
+        .filter(|k| &k.market_slug == market_slug && k.round_start == *round_start)
+        .cloned()
+        .collect();
+    keys.sort_by(|a, b| a.strategy.cmp(&b.strategy));
+
+    for key in keys {
+        let Some(pos) = open_positions.remove(&key) else {
+            continue;
+        };
+        let settlement_price = resolution.payoff(market_slug, *round_start, pos.side, final_mids);
+        let cost = pos.leg1_price * pos.shares;
+        let pnl = settlement_price * pos.shares - cost - cost * fee_rate;
+        *capital += pnl;
+
+        naked_legs.push(NakedLegSettlement {
+            strategy: key.strategy,
+            market_slug: key.market_slug,
+            round_start: key.round_start,
+            side: pos.side,
+            entry_price: pos.leg1_price,
+            settlement_price,
+            shares: pos.shares,
+            pnl,
+        });
+    }
+}
+
+/// Fill `intent` completely at its limit price and report the fill to its strategy.
+fn fill_intent(
+    intent: OrderIntent,
+    ts: DateTime<Utc>,
+    fee_rate: f64,
+    capital: &mut f64,
+    open_positions: &mut HashMap<PositionKey, OpenPosition>,
+    trades: &mut Vec<BacktestTrade>,
+    strategies: &mut StrategySet,
+) {
+    let fill = StrategyFill::from_intent(&intent, intent.limit_price, intent.shares, ts);
+    apply_intent(intent, fee_rate, capital, open_positions, trades);
+    strategies.on_fill(&fill);
+}
+
+/// Whether a limit order for `intent` would trade against the touch of `snapshot`.
+fn is_marketable(intent: &OrderIntent, snapshot: &MarketSnapshot) -> bool {
+    match intent.action {
+        TradeAction::Buy => {
+            let ask = snapshot.ask(intent.side);
+            ask > 0.0 && intent.limit_price >= ask - 1e-9
+        }
+        TradeAction::Sell => {
+            let bid = snapshot.bid(intent.side);
+            bid > 0.0 && intent.limit_price <= bid + 1e-9
+        }
+    }
+}
+
+/// Whether `intent` cancels the resting `order` (same strategy, market and round, with one of
+/// the labels it replaces).
+fn replaces(intent: &OrderIntent, order: &OrderIntent) -> bool {
+    order.strategy == intent.strategy
+        && order.market_slug == intent.market_slug
+        && order.round_start == intent.round_start
+        && intent.replaces.contains(&order.label)
+}
+
+fn apply_intent(
+    intent: OrderIntent,
+    fee_rate: f64,
+    capital: &mut f64,
+    open_positions: &mut HashMap<PositionKey, OpenPosition>,
+    trades: &mut Vec<BacktestTrade>,
+) {
+    let key = PositionKey {
+        strategy: intent.strategy,
+        market_slug: intent.market_slug,
+        round_start: intent.round_start,
+    };
+    let price = intent.limit_price;
+    let shares = intent.shares;
+    let expected_locked_profit = intent.expected_locked_profit;
+
+    match (intent.action, open_positions.get_mut(&key)) {
+        (TradeAction::Buy, None) => {
+            open_positions.insert(
+                key,
+                OpenPosition {
+                    side: intent.side,
+                    leg1_price: price,
+                    shares,
+                },
+            );
+        }
+        (TradeAction::Buy, Some(pos)) if pos.side == intent.side => {
+            // Scaling into the same token: track the volume-weighted entry.
+            let total = pos.shares + shares;
+            pos.leg1_price = (pos.leg1_price * pos.shares + price * shares) / total;
+            pos.shares = total;
+        }
+        (TradeAction::Buy, Some(pos)) => {
+            // Hedges may be split across snapshots when displayed liquidity is thin; shares
+            // beyond the open position are not modeled.
+            let leg1_price = pos.leg1_price;
+            let matched = shares.min(pos.shares);
+            pos.shares -= matched;
+            if pos.shares <= 1e-9 {
+                open_positions.remove(&key);
+            }
+
+            // Report what the strategy expected when it sent the hedge, as live does; scaled
+            // down when only part of the hedge matched the open position.
+            let profit = match expected_locked_profit {
+                Some(expected) if shares > 0.0 => expected * matched / shares,
+                _ => locked_profit(leg1_price, price, matched, fee_rate),
+            };
+            *capital += profit;
+            trades.push(BacktestTrade {
+                market_slug: key.market_slug,
+                round_start: key.round_start,
+                leg1_price,
+                leg2_price: price,
+                shares: matched,
+                locked_profit: profit,
+            });
+        }
+        (TradeAction::Sell, Some(pos)) if pos.side == intent.side => {
+            let closed = shares.min(pos.shares);
+            let entry = pos.leg1_price;
+            pos.shares -= closed;
+            if pos.shares <= 1e-9 {
+                open_positions.remove(&key);
+            }
+            *capital += (price - entry) * closed - (price + entry) * closed * fee_rate;
+        }
+        (TradeAction::Sell, _) => {
+            // Short sales are not modeled; only held shares can be sold.
+        }
     }
 }
 
@@ -146,6 +471,11 @@ mod tests {
             up_ask: price_up * 1.01,
             down_bid: price_down * 0.99,
             down_ask: price_down * 1.01,
+            up_bid_size: None,
+            up_ask_size: None,
+            down_bid_size: None,
+            down_ask_size: None,
+            depth: None,
         }
     }
 
@@ -159,6 +489,16 @@ mod tests {
             risk_per_trade_pct: 2.0,
             fee_rate: 0.02,
             min_profit_usd: 0.0,
+            leg1_up_enabled: true,
+            leg1_down_enabled: true,
+            max_order_retries: 2,
+            hedge_requote_threshold: 0.01,
+            hedge_escalation_secs: 300,
+            hedge_taker_secs: 60,
+            hedge_max_sum: 1.0,
+            stop_loss_pct: None,
+            exit_before_end_secs: None,
+            emergency_hedge_max_loss_usd: None,
         }
     }
 
@@ -191,5 +531,134 @@ mod tests {
         assert!(!result.trades.is_empty());
         assert!(result.total_profit > 0.0);
     }
-}
 
+    #[test]
+    fn hedge_reports_the_strategy_expected_profit() {
+        let intent = |side, price, expected| OrderIntent {
+            strategy: "two_leg".to_string(),
+            market_slug: "BTC-USD-15MIN".to_string(),
+            round_start: ts("2024-01-01T12:00:00"),
+            side,
+            action: TradeAction::Buy,
+            shares: 10.0,
+            limit_price: price,
+            label: "leg".to_string(),
+            expected_locked_profit: expected,
+            attempt: 0,
+            replaces: Vec::new(),
+            immediate_or_cancel: false,
+            reduces_risk: false,
+        };
+        let mut capital = 100.0;
+        let mut positions = HashMap::new();
+        let mut trades = Vec::new();
+        apply_intent(
+            intent(LegSide::Up, 0.40, None),
+            0.02,
+            &mut capital,
+            &mut positions,
+            &mut trades,
+        );
+        apply_intent(
+            intent(LegSide::Down, 0.50, Some(0.75)),
+            0.02,
+            &mut capital,
+            &mut positions,
+            &mut trades,
+        );
+
+        assert_eq!(trades.len(), 1);
+        assert!((trades[0].locked_profit - 0.75).abs() < 1e-9);
+        assert!((capital - 100.75).abs() < 1e-9);
+    }
+
+    fn naked_leg_run(resolution: &ResolutionSource) -> BacktestResult {
+        let cfg = bot_cfg();
+        let snaps = vec![
+            snapshot(0.6, 0.4, "2024-01-01T12:00:10"),
+            // UP crashes; the hedge is too expensive for the whole round.
+            snapshot(0.4, 0.6, "2024-01-01T12:01:00"),
+            snapshot(0.3, 0.7, "2024-01-01T12:14:00"),
+            snapshot(0.5, 0.5, "2024-01-01T12:15:10"),
+        ];
+        let mut strategies = StrategySet::single(build_strategy(
+            StrategyKind::TwoLeg,
+            DEFAULT_STRATEGY_NAME,
+            &cfg,
+        ));
+        run_backtest_with_strategies(&snaps, &mut strategies, cfg.fee_rate, resolution, 10_000.0, None)
+    }
+
+    #[test]
+    fn settles_unhedged_leg_at_round_end() {
+        let result = naked_leg_run(&ResolutionSource::FinalMid);
+        assert!(result.trades.is_empty());
+        assert_eq!(result.naked_legs.len(), 1);
+
+        let leg = &result.naked_legs[0];
+        assert_eq!(leg.side, LegSide::Up);
+        assert_eq!(leg.round_start, ts("2024-01-01T12:00:00"));
+        assert!((leg.settlement_price - 0.3).abs() < 1e-9);
+        let cost = leg.entry_price * leg.shares;
+        let expected = 0.3 * leg.shares - cost * 1.02;
+        assert!((leg.pnl - expected).abs() < 1e-9);
+        assert!((result.naked_profit - expected).abs() < 1e-9);
+        assert!((result.total_profit - expected).abs() < 1e-9);
+        assert_eq!(result.locked_profit, 0.0);
+    }
+
+    #[test]
+    fn resolution_source_decides_naked_payoff() {
+        let winner = naked_leg_run(&ResolutionSource::FinalMidWinner);
+        let leg = &winner.naked_legs[0];
+        assert_eq!(leg.settlement_price, 0.0);
+        assert!((winner.total_profit + leg.entry_price * leg.shares * 1.02).abs() < 1e-9);
+
+        let outcomes = HashMap::from([(
+            ("BTC-USD-15MIN".to_string(), ts("2024-01-01T12:00:00")),
+            LegSide::Up,
+        )]);
+        let recorded = naked_leg_run(&ResolutionSource::Outcomes(outcomes));
+        assert_eq!(recorded.naked_legs[0].settlement_price, 1.0);
+        assert!(recorded.total_profit > 0.0);
+    }
+
+    #[test]
+    fn resting_hedge_fills_once_the_ask_trades_through_it() {
+        let cfg = bot_cfg();
+        let snaps = vec![
+            snapshot(0.6, 0.4, "2024-01-01T12:00:10"),
+            snapshot(0.4, 0.6, "2024-01-01T12:01:00"),
+            // Hedge too expensive: it rests at 0.95 - 0.404, floored to 0.54.
+            snapshot(0.4, 0.6, "2024-01-01T12:02:00"),
+            // The DOWN ask falls through the resting bid.
+            snapshot(0.5, 0.53, "2024-01-01T12:03:00"),
+        ];
+
+        let result = run_backtest_on_snapshots(&snaps, &cfg, 10_000.0, None);
+        assert_eq!(result.trades.len(), 1);
+        assert!((result.trades[0].leg2_price - 0.54).abs() < 1e-9);
+        assert!(result.naked_legs.is_empty());
+    }
+
+    #[test]
+    fn stop_loss_exit_sells_leg1_instead_of_settling_it() {
+        let mut cfg = bot_cfg();
+        cfg.stop_loss_pct = Some(0.2);
+        let snaps = vec![
+            snapshot(0.6, 0.4, "2024-01-01T12:00:10"),
+            snapshot(0.4, 0.6, "2024-01-01T12:01:00"),
+            snapshot(0.3, 0.75, "2024-01-01T12:02:00"),
+            snapshot(0.2, 0.8, "2024-01-01T12:14:00"),
+        ];
+
+        let result = run_backtest_on_snapshots(&snaps, &cfg, 10_000.0, None);
+        assert!(result.trades.is_empty());
+        assert!(result.naked_legs.is_empty());
+        assert!(result.total_profit < 0.0);
+        // Selling at 0.297 after buying at 0.404 loses 0.107 a share plus fees on both sides.
+        let per_share = 0.297 - 0.404 - (0.297 + 0.404) * cfg.fee_rate;
+        let shares = result.total_profit / per_share;
+        assert!(shares > 0.0 && shares <= cfg.shares + 1e-9);
+    }
+}
@@ -1,24 +1,32 @@
+use std::collections::HashMap;
+
 use chrono::Utc;
 use serde::Serialize;
-use tracing::info;
+use sqlx::PgPool;
+use tracing::{info, warn};
 
 use crate::{
-    backtest::config::BacktestConfig,
-    backtest::core::{run_backtest_on_snapshots, BacktestResult},
-    storage::{create_pg_pool, models::MarketSnapshotRow},
-    strategy::MarketSnapshot,
+    backtest::config::{BacktestConfig, MarketBacktestRange, ResolutionMode},
+    backtest::core::{run_backtest_with_strategies, BacktestResult, ResolutionSource, RoundId},
+    storage::{
+        create_pg_pool,
+        models::{MarketSnapshotRow, RoundOutcomeRow},
+    },
+    strategy::{LegSide, MarketSnapshot, StrategySet},
 };
 
 /// Execute a backtest by loading snapshots from TimescaleDB and replaying them through the
-/// two-leg strategy engine.
+/// configured strategies.
 pub async fn run_backtest(cfg: BacktestConfig) -> anyhow::Result<()> {
+    let mut strategies = StrategySet::from_config(&cfg.bot, &cfg.strategies)?;
     let pool = create_pg_pool(&cfg.postgres).await?;
 
     let mut rows_all: Vec<MarketSnapshotRow> = Vec::new();
 
     for m in &cfg.markets {
         let mut rows: Vec<MarketSnapshotRow> = sqlx::query_as(
-            "SELECT ts, market_slug, up_bid, up_ask, down_bid, down_ask \
+            "SELECT ts, market_slug, up_bid, up_ask, down_bid, down_ask, \
+                    up_bid_size, up_ask_size, down_bid_size, down_ask_size, depth \
              FROM market_snapshots \
              WHERE market_slug = $1 AND ts >= $2 AND ts <= $3 \
              ORDER BY ts ASC",
@@ -46,9 +54,19 @@ pub async fn run_backtest(cfg: BacktestConfig) -> anyhow::Result<()> {
         .map(MarketSnapshot::from)
         .collect();
 
-    let result = run_backtest_on_snapshots(
+    let resolution = match cfg.resolution.mode {
+        ResolutionMode::FinalMid => ResolutionSource::FinalMid,
+        ResolutionMode::FinalMidWinner => ResolutionSource::FinalMidWinner,
+        ResolutionMode::Outcomes => {
+            ResolutionSource::Outcomes(load_round_outcomes(&pool, &cfg.markets).await?)
+        }
+    };
+
+    let result = run_backtest_with_strategies(
         &snapshots,
-        &cfg.bot,
+        &mut strategies,
+        cfg.bot.fee_rate,
+        &resolution,
         cfg.initial_capital,
         None,
     );
@@ -58,6 +76,45 @@ pub async fn run_backtest(cfg: BacktestConfig) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Load recorded round winners for the configured ranges from the `round_outcomes` table.
+async fn load_round_outcomes(
+    pool: &PgPool,
+    markets: &[MarketBacktestRange],
+) -> anyhow::Result<HashMap<RoundId, LegSide>> {
+    let mut outcomes = HashMap::new();
+    for m in markets {
+        let rows: Vec<RoundOutcomeRow> = sqlx::query_as(
+            "SELECT market_slug, round_start, winner \
+             FROM round_outcomes \
+             WHERE market_slug = $1 AND round_start >= $2 AND round_start <= $3",
+        )
+        .bind(&m.slug)
+        .bind(m.start)
+        .bind(m.end)
+        .fetch_all(pool)
+        .await?;
+
+        for row in rows {
+            let winner = match row.winner.as_str() {
+                "up" => LegSide::Up,
+                "down" => LegSide::Down,
+                other => {
+                    warn!(
+                        target: "backtest",
+                        market = %row.market_slug,
+                        round_start = %row.round_start,
+                        winner = other,
+                        "ignoring round outcome with unknown winner"
+                    );
+                    continue;
+                }
+            };
+            outcomes.insert((row.market_slug, row.round_start), winner);
+        }
+    }
+    Ok(outcomes)
+}
+
 #[derive(Serialize)]
 struct BacktestSummary<'a> {
     event: &'a str,
@@ -66,8 +123,11 @@ struct BacktestSummary<'a> {
     initial_capital: f64,
     final_capital: f64,
     total_profit: f64,
+    locked_profit: f64,
+    naked_profit: f64,
     roi_pct: f64,
     trades: usize,
+    naked_legs: usize,
 }
 
 fn log_summary(result: &BacktestResult) {
@@ -79,12 +139,15 @@ fn log_summary(result: &BacktestResult) {
         initial_capital: result.initial_capital,
         final_capital: result.final_capital,
         total_profit: result.total_profit,
+        locked_profit: result.locked_profit,
+        naked_profit: result.naked_profit,
         roi_pct: if result.initial_capital != 0.0 {
             (result.final_capital / result.initial_capital - 1.0) * 100.0
         } else {
             0.0
         },
         trades: result.trades.len(),
+        naked_legs: result.naked_legs.len(),
     };
 
     let payload =
@@ -1,7 +1,7 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use alloy::{
-    primitives::{Address, U256},
+    primitives::{address, Address, U256},
     signers::local::PrivateKeySigner,
     signers::Signer,
 };
@@ -10,9 +10,10 @@ use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
 use base64::Engine as _;
 use chrono::Utc;
 use hmac::{Hmac, Mac};
+use serde::Serialize;
 use sha2::Sha256;
 
-use crate::types::ApiConfig;
+use crate::types::{ApiConfig, SignatureType};
 
 use super::{ClientError, ClientResult};
 
@@ -22,6 +23,17 @@ const CLOB_DOMAIN_NAME: &str = "ClobAuthDomain";
 const CLOB_VERSION: &str = "1";
 const MSG_TO_SIGN: &str = "This message attests that I control the given wallet";
 
+const EXCHANGE_DOMAIN_NAME: &str = "Polymarket CTF Exchange";
+const EXCHANGE_VERSION: &str = "1";
+
+/// USDC and conditional tokens both use 6 decimals on-chain.
+const TOKEN_DECIMALS: u32 = 6;
+/// The CLOB accepts sizes with at most two decimals.
+const SIZE_DECIMALS: u32 = 2;
+
+const POLYGON_CHAIN_ID: u64 = 137;
+const AMOY_CHAIN_ID: u64 = 80002;
+
 sol! {
     /// EIP-712 struct for CLOB L1 authentication.
     struct ClobAuth {
@@ -30,6 +42,22 @@ sol! {
         uint256 nonce;
         string message;
     }
+
+    /// EIP-712 struct for CTF Exchange limit orders.
+    struct Order {
+        uint256 salt;
+        address maker;
+        address signer;
+        address taker;
+        uint256 tokenId;
+        uint256 makerAmount;
+        uint256 takerAmount;
+        uint256 expiration;
+        uint256 nonce;
+        uint256 feeRateBps;
+        uint8 side;
+        uint8 signatureType;
+    }
 }
 
 /// Build an alloy private key signer from the configured wallet private key.
@@ -127,3 +155,432 @@ pub fn default_nonce() -> u64 {
         .unwrap_or_else(|_| rand::random())
 }
 
+/// Order direction as encoded in the CTF Exchange `side` field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    fn as_u8(self) -> u8 {
+        match self {
+            Side::Buy => 0,
+            Side::Sell => 1,
+        }
+    }
+}
+
+impl SignatureType {
+    pub(crate) fn as_u8(self) -> u8 {
+        match self {
+            SignatureType::Eoa => 0,
+            SignatureType::PolyProxy => 1,
+            SignatureType::PolyGnosisSafe => 2,
+        }
+    }
+}
+
+/// CTF Exchange contract that settles orders on the given chain.
+pub fn exchange_address(chain_id: u64, neg_risk: bool) -> ClientResult<Address> {
+    match (chain_id, neg_risk) {
+        (POLYGON_CHAIN_ID, false) => Ok(address!("4bFb41d5B3570DeFd03C39a9A4D8dE6Bd8B8982E")),
+        (POLYGON_CHAIN_ID, true) => Ok(address!("C5d563A36AE78145C45a50134d48A1215220f80a")),
+        (AMOY_CHAIN_ID, false) => Ok(address!("dFE02Eb6733538f8Ea35D585af8DE5958AD99E40")),
+        (AMOY_CHAIN_ID, true) => Ok(address!("d91E80cF2E7be2e162c6513ceD06f1dD0dA35296")),
+        _ => Err(ClientError::Config(format!(
+            "no CTF exchange known for chain id {chain_id}"
+        ))),
+    }
+}
+
+/// Human-level order parameters, before scaling to on-chain amounts.
+#[derive(Clone, Debug)]
+pub struct OrderArgs {
+    /// Conditional token ID as a decimal string.
+    pub token_id: String,
+    pub side: Side,
+    pub price: f64,
+    /// Shares; rounded down to the CLOB size precision.
+    pub size: f64,
+    /// Market tick size (0.1, 0.01, 0.001 or 0.0001); the price is rounded to it.
+    pub tick_size: f64,
+    pub fee_rate_bps: u32,
+    pub nonce: u64,
+    /// UNIX seconds after which the order is void; 0 for none.
+    pub expiration: u64,
+    /// Whether the market settles through the neg-risk exchange.
+    pub neg_risk: bool,
+}
+
+/// Maker and taker amounts in 6-decimal token units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OrderAmounts {
+    pub maker_amount: u64,
+    pub taker_amount: u64,
+}
+
+/// Scale price and size into the amounts the exchange settles.
+///
+/// Buys give USDC (maker) for shares (taker); sells the reverse. The size is rounded down to
+/// two decimals and the price to the tick, so the USDC amount is exact at tick precision + 2.
+pub fn order_amounts(
+    side: Side,
+    price: f64,
+    size: f64,
+    tick_size: f64,
+) -> ClientResult<OrderAmounts> {
+    if !(tick_size > 0.0 && tick_size < 1.0) {
+        return Err(ClientError::Config(format!(
+            "invalid tick size {tick_size}"
+        )));
+    }
+    let price_decimals = (-tick_size.log10()).round() as u32;
+    if price_decimals > TOKEN_DECIMALS - SIZE_DECIMALS {
+        return Err(ClientError::Config(format!(
+            "unsupported tick size {tick_size}"
+        )));
+    }
+
+    let tick_units = (tick_size * 10f64.powi(price_decimals as i32)).round() as u64;
+    let price_units =
+        ((price * 10f64.powi(price_decimals as i32)).round() as u64 / tick_units) * tick_units;
+    let max_price_units = 10u64.pow(price_decimals) - tick_units;
+    if price_units < tick_units || price_units > max_price_units {
+        return Err(ClientError::Config(format!(
+            "price {price} outside [{tick_size}, {}]",
+            1.0 - tick_size
+        )));
+    }
+
+    // Small epsilon so 0.29 * 100 does not floor to 28.
+    let size_units = (size * 10f64.powi(SIZE_DECIMALS as i32) + 1e-9).floor() as u64;
+    if size_units == 0 {
+        return Err(ClientError::Config(format!("size {size} rounds to zero")));
+    }
+
+    let shares = size_units * 10u64.pow(TOKEN_DECIMALS - SIZE_DECIMALS);
+    let notional =
+        size_units * price_units * 10u64.pow(TOKEN_DECIMALS - SIZE_DECIMALS - price_decimals);
+
+    Ok(match side {
+        Side::Buy => OrderAmounts {
+            maker_amount: notional,
+            taker_amount: shares,
+        },
+        Side::Sell => OrderAmounts {
+            maker_amount: shares,
+            taker_amount: notional,
+        },
+    })
+}
+
+/// Signed order in the JSON shape `POST /order` expects.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedOrder {
+    pub salt: u64,
+    pub maker: String,
+    pub signer: String,
+    pub taker: String,
+    pub token_id: String,
+    pub maker_amount: String,
+    pub taker_amount: String,
+    pub expiration: String,
+    pub nonce: String,
+    pub fee_rate_bps: String,
+    pub side: Side,
+    pub signature_type: u8,
+    pub signature: String,
+}
+
+/// Builds and signs CTF Exchange orders for one wallet setup.
+pub struct OrderBuilder {
+    signer: PrivateKeySigner,
+    chain_id: u64,
+    signature_type: SignatureType,
+    /// Address holding the funds; the signer itself for EOA wallets.
+    maker: Address,
+}
+
+impl OrderBuilder {
+    /// `funder` is required for proxy and Safe wallets and ignored for EOA.
+    pub fn new(
+        signer: PrivateKeySigner,
+        chain_id: u64,
+        signature_type: SignatureType,
+        funder: Option<&str>,
+    ) -> ClientResult<Self> {
+        let maker = match signature_type {
+            SignatureType::Eoa => signer.address(),
+            SignatureType::PolyProxy | SignatureType::PolyGnosisSafe => funder
+                .filter(|f| !f.is_empty())
+                .ok_or_else(|| {
+                    ClientError::Config(format!(
+                        "{signature_type:?} orders need gnosis_safe_address as funder"
+                    ))
+                })?
+                .parse()
+                .map_err(|e| ClientError::Config(format!("invalid funder address: {e}")))?,
+        };
+        exchange_address(chain_id, false)?;
+
+        Ok(Self {
+            signer,
+            chain_id,
+            signature_type,
+            maker,
+        })
+    }
+
+    /// Build the builder from `[api]`, using `gnosis_safe_address` as the funder.
+    pub fn from_config(config: &ApiConfig) -> ClientResult<Self> {
+        let signer = build_signer_from_config(config, config.chain_id)?;
+        Self::new(
+            signer,
+            config.chain_id,
+            config.signature_type,
+            config.gnosis_safe_address.as_deref(),
+        )
+    }
+
+    /// Build and sign an order with a fresh random salt.
+    pub async fn create_order(&self, args: &OrderArgs) -> ClientResult<SignedOrder> {
+        self.sign_order(args, rand::random::<u32>() as u64).await
+    }
+
+    /// Build and sign an order with the given salt.
+    pub async fn sign_order(&self, args: &OrderArgs, salt: u64) -> ClientResult<SignedOrder> {
+        let amounts = order_amounts(args.side, args.price, args.size, args.tick_size)?;
+        let token_id: U256 = args
+            .token_id
+            .parse()
+            .map_err(|e| ClientError::Config(format!("invalid token id {}: {e}", args.token_id)))?;
+
+        let domain = eip712_domain! {
+            name: EXCHANGE_DOMAIN_NAME,
+            version: EXCHANGE_VERSION,
+            chain_id: self.chain_id,
+            verifying_contract: exchange_address(self.chain_id, args.neg_risk)?,
+        };
+
+        let payload = Order {
+            salt: U256::from(salt),
+            maker: self.maker,
+            signer: self.signer.address(),
+            taker: Address::ZERO,
+            tokenId: token_id,
+            makerAmount: U256::from(amounts.maker_amount),
+            takerAmount: U256::from(amounts.taker_amount),
+            expiration: U256::from(args.expiration),
+            nonce: U256::from(args.nonce),
+            feeRateBps: U256::from(args.fee_rate_bps),
+            side: args.side.as_u8(),
+            signatureType: self.signature_type.as_u8(),
+        };
+
+        let sig = self
+            .signer
+            .sign_typed_data(&payload, &domain)
+            .await
+            .map_err(|e| ClientError::Eip712(e.to_string()))?;
+
+        Ok(SignedOrder {
+            salt,
+            maker: self.maker.to_checksum(None),
+            signer: self.signer.address().to_checksum(None),
+            taker: Address::ZERO.to_checksum(None),
+            token_id: args.token_id.clone(),
+            maker_amount: amounts.maker_amount.to_string(),
+            taker_amount: amounts.taker_amount.to_string(),
+            expiration: args.expiration.to_string(),
+            nonce: args.nonce.to_string(),
+            fee_rate_bps: args.fee_rate_bps.to_string(),
+            side: args.side,
+            signature_type: self.signature_type.as_u8(),
+            signature: sig.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Signature;
+    use alloy_sol_types::SolStruct;
+
+    /// First Hardhat/Anvil dev account.
+    const TEST_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const TEST_ADDRESS: Address = address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+    const SAFE: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+
+    fn args(side: Side) -> OrderArgs {
+        OrderArgs {
+            token_id: "1234".to_string(),
+            side,
+            price: 0.5,
+            size: 100.0,
+            tick_size: 0.01,
+            fee_rate_bps: 100,
+            nonce: 0,
+            expiration: 0,
+            neg_risk: false,
+        }
+    }
+
+    #[test]
+    fn scales_amounts_to_token_units() {
+        assert_eq!(
+            order_amounts(Side::Buy, 0.5, 100.0, 0.01).unwrap(),
+            OrderAmounts {
+                maker_amount: 50_000_000,
+                taker_amount: 100_000_000
+            }
+        );
+        assert_eq!(
+            order_amounts(Side::Sell, 0.5, 100.0, 0.01).unwrap(),
+            OrderAmounts {
+                maker_amount: 100_000_000,
+                taker_amount: 50_000_000
+            }
+        );
+        // Size floors to 0.01 shares, price rounds to the tick.
+        assert_eq!(
+            order_amounts(Side::Sell, 0.1234, 10.567, 0.001).unwrap(),
+            OrderAmounts {
+                maker_amount: 10_560_000,
+                taker_amount: 1_298_880
+            }
+        );
+        assert_eq!(
+            order_amounts(Side::Buy, 0.29, 0.29, 0.01).unwrap(),
+            OrderAmounts {
+                maker_amount: 84_100,
+                taker_amount: 290_000
+            }
+        );
+        assert_eq!(
+            order_amounts(Side::Buy, 0.0567, 3.0, 0.0001).unwrap(),
+            OrderAmounts {
+                maker_amount: 170_100,
+                taker_amount: 3_000_000
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_unscalable_orders() {
+        assert!(order_amounts(Side::Buy, 0.0, 10.0, 0.01).is_err());
+        assert!(order_amounts(Side::Buy, 0.995, 10.0, 0.01).is_err());
+        assert!(order_amounts(Side::Buy, 0.5, 0.004, 0.01).is_err());
+        assert!(order_amounts(Side::Buy, 0.5, 10.0, 0.00001).is_err());
+    }
+
+    #[test]
+    fn order_type_matches_exchange_contract() {
+        assert_eq!(
+            Order::eip712_encode_type(),
+            "Order(uint256 salt,address maker,address signer,address taker,uint256 tokenId,\
+             uint256 makerAmount,uint256 takerAmount,uint256 expiration,uint256 nonce,\
+             uint256 feeRateBps,uint8 side,uint8 signatureType)"
+        );
+    }
+
+    #[tokio::test]
+    async fn l1_signature_recovers_wallet_address() {
+        let signer: PrivateKeySigner = TEST_KEY.parse().unwrap();
+        let sig = build_clob_eip712_signature(&signer, 137, 1_700_000_000, 7)
+            .await
+            .unwrap();
+
+        let payload = ClobAuth {
+            address: TEST_ADDRESS,
+            timestamp: "1700000000".into(),
+            nonce: U256::from(7),
+            message: MSG_TO_SIGN.into(),
+        };
+        let domain = eip712_domain! {
+            name: CLOB_DOMAIN_NAME,
+            version: CLOB_VERSION,
+            chain_id: 137,
+        };
+        let sig: Signature = sig.parse().unwrap();
+        let recovered = sig
+            .recover_address_from_prehash(&payload.eip712_signing_hash(&domain))
+            .unwrap();
+        assert_eq!(recovered, TEST_ADDRESS);
+    }
+
+    #[tokio::test]
+    async fn signs_eoa_order_with_known_vector() {
+        let signer: PrivateKeySigner = TEST_KEY.parse().unwrap();
+        assert_eq!(signer.address(), TEST_ADDRESS);
+        let builder = OrderBuilder::new(signer, 137, SignatureType::Eoa, None).unwrap();
+
+        let order = builder
+            .sign_order(&args(Side::Buy), 479_249_096_354)
+            .await
+            .unwrap();
+        assert_eq!(order.maker, TEST_ADDRESS.to_checksum(None));
+        assert_eq!(order.signer, order.maker);
+        assert_eq!(order.maker_amount, "50000000");
+        assert_eq!(order.taker_amount, "100000000");
+        assert_eq!(order.signature_type, 0);
+        assert_eq!(
+            order.signature,
+            "0xe68947be74ccd1248f13e9e10c3c0dd1cd95a054404937fba45a0c0979b084b4\
+             33076c5a5e48d3fda3122ef5fe973a1be0e61eaed73933933276dae638fa84091b"
+        );
+
+        let json = serde_json::to_value(&order).unwrap();
+        assert_eq!(json["side"], "BUY");
+        assert_eq!(json["tokenId"], "1234");
+        assert_eq!(json["feeRateBps"], "100");
+    }
+
+    #[tokio::test]
+    async fn signature_recovers_signer_for_safe_orders() {
+        let signer: PrivateKeySigner = TEST_KEY.parse().unwrap();
+        assert!(
+            OrderBuilder::new(signer.clone(), 137, SignatureType::PolyGnosisSafe, None).is_err()
+        );
+        let builder =
+            OrderBuilder::new(signer, 137, SignatureType::PolyGnosisSafe, Some(SAFE)).unwrap();
+
+        let args = OrderArgs {
+            neg_risk: true,
+            ..args(Side::Sell)
+        };
+        let order = builder.sign_order(&args, 1).await.unwrap();
+        assert_eq!(order.maker, SAFE);
+        assert_eq!(order.signer, TEST_ADDRESS.to_checksum(None));
+        assert_eq!(order.signature_type, 2);
+
+        let payload = Order {
+            salt: U256::from(1),
+            maker: SAFE.parse().unwrap(),
+            signer: TEST_ADDRESS,
+            taker: Address::ZERO,
+            tokenId: U256::from(1234),
+            makerAmount: U256::from(100_000_000u64),
+            takerAmount: U256::from(50_000_000u64),
+            expiration: U256::ZERO,
+            nonce: U256::ZERO,
+            feeRateBps: U256::from(100),
+            side: 1,
+            signatureType: 2,
+        };
+        let domain = eip712_domain! {
+            name: EXCHANGE_DOMAIN_NAME,
+            version: EXCHANGE_VERSION,
+            chain_id: 137,
+            verifying_contract: exchange_address(137, true).unwrap(),
+        };
+        let sig: Signature = order.signature.parse().unwrap();
+        let recovered = sig
+            .recover_address_from_prehash(&payload.eip712_signing_hash(&domain))
+            .unwrap();
+        assert_eq!(recovered, TEST_ADDRESS);
+    }
+}
@@ -1,16 +1,42 @@
+use std::fmt;
+use std::sync::Arc;
 use std::time::Duration;
-use reqwest::{Client, Method};
+use alloy::signers::local::PrivateKeySigner;
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
 use serde::de::DeserializeOwned;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tokio::time::sleep;
+use tracing::{info, warn};
 
+use crate::monitoring::metrics::METRICS;
 use crate::types::ApiConfig;
 
-use super::auth::{build_poly_hmac_signature, current_unix_timestamp};
+use super::auth::{build_clob_eip712_signature, build_poly_hmac_signature};
+use super::clock::{Clock, ServerTime, SystemClock};
+use super::rate_limit::{retry_after, EndpointClass, RateLimiter};
 use super::{ClientError, ClientResult};
 
 const DEFAULT_MAX_RETRIES: u32 = 3;
 
+/// L2 API credentials the CLOB issues for a wallet.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiCredentials {
+    pub api_key: String,
+    pub secret: String,
+    pub passphrase: String,
+}
+
+impl fmt::Debug for ApiCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApiCredentials")
+            .field("api_key", &self.api_key)
+            .field("secret", &"<redacted>")
+            .field("passphrase", &"<redacted>")
+            .finish()
+    }
+}
+
 pub struct ClobClient {
     http: Client,
     base_url: String,
@@ -18,16 +44,14 @@ pub struct ClobClient {
     api_key: String,
     api_secret: String,
     api_passphrase: String,
+    chain_id: u64,
     max_retries: u32,
+    limiter: RateLimiter,
+    time: ServerTime,
 }
 
 impl ClobClient {
     pub fn new(config: &ApiConfig) -> ClientResult<Self> {
-        let http = Client::builder()
-            .user_agent("polymarket-hft-bot/0.1")
-            .build()
-            .map_err(ClientError::Http)?;
-
         let address = if let Some(addr) = &config.gnosis_safe_address {
             if !addr.is_empty() {
                 addr.clone()
@@ -41,6 +65,31 @@ impl ClobClient {
                 "gnosis_safe_address must be configured for L2 auth".to_string(),
             ));
         };
+        if [&config.api_key, &config.api_secret, &config.api_passphrase]
+            .iter()
+            .any(|v| v.is_empty())
+        {
+            return Err(ClientError::Config(
+                "api_key, api_secret and api_passphrase must be set for L2 auth \
+                 (the api-key command creates them)"
+                    .to_string(),
+            ));
+        }
+
+        Self::with_address(config, address)
+    }
+
+    /// Client for public and L1-authenticated endpoints, such as creating API keys for a
+    /// wallet that has none yet. It needs no L2 credentials or funder address.
+    pub fn unauthenticated(config: &ApiConfig) -> ClientResult<Self> {
+        Self::with_address(config, String::new())
+    }
+
+    fn with_address(config: &ApiConfig, address: String) -> ClientResult<Self> {
+        let http = Client::builder()
+            .user_agent("polymarket-hft-bot/0.1")
+            .build()
+            .map_err(ClientError::Http)?;
 
         Ok(Self {
             http,
@@ -49,12 +98,44 @@ impl ClobClient {
             api_key: config.api_key.clone(),
             api_secret: config.api_secret.clone(),
             api_passphrase: config.api_passphrase.clone(),
+            chain_id: config.chain_id,
             max_retries: DEFAULT_MAX_RETRIES,
+            limiter: RateLimiter::new(&config.rate_limit),
+            time: ServerTime::new(Arc::new(SystemClock)),
         })
     }
 
+    /// Use `clock` as the local time source instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.time = ServerTime::new(clock);
+        self
+    }
+
+    /// Venue-corrected UNIX seconds for L1 and L2 auth headers.
+    pub fn timestamp(&self) -> i64 {
+        self.time.timestamp()
+    }
+
+    /// Measure the offset between the local clock and the CLOB's `/time`; every signed
+    /// request after this uses the corrected time.
+    pub async fn sync_time(&self) -> ClientResult<chrono::Duration> {
+        let sent = self.time.local_now();
+        let server_ts: i64 = self.get_public("/time").await?;
+        let received = self.time.local_now();
+        let offset = self.time.update(server_ts, sent, received);
+        info!(
+            target: "execution",
+            server_ts,
+            offset_ms = offset.num_milliseconds(),
+            round_trip_ms = (received - sent).num_milliseconds(),
+            "synced clock with CLOB server time"
+        );
+        Ok(offset)
+    }
+
+    /// Full URL for `path` and the request path to sign, which excludes any query string.
     fn build_url_and_path(&self, path: &str) -> (String, String) {
-        let request_path = if path.starts_with('/') {
+        let path = if path.starts_with('/') {
             path.to_string()
         } else {
             format!("/{path}")
@@ -63,12 +144,54 @@ impl ClobClient {
         let url = format!(
             "{}/{}",
             self.base_url.trim_end_matches('/'),
-            request_path.trim_start_matches('/')
+            path.trim_start_matches('/')
         );
+        let request_path = match path.split_once('?') {
+            Some((request_path, _)) => request_path.to_string(),
+            None => path,
+        };
 
         (url, request_path)
     }
 
+    /// Add L2 auth headers signed at the current (venue-corrected) time.
+    fn sign(
+        &self,
+        req: RequestBuilder,
+        method: &Method,
+        request_path: &str,
+        body: Option<&str>,
+    ) -> ClientResult<RequestBuilder> {
+        let timestamp = self.time.timestamp();
+        let signature = build_poly_hmac_signature(
+            &self.api_secret,
+            timestamp,
+            method.as_str(),
+            request_path,
+            body,
+        )?;
+        Ok(req
+            .header("POLY_ADDRESS", &self.address)
+            .header("POLY_SIGNATURE", signature)
+            .header("POLY_TIMESTAMP", timestamp.to_string())
+            .header("POLY_API_KEY", &self.api_key)
+            .header("POLY_PASSPHRASE", &self.api_passphrase))
+    }
+
+    /// Parse a successful response, or turn any other status into an error.
+    async fn read_response<TResp>(resp: Response) -> ClientResult<TResp>
+    where
+        TResp: DeserializeOwned,
+    {
+        if resp.status().is_success() {
+            Ok(resp.json::<TResp>().await?)
+        } else {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            Err(ClientError::HttpStatus { status, body })
+        }
+    }
+
     fn backoff(attempt: u32) -> Duration {
         let capped = attempt.min(5);
         let millis = 500 * (1_u64 << capped);
@@ -86,7 +209,7 @@ impl ClobClient {
         TResp: DeserializeOwned,
     {
         let (url, request_path) = self.build_url_and_path(path);
-        let timestamp = current_unix_timestamp();
+        let class = EndpointClass::of(&method, &request_path);
 
         let body_json = if let Some(body) = body {
             Some(serde_json::to_string(body)?)
@@ -94,24 +217,18 @@ impl ClobClient {
             None
         };
 
-        let signature = build_poly_hmac_signature(
-            &self.api_secret,
-            timestamp,
-            method.as_str(),
-            &request_path,
-            body_json.as_deref(),
-        )?;
-
         let mut attempt = 0;
+        let mut resynced = false;
         loop {
-            let mut req = self
-                .http
-                .request(method.clone(), &url)
-                .header("POLY_ADDRESS", &self.address)
-                .header("POLY_SIGNATURE", &signature)
-                .header("POLY_TIMESTAMP", timestamp.to_string())
-                .header("POLY_API_KEY", &self.api_key)
-                .header("POLY_PASSPHRASE", &self.api_passphrase);
+            self.limiter.acquire(class).await;
+            // Signed per attempt: backoff and rate-limit waits can outlast the venue's
+            // timestamp window.
+            let mut req = self.sign(
+                self.http.request(method.clone(), &url),
+                &method,
+                &request_path,
+                body_json.as_deref(),
+            )?;
 
             if let Some(body) = &body_json {
                 req = req
@@ -126,6 +243,35 @@ impl ClobClient {
                         return Ok(parsed);
                     }
 
+                    if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+                        // Hold back the whole endpoint class, not just this request.
+                        let delay = retry_after(resp.headers())
+                            .unwrap_or_else(|| Self::backoff(attempt + 1));
+                        METRICS.record_rate_limited(class.as_str(), delay);
+                        self.limiter.pause(class, delay);
+                        if attempt < self.max_retries {
+                            warn!(
+                                target: "execution",
+                                path = %request_path,
+                                retry_after_ms = delay.as_millis() as u64,
+                                "rate limited by CLOB; retrying"
+                            );
+                            attempt += 1;
+                            continue;
+                        }
+                    }
+
+                    // A refused signature may be clock skew; resync once and try again.
+                    if resp.status() == StatusCode::UNAUTHORIZED && !resynced {
+                        resynced = true;
+                        match self.sync_time().await {
+                            Ok(_) => continue,
+                            Err(err) => {
+                                warn!(target: "execution", error = %err, "clock resync failed");
+                            }
+                        }
+                    }
+
                     if resp.status().is_server_error() && attempt < self.max_retries {
                         attempt += 1;
                         sleep(Self::backoff(attempt)).await;
@@ -169,21 +315,228 @@ impl ClobClient {
         self.send_signed_request(Method::POST, path, Some(body)).await
     }
 
+    /// Signed DELETE; the body (if any) is part of the HMAC message like for POST.
+    pub async fn delete_private<TBody, TResp>(
+        &self,
+        path: &str,
+        body: Option<&TBody>,
+    ) -> ClientResult<TResp>
+    where
+        TBody: Serialize + ?Sized,
+        TResp: DeserializeOwned,
+    {
+        self.send_signed_request(Method::DELETE, path, body).await
+    }
+
     /// Simple helper for public GET endpoints that do not require auth.
     pub async fn get_public<TResp>(&self, path: &str) -> ClientResult<TResp>
     where
         TResp: DeserializeOwned,
     {
-        let (url, _) = self.build_url_and_path(path);
+        let (url, request_path) = self.build_url_and_path(path);
+        self.limiter
+            .acquire(EndpointClass::of(&Method::GET, &request_path))
+            .await;
         let resp = self.http.get(url).send().await?;
-        if resp.status().is_success() {
-            let parsed = resp.json::<TResp>().await?;
-            Ok(parsed)
-        } else {
-            let status = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            Err(ClientError::HttpStatus { status, body })
+        Self::read_response(resp).await
+    }
+
+    /// Request authenticated with L1 headers: an EIP-712 signature by the wallet itself.
+    async fn send_l1_request<TResp>(
+        &self,
+        method: Method,
+        path: &str,
+        signer: &PrivateKeySigner,
+        nonce: u64,
+    ) -> ClientResult<TResp>
+    where
+        TResp: DeserializeOwned,
+    {
+        let (url, request_path) = self.build_url_and_path(path);
+        self.limiter
+            .acquire(EndpointClass::of(&method, &request_path))
+            .await;
+        let timestamp = self.time.timestamp();
+        let signature =
+            build_clob_eip712_signature(signer, self.chain_id, timestamp, nonce).await?;
+        let resp = self
+            .http
+            .request(method, url)
+            .header("POLY_ADDRESS", signer.address().to_checksum(None))
+            .header("POLY_SIGNATURE", signature)
+            .header("POLY_TIMESTAMP", timestamp.to_string())
+            .header("POLY_NONCE", nonce.to_string())
+            .send()
+            .await?;
+        Self::read_response(resp).await
+    }
+
+    /// Create new L2 API credentials for `signer`'s wallet, bound to `nonce`.
+    pub async fn create_api_key(
+        &self,
+        signer: &PrivateKeySigner,
+        nonce: u64,
+    ) -> ClientResult<ApiCredentials> {
+        self.send_l1_request(Method::POST, "/auth/api-key", signer, nonce)
+            .await
+    }
+
+    /// Recover the L2 API credentials previously created for `signer`'s wallet and `nonce`.
+    pub async fn derive_api_key(
+        &self,
+        signer: &PrivateKeySigner,
+        nonce: u64,
+    ) -> ClientResult<ApiCredentials> {
+        self.send_l1_request(Method::GET, "/auth/derive-api-key", signer, nonce)
+            .await
+    }
+
+    /// Create credentials for `nonce`, or derive them if the wallet already has a key for it.
+    pub async fn create_or_derive_api_key(
+        &self,
+        signer: &PrivateKeySigner,
+        nonce: u64,
+    ) -> ClientResult<ApiCredentials> {
+        match self.create_api_key(signer, nonce).await {
+            Ok(creds) => Ok(creds),
+            Err(ClientError::HttpStatus { status, .. }) if status.is_client_error() => {
+                self.derive_api_key(signer, nonce).await
+            }
+            Err(err) => Err(err),
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicI64, Ordering};
+
+    use chrono::{DateTime, Utc};
+
+    use crate::types::{RateLimitConfig, SignatureType};
+
+    /// Clock the test moves by hand.
+    struct ManualClock(AtomicI64);
+
+    impl Clock for ManualClock {
+        fn now(&self) -> DateTime<Utc> {
+            DateTime::from_timestamp(self.0.load(Ordering::Relaxed), 0).unwrap()
+        }
+    }
+
+    fn client(clock: Arc<dyn Clock>) -> ClobClient {
+        let config = ApiConfig {
+            base_url: "https://clob.example".to_string(),
+            ws_url: "wss://ws.example/ws/market".to_string(),
+            user_ws_url: None,
+            api_key: "key".to_string(),
+            api_secret: "c2VjcmV0".to_string(),
+            api_passphrase: "pass".to_string(),
+            wallet_private_key: String::new(),
+            gnosis_safe_address: Some("0xsafe".to_string()),
+            chain_id: 137,
+            signature_type: SignatureType::PolyGnosisSafe,
+            fee_rate_bps: 0,
+            rate_limit: RateLimitConfig::default(),
+        };
+        ClobClient::new(&config).unwrap().with_clock(clock)
+    }
+
+    fn signed_headers(client: &ClobClient) -> (String, String) {
+        let req = client
+            .sign(
+                client.http.post("https://clob.example/order"),
+                &Method::POST,
+                "/order",
+                Some("{}"),
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        let header = |name| req.headers()[name].to_str().unwrap().to_string();
+        (header("POLY_TIMESTAMP"), header("POLY_SIGNATURE"))
+    }
+
+    #[test]
+    fn credentials_parse_and_redact_secrets() {
+        let creds: ApiCredentials = serde_json::from_str(
+            r#"{"apiKey":"11111111-2222-3333-4444-555555555555","secret":"c2VjcmV0","passphrase":"pp"}"#,
+        )
+        .unwrap();
+        assert_eq!(creds.api_key, "11111111-2222-3333-4444-555555555555");
+        assert_eq!(creds.secret, "c2VjcmV0");
+        let debug = format!("{creds:?}");
+        assert!(debug.contains(&creds.api_key));
+        assert!(!debug.contains("c2VjcmV0") && !debug.contains("\"pp\""));
+    }
+
+    #[test]
+    fn each_attempt_is_signed_at_the_current_server_time() {
+        let clock = Arc::new(ManualClock(AtomicI64::new(1_700_000_000)));
+        let client = client(clock.clone());
+        let (ts, first) = signed_headers(&client);
+        assert_eq!(ts, "1700000000");
+
+        // A retry after backoff carries a new timestamp and signature.
+        clock.0.store(1_700_000_008, Ordering::Relaxed);
+        let (ts, retried) = signed_headers(&client);
+        assert_eq!(ts, "1700000008");
+        assert_ne!(first, retried);
+
+        // Skew measured against the venue shifts the signed timestamp.
+        let local = clock.now();
+        client.time.update(1_700_000_068, local, local);
+        assert_eq!(signed_headers(&client).0, "1700000068");
+        assert_eq!(client.timestamp(), 1_700_000_068);
+    }
+
+    #[tokio::test]
+    async fn retried_request_carries_a_fresh_timestamp() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let clock = Arc::new(ManualClock(AtomicI64::new(1_700_000_000)));
+        let mut client = client(clock.clone());
+        client.base_url = format!("http://{}", listener.local_addr().unwrap());
+
+        // Answers the first attempt with a 429 and the retry with an empty object, recording
+        // the POLY_TIMESTAMP of each; time moves on while the first one is served.
+        let server = tokio::spawn(async move {
+            let mut stamps = Vec::new();
+            for reply in [
+                "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\n",
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n",
+            ] {
+                let (mut conn, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = conn.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                }
+                let request = String::from_utf8_lossy(&request).to_lowercase();
+                let stamp = request
+                    .lines()
+                    .find_map(|line| line.strip_prefix("poly_timestamp: "))
+                    .unwrap()
+                    .trim()
+                    .to_string();
+                stamps.push(stamp);
+                clock.0.fetch_add(8, Ordering::Relaxed);
+                let response =
+                    format!("{reply}Content-Length: 2\r\nConnection: close\r\n\r\n{{}}");
+                conn.write_all(response.as_bytes()).await.unwrap();
+            }
+            stamps
+        });
+
+        let resp: serde_json::Value = client
+            .post_private("/order", &serde_json::json!({}))
+            .await
+            .unwrap();
+        assert_eq!(resp, serde_json::json!({}));
+        assert_eq!(server.await.unwrap(), ["1700000000", "1700000008"]);
+    }
+}
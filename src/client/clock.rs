@@ -0,0 +1,111 @@
+//! Time source for signed CLOB requests.
+//!
+//! L1 and L2 auth headers carry a timestamp the venue checks against its own clock, so a
+//! skewed host clock gets requests refused. [`ServerTime`] keeps the offset to the CLOB's
+//! `/time` and applies it to a pluggable [`Clock`], which tests replace with a fixed one.
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+
+/// Source of the local wall-clock time.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The host's system clock.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Local clock corrected by the last measured offset to the venue's clock.
+pub struct ServerTime {
+    clock: Arc<dyn Clock>,
+    offset_ms: AtomicI64,
+}
+
+impl ServerTime {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            offset_ms: AtomicI64::new(0),
+        }
+    }
+
+    /// Local time, unadjusted; used to time a sync round-trip.
+    pub fn local_now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// Best estimate of the venue's current time.
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now() + Duration::milliseconds(self.offset_ms.load(Ordering::Relaxed))
+    }
+
+    /// UNIX seconds for auth headers.
+    pub fn timestamp(&self) -> i64 {
+        self.now().timestamp()
+    }
+
+    /// Offset currently applied to the local clock.
+    pub fn offset(&self) -> Duration {
+        Duration::milliseconds(self.offset_ms.load(Ordering::Relaxed))
+    }
+
+    /// Record the venue's `server_ts` (UNIX seconds), read by a request sent at `sent` and
+    /// answered at `received` local time. The server is assumed to have read its clock half
+    /// way through the round-trip; returns the new offset.
+    pub fn update(
+        &self,
+        server_ts: i64,
+        sent: DateTime<Utc>,
+        received: DateTime<Utc>,
+    ) -> Duration {
+        let midpoint = sent + (received - sent) / 2;
+        // The venue reports whole seconds, so its clock is anywhere in the following second.
+        let server = DateTime::from_timestamp(server_ts, 500_000_000).unwrap_or(midpoint);
+        let offset = server - midpoint;
+        self.offset_ms
+            .store(offset.num_milliseconds(), Ordering::Relaxed);
+        offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedClock(DateTime<Utc>);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> DateTime<Utc> {
+            self.0
+        }
+    }
+
+    #[test]
+    fn applies_offset_measured_at_round_trip_midpoint() {
+        let local = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let time = ServerTime::new(Arc::new(FixedClock(local)));
+        assert_eq!(time.timestamp(), 1_700_000_000);
+
+        // Host clock 30 s behind; the request took 200 ms.
+        let offset = time.update(
+            1_700_000_030,
+            local - Duration::milliseconds(100),
+            local + Duration::milliseconds(100),
+        );
+        assert_eq!(offset, Duration::milliseconds(30_500));
+        assert_eq!(time.timestamp(), 1_700_000_030);
+
+        // Host clock ahead.
+        time.update(1_699_999_990, local, local);
+        assert_eq!(time.timestamp(), 1_699_999_990);
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! 15m markets use dynamic slugs: `{coin}-updown-15m-{round_start_unix}`.
 //! Round start is current time in seconds floored to 900-second (15 min) buckets.
+//! Once a round has closed, the same market row carries its result in `outcomePrices`.
 
 use serde::Deserialize;
 
@@ -9,9 +10,17 @@ use super::{ClientError, ClientResult};
 
 const GAMMA_API_BASE: &str = "https://gamma-api.polymarket.com";
 
+/// Length of a 15m round in seconds.
+pub const ROUND_SECS: i64 = 900;
+
 /// Current Unix timestamp floored to the start of the current 15-minute round (UTC).
 pub fn current_15m_round_ts() -> i64 {
-    chrono::Utc::now().timestamp() / 900 * 900
+    chrono::Utc::now().timestamp() / ROUND_SECS * ROUND_SECS
+}
+
+/// Unix timestamp of the start of the round following the one containing `ts`.
+pub fn next_15m_round_ts(ts: i64) -> i64 {
+    ts.div_euclid(ROUND_SECS) * ROUND_SECS + ROUND_SECS
 }
 
 /// Build the Gamma API slug for a 15m UP/DOWN market.
@@ -27,6 +36,8 @@ pub struct ResolvedMarket {
     pub slug: String,
     pub up_token_id: String,
     pub down_token_id: String,
+    /// Whether the market settles through the neg-risk CTF Exchange; orders are signed for it.
+    pub neg_risk: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -35,6 +46,65 @@ struct GammaMarketRow {
     clob_token_ids: Option<Vec<String>>,
     #[serde(default)]
     tokens: Option<Vec<GammaToken>>,
+    #[serde(default, rename = "negRisk")]
+    neg_risk: Option<bool>,
+    #[serde(default)]
+    closed: Option<bool>,
+    /// Final price per outcome once resolved, e.g. `["1", "0"]`; Gamma sends the list as a
+    /// JSON-encoded string.
+    #[serde(default, rename = "outcomePrices")]
+    outcome_prices: Option<serde_json::Value>,
+}
+
+impl GammaMarketRow {
+    /// Token IDs for Yes (index 0) and No (index 1), or None if the row lists neither form.
+    fn market(&self, slug: &str) -> Option<ResolvedMarket> {
+        let (up_token_id, down_token_id) = if let Some(ids) = &self.clob_token_ids {
+            if ids.len() >= 2 {
+                (ids[0].clone(), ids[1].clone())
+            } else {
+                return None;
+            }
+        } else if let Some(tokens) = &self.tokens {
+            let token = |outcome: &str| {
+                tokens
+                    .iter()
+                    .find(|t| t.outcome.eq_ignore_ascii_case(outcome))
+                    .map(|t| t.token_id.clone())
+            };
+            (token("yes")?, token("no")?)
+        } else {
+            return None;
+        };
+
+        Some(ResolvedMarket {
+            slug: slug.to_string(),
+            up_token_id,
+            down_token_id,
+            neg_risk: self.neg_risk.unwrap_or(false),
+        })
+    }
+
+    /// Index of the outcome that paid out (0 for Yes/UP, 1 for No/DOWN), once the market has
+    /// closed with a final result.
+    fn winner(&self) -> Option<usize> {
+        if self.closed != Some(true) {
+            return None;
+        }
+        let prices = match self.outcome_prices.as_ref()? {
+            serde_json::Value::String(encoded) => serde_json::from_str(encoded).ok()?,
+            other => other.clone(),
+        };
+        let prices: Vec<f64> = prices
+            .as_array()?
+            .iter()
+            .map(|p| match p {
+                serde_json::Value::String(s) => s.parse().ok(),
+                other => other.as_f64(),
+            })
+            .collect::<Option<_>>()?;
+        prices.iter().position(|p| *p >= 1.0 - 1e-9)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -43,12 +113,11 @@ struct GammaToken {
     outcome: String,
 }
 
-/// Fetch a single market by slug from the Gamma API.
-/// Returns token IDs for Yes (index 0) and No (index 1), or None if not found/invalid.
-pub async fn fetch_market_by_slug(
+/// Fetch the raw Gamma row of a market by slug; `None` if Gamma does not list it.
+async fn fetch_market_row(
     http: &reqwest::Client,
     slug: &str,
-) -> ClientResult<Option<ResolvedMarket>> {
+) -> ClientResult<Option<GammaMarketRow>> {
     let url = format!("{}/markets", GAMMA_API_BASE);
     let resp = http
         .get(&url)
@@ -63,33 +132,17 @@ pub async fn fetch_market_by_slug(
     }
 
     let rows: Vec<GammaMarketRow> = resp.json().await?;
-    let row = match rows.first() {
-        Some(r) => r,
-        None => return Ok(None),
-    };
-
-    let (up_token_id, down_token_id) = if let Some(ids) = &row.clob_token_ids {
-        if ids.len() >= 2 {
-            (ids[0].clone(), ids[1].clone())
-        } else {
-            return Ok(None);
-        }
-    } else if let Some(tokens) = &row.tokens {
-        let yes_id = tokens.iter().find(|t| t.outcome.eq_ignore_ascii_case("yes")).map(|t| t.token_id.clone());
-        let no_id = tokens.iter().find(|t| t.outcome.eq_ignore_ascii_case("no")).map(|t| t.token_id.clone());
-        match (yes_id, no_id) {
-            (Some(y), Some(n)) => (y, n),
-            _ => return Ok(None),
-        }
-    } else {
-        return Ok(None);
-    };
-
-    Ok(Some(ResolvedMarket {
-        slug: slug.to_string(),
-        up_token_id,
-        down_token_id,
-    }))
+    Ok(rows.into_iter().next())
+}
+
+/// Fetch a single market by slug from the Gamma API.
+/// Returns token IDs for Yes (index 0) and No (index 1), or None if not found/invalid.
+pub async fn fetch_market_by_slug(
+    http: &reqwest::Client,
+    slug: &str,
+) -> ClientResult<Option<ResolvedMarket>> {
+    let row = fetch_market_row(http, slug).await?;
+    Ok(row.and_then(|r| r.market(slug)))
 }
 
 /// Resolve the current 15m market for a coin and return token IDs.
@@ -99,7 +152,17 @@ pub async fn resolve_15m_market(
     coin: &str,
     logical_slug: &str,
 ) -> ClientResult<Option<ResolvedMarket>> {
-    let round_ts = current_15m_round_ts();
+    resolve_15m_market_at(http, coin, logical_slug, current_15m_round_ts()).await
+}
+
+/// Resolve the 15m market for a coin and an explicit round start (e.g. the next round,
+/// so token IDs can be fetched ahead of the boundary).
+pub async fn resolve_15m_market_at(
+    http: &reqwest::Client,
+    coin: &str,
+    logical_slug: &str,
+    round_ts: i64,
+) -> ClientResult<Option<ResolvedMarket>> {
     let slug = slug_15m(coin, round_ts);
     let mut market = fetch_market_by_slug(http, &slug).await?;
     if let Some(ref mut m) = market {
@@ -107,3 +170,57 @@ pub async fn resolve_15m_market(
     }
     Ok(market)
 }
+
+/// Result of the 15m round of a coin starting at `round_ts`: the round's market, named
+/// `logical_slug`, and its winning outcome (0 for UP, 1 for DOWN). `None` until the market has
+/// closed with a final result.
+pub async fn fetch_15m_result(
+    http: &reqwest::Client,
+    coin: &str,
+    logical_slug: &str,
+    round_ts: i64,
+) -> ClientResult<Option<(ResolvedMarket, usize)>> {
+    let row = fetch_market_row(http, &slug_15m(coin, round_ts)).await?;
+    Ok(row.and_then(|r| Some((r.market(logical_slug)?, r.winner()?))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_round_ts_rolls_to_boundary() {
+        assert_eq!(next_15m_round_ts(1_704_110_400), 1_704_111_300);
+        assert_eq!(next_15m_round_ts(1_704_110_401), 1_704_111_300);
+        assert_eq!(next_15m_round_ts(1_704_111_299), 1_704_111_300);
+    }
+
+    #[test]
+    fn reads_neg_risk_flag() {
+        let rows: Vec<GammaMarketRow> = serde_json::from_str(
+            r#"[{"clobTokenIds":["1","2"],"negRisk":true},{"clobTokenIds":["3","4"]}]"#,
+        )
+        .unwrap();
+        assert_eq!(rows[0].neg_risk, Some(true));
+        assert_eq!(rows[1].neg_risk, None);
+    }
+
+    #[test]
+    fn winner_is_read_once_the_market_closes() {
+        let rows: Vec<GammaMarketRow> = serde_json::from_str(
+            r#"[{"closed":true,"outcomePrices":"[\"0\", \"1\"]"},
+                {"closed":true,"outcomePrices":["1","0"]},
+                {"closed":false,"outcomePrices":"[\"0.97\", \"0.03\"]"},
+                {"closed":true,"outcomePrices":"[\"0.5\", \"0.5\"]"},
+                {"closed":true}]"#,
+        )
+        .unwrap();
+        let winners: Vec<_> = rows.iter().map(GammaMarketRow::winner).collect();
+        assert_eq!(winners, vec![Some(1), Some(0), None, None, None]);
+    }
+
+    #[test]
+    fn slug_is_lowercased() {
+        assert_eq!(slug_15m("BTC", 1_704_110_400), "btc-updown-15m-1704110400");
+    }
+}
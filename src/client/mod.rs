@@ -2,7 +2,9 @@ use thiserror::Error;
 
 pub mod auth;
 pub mod clob;
+pub mod clock;
 pub mod gamma;
+pub mod rate_limit;
 pub mod websocket;
 
 #[derive(Debug, Error)]
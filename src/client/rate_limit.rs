@@ -0,0 +1,226 @@
+//! Client-side rate limiting for CLOB REST calls.
+//!
+//! Each endpoint class gets a token bucket sized from `[api.rate_limit]`, so a burst of orders
+//! across markets is spread out before the venue starts refusing it. A 429 pauses the whole
+//! class for as long as the venue's `Retry-After` asks.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::Method;
+use tokio::time::sleep;
+
+use crate::monitoring::metrics::METRICS;
+use crate::types::{RateLimit, RateLimitConfig};
+
+/// Class of CLOB REST endpoint, each with its own limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EndpointClass {
+    Order,
+    Cancel,
+    Read,
+}
+
+impl EndpointClass {
+    /// Class of a request to `path` (without query string).
+    pub fn of(method: &Method, path: &str) -> Self {
+        let path = path.trim_end_matches('/');
+        if *method == Method::DELETE {
+            EndpointClass::Cancel
+        } else if *method == Method::POST && (path.ends_with("/order") || path.ends_with("/orders"))
+        {
+            EndpointClass::Order
+        } else {
+            EndpointClass::Read
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EndpointClass::Order => "order",
+            EndpointClass::Cancel => "cancel",
+            EndpointClass::Read => "read",
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            EndpointClass::Order => 0,
+            EndpointClass::Cancel => 1,
+            EndpointClass::Read => 2,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    per_sec: f64,
+    /// Tokens available; negative while requests are queued for tokens not yet refilled.
+    tokens: f64,
+    /// Time `tokens` is counted up to; in the future while paused by a 429.
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        let capacity = f64::from(limit.burst.max(1));
+        Self {
+            capacity,
+            per_sec: limit.per_sec.max(0.001),
+            tokens: capacity,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_sec).min(self.capacity);
+        self.updated = self.updated.max(now);
+    }
+
+    /// Take a token; returns how long the caller has to wait before using it.
+    fn reserve(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+        self.tokens -= 1.0;
+        let deficit = Duration::from_secs_f64((-self.tokens).max(0.0) / self.per_sec);
+        (self.updated + deficit).saturating_duration_since(now)
+    }
+
+    /// Stop handing out tokens until `until` and start from an empty bucket after it.
+    fn pause(&mut self, now: Instant, until: Instant) {
+        self.refill(now);
+        self.tokens = self.tokens.min(0.0);
+        // Nothing refills while paused.
+        self.updated = self.updated.max(until);
+    }
+}
+
+/// Token buckets for every endpoint class, shared by all requests of a client.
+#[derive(Debug)]
+pub struct RateLimiter {
+    buckets: [Mutex<TokenBucket>; 3],
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        let now = Instant::now();
+        Self {
+            buckets: [config.orders, config.cancels, config.reads]
+                .map(|limit| Mutex::new(TokenBucket::new(limit, now))),
+        }
+    }
+
+    fn bucket(&self, class: EndpointClass) -> std::sync::MutexGuard<'_, TokenBucket> {
+        self.buckets[class.index()]
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Wait until a request of `class` may go out.
+    pub async fn acquire(&self, class: EndpointClass) {
+        let wait = self.bucket(class).reserve(Instant::now());
+        if !wait.is_zero() {
+            METRICS.record_request_throttled(class.as_str(), wait);
+            sleep(wait).await;
+        }
+    }
+
+    /// Hold back every request of `class` for `delay`, as asked by a 429 response.
+    pub fn pause(&self, class: EndpointClass, delay: Duration) {
+        let now = Instant::now();
+        self.bucket(class).pause(now, now + delay);
+    }
+}
+
+/// Delay a 429 response asks for in `Retry-After`: seconds or an HTTP date.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<f64>() {
+        return (secs.is_finite() && secs >= 0.0).then(|| Duration::from_secs_f64(secs));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc);
+    Some((at - Utc::now()).to_std().unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn classifies_endpoints() {
+        assert_eq!(EndpointClass::of(&Method::POST, "/order"), EndpointClass::Order);
+        assert_eq!(EndpointClass::of(&Method::DELETE, "/order"), EndpointClass::Cancel);
+        assert_eq!(EndpointClass::of(&Method::DELETE, "/cancel-all"), EndpointClass::Cancel);
+        assert_eq!(EndpointClass::of(&Method::GET, "/data/orders"), EndpointClass::Read);
+    }
+
+    #[test]
+    fn bucket_allows_a_burst_then_spaces_requests() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(
+            RateLimit {
+                burst: 2,
+                per_sec: 10.0,
+            },
+            start,
+        );
+        assert_eq!(bucket.reserve(start), Duration::ZERO);
+        assert_eq!(bucket.reserve(start), Duration::ZERO);
+        // Queued callers wait one refill interval more each.
+        let third = bucket.reserve(start);
+        let fourth = bucket.reserve(start);
+        assert!((third.as_secs_f64() - 0.1).abs() < 1e-6);
+        assert!((fourth.as_secs_f64() - 0.2).abs() < 1e-6);
+
+        // Refilled tokens pay off the queue before a new burst is allowed.
+        let later = start + Duration::from_secs(1);
+        assert_eq!(bucket.reserve(later), Duration::ZERO);
+    }
+
+    #[test]
+    fn pause_holds_requests_until_retry_after() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(
+            RateLimit {
+                burst: 5,
+                per_sec: 10.0,
+            },
+            start,
+        );
+        bucket.pause(start, start + Duration::from_secs(2));
+        let wait = bucket.reserve(start + Duration::from_millis(500));
+        // Held until the pause ends, then one refill interval for the token it took.
+        assert!((wait.as_secs_f64() - 1.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn bucket_is_empty_when_a_pause_ends() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(
+            RateLimit {
+                burst: 5,
+                per_sec: 10.0,
+            },
+            start,
+        );
+        let end = start + Duration::from_secs(10);
+        bucket.pause(start, end);
+        // No burst builds up during the pause: the first request after it waits for a token.
+        let wait = bucket.reserve(end);
+        assert!((wait.as_secs_f64() - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("3"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(3)));
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+    }
+}
@@ -1,14 +1,20 @@
+use std::collections::BTreeSet;
 use std::sync::atomic::{AtomicU8, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use futures::{SinkExt, StreamExt};
+use serde::Serialize;
 use tokio::sync::mpsc;
 use tokio::time::interval;
 use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
 
 use super::{ClientError, ClientResult};
 
+/// Inbound events buffered for the consumer. When it falls this far behind, the connection
+/// task stops reading and the backlog stays on the socket.
+const INBOUND_CAPACITY: usize = 1024;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConnectionState {
     Connecting,
@@ -39,11 +45,151 @@ impl From<ConnectionState> for u8 {
     }
 }
 
+/// Polymarket WebSocket channel a connection subscribes to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Market,
+    /// Authenticated channel carrying our own order and trade events.
+    User,
+}
+
+impl Channel {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Channel::Market => "market",
+            Channel::User => "user",
+        }
+    }
+
+    /// Field listing the subscribed IDs: asset IDs on the market channel, condition IDs on the
+    /// user channel.
+    fn subscription_key(&self) -> &'static str {
+        match self {
+            Channel::Market => "assets_ids",
+            Channel::User => "markets",
+        }
+    }
+}
+
+/// L2 API credentials sent with the user channel subscription.
+#[derive(Clone, Serialize)]
+pub struct UserChannelAuth {
+    #[serde(rename = "apiKey")]
+    pub api_key: String,
+    pub secret: String,
+    pub passphrase: String,
+}
+
+/// Event delivered to the consumer of a `WebSocketConnection`.
+#[derive(Debug, Clone)]
+pub enum WsEvent {
+    /// Raw message received from the server.
+    Message(Message),
+    /// The socket dropped and a new one is up with all subscriptions replayed.
+    /// Any state derived from the stream (e.g. order books) should be reset.
+    Reconnected,
+}
+
+/// Shared set of active asset subscriptions, replayed on every successful connect.
+///
+/// Cloning is cheap; all clones refer to the same underlying set and connection.
+#[derive(Clone)]
+pub struct SubscriptionHandle {
+    channel: Channel,
+    auth: Option<Arc<UserChannelAuth>>,
+    assets: Arc<Mutex<BTreeSet<String>>>,
+    outbound_tx: mpsc::UnboundedSender<Message>,
+    state: Arc<AtomicU8>,
+}
+
+impl SubscriptionHandle {
+    /// Add assets to the subscription set and subscribe on the live socket, if connected.
+    pub fn subscribe<I>(&self, assets: I) -> ClientResult<()>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let added: Vec<String> = {
+            let mut set = self.assets.lock().unwrap_or_else(|e| e.into_inner());
+            assets.into_iter().filter(|a| set.insert(a.clone())).collect()
+        };
+        self.send_operation("subscribe", &added)
+    }
+
+    /// Remove assets from the subscription set and unsubscribe on the live socket, if connected.
+    pub fn unsubscribe<I>(&self, assets: I) -> ClientResult<()>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let removed: Vec<String> = {
+            let mut set = self.assets.lock().unwrap_or_else(|e| e.into_inner());
+            assets.into_iter().filter(|a| set.remove(a)).collect()
+        };
+        self.send_operation("unsubscribe", &removed)
+    }
+
+    /// Unsubscribe and immediately re-subscribe assets already in the set, prompting the venue
+    /// to send fresh `book` snapshots for them.
+    pub fn resubscribe<I>(&self, assets: I) -> ClientResult<()>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let present: Vec<String> = {
+            let set = self.assets.lock().unwrap_or_else(|e| e.into_inner());
+            assets.into_iter().filter(|a| set.contains(a)).collect()
+        };
+        self.send_operation("unsubscribe", &present)?;
+        self.send_operation("subscribe", &present)
+    }
+
+    /// Currently subscribed assets.
+    pub fn assets(&self) -> Vec<String> {
+        let set = self.assets.lock().unwrap_or_else(|e| e.into_inner());
+        set.iter().cloned().collect()
+    }
+
+    fn send_operation(&self, operation: &str, assets: &[String]) -> ClientResult<()> {
+        // While disconnected the change is only recorded; the next connect replays the full set.
+        let state = ConnectionState::from(self.state.load(Ordering::SeqCst));
+        if assets.is_empty() || state != ConnectionState::Connected {
+            return Ok(());
+        }
+        let mut payload = serde_json::json!({
+            "operation": operation,
+            "custom_feature_enabled": true
+        });
+        payload[self.channel.subscription_key()] = serde_json::json!(assets);
+        self.outbound_tx
+            .send(Message::Text(payload.to_string()))
+            .map_err(|e| ClientError::Config(format!("websocket task is not running: {e}")))
+    }
+
+    /// Initial subscription message sent right after a connection is established.
+    ///
+    /// The user channel always authenticates, even with no markets (which streams all of them).
+    fn initial_payload(&self) -> Option<Message> {
+        let set = self.assets.lock().unwrap_or_else(|e| e.into_inner());
+        let mut payload = match &self.auth {
+            Some(auth) => serde_json::json!({
+                "auth": auth.as_ref(),
+                "type": self.channel.as_str(),
+            }),
+            None if set.is_empty() => return None,
+            None => serde_json::json!({
+                "type": self.channel.as_str(),
+                "custom_feature_enabled": true
+            }),
+        };
+        payload[self.channel.subscription_key()] = serde_json::json!(set.iter().collect::<Vec<_>>());
+        Some(Message::Text(payload.to_string()))
+    }
+}
+
 pub struct WebSocketConnection {
     _url: String,
     outbound_tx: mpsc::UnboundedSender<Message>,
-    inbound_rx: mpsc::UnboundedReceiver<Message>,
+    inbound_rx: mpsc::Receiver<WsEvent>,
     state: Arc<AtomicU8>,
+    subscriptions: SubscriptionHandle,
 }
 
 impl WebSocketConnection {
@@ -51,10 +197,15 @@ impl WebSocketConnection {
         self.outbound_tx.clone()
     }
 
-    pub fn receiver(&mut self) -> &mut mpsc::UnboundedReceiver<Message> {
+    pub fn receiver(&mut self) -> &mut mpsc::Receiver<WsEvent> {
         &mut self.inbound_rx
     }
 
+    /// Handle to the subscription set owned by this connection.
+    pub fn subscriptions(&self) -> SubscriptionHandle {
+        self.subscriptions.clone()
+    }
+
     pub fn state(&self) -> ConnectionState {
         self.state.load(Ordering::SeqCst).into()
     }
@@ -63,13 +214,30 @@ impl WebSocketConnection {
 async fn handle_connection(
     url: &str,
     outbound_rx: &mut mpsc::UnboundedReceiver<Message>,
-    inbound_tx: &mpsc::UnboundedSender<Message>,
+    inbound_tx: &mpsc::Sender<WsEvent>,
     state: &Arc<AtomicU8>,
+    subscriptions: &SubscriptionHandle,
+    connected_before: &mut bool,
 ) -> ClientResult<()> {
     let (ws_stream, _) = connect_async(url).await?;
+    // Mark connected before reading the subscription set so that concurrent `subscribe` calls
+    // either land in the replayed set or are sent as incremental operations.
     state.store(ConnectionState::Connected.into(), Ordering::SeqCst);
 
     let (mut write, mut read) = ws_stream.split();
+
+    if let Some(initial) = subscriptions.initial_payload() {
+        if let Err(err) = write.send(initial).await {
+            state.store(ConnectionState::Reconnecting.into(), Ordering::SeqCst);
+            return Err(ClientError::WebSocket(err));
+        }
+    }
+    if *connected_before && inbound_tx.send(WsEvent::Reconnected).await.is_err() {
+        state.store(ConnectionState::Disconnected.into(), Ordering::SeqCst);
+        return Ok(());
+    }
+    *connected_before = true;
+
     let mut heartbeat = interval(Duration::from_secs(10));
 
     loop {
@@ -83,7 +251,7 @@ async fn handle_connection(
             maybe_msg = read.next() => {
                 match maybe_msg {
                     Some(Ok(msg)) => {
-                        if inbound_tx.send(msg).is_err() {
+                        if inbound_tx.send(WsEvent::Message(msg)).await.is_err() {
                             // receiver dropped; treat as graceful shutdown
                             state.store(ConnectionState::Disconnected.into(), Ordering::SeqCst);
                             return Ok(());
@@ -94,8 +262,11 @@ async fn handle_connection(
                         return Err(ClientError::WebSocket(err));
                     }
                     None => {
+                        // Server closed the stream; reconnect rather than treating it as shutdown.
                         state.store(ConnectionState::Reconnecting.into(), Ordering::SeqCst);
-                        return Ok(());
+                        return Err(ClientError::WebSocket(
+                            tokio_tungstenite::tungstenite::Error::ConnectionClosed,
+                        ));
                     }
                 }
             }
@@ -115,26 +286,59 @@ async fn handle_connection(
 /// - Maintains the TCP/WebSocket connection.
 /// - Sends `PING` heartbeats every 10 seconds.
 /// - Reconnects with exponential backoff if the connection drops.
+/// - Replays the full subscription set on every successful connect.
 ///
 /// The returned `WebSocketConnection` exposes:
-/// - A sender for outbound messages (e.g. subscription payloads).
-/// - A receiver for inbound messages.
+/// - A sender for raw outbound messages.
+/// - A subscription handle for adding/removing assets on `channel`.
+/// - A bounded receiver for inbound events, including `WsEvent::Reconnected` after a drop.
 /// - A connection state indicator.
-pub fn connect_with_retries(url: impl Into<String>) -> WebSocketConnection {
-    let url = url.into();
+pub fn connect_with_retries(url: impl Into<String>, channel: Channel) -> WebSocketConnection {
+    connect(url.into(), channel, None)
+}
+
+/// Connect to the authenticated user channel, which streams `order` and `trade` events for
+/// the API key's own orders. Reconnects and re-authenticates like [`connect_with_retries`].
+pub fn connect_user_channel(url: impl Into<String>, auth: UserChannelAuth) -> WebSocketConnection {
+    connect(url.into(), Channel::User, Some(Arc::new(auth)))
+}
+
+fn connect(
+    url: String,
+    channel: Channel,
+    auth: Option<Arc<UserChannelAuth>>,
+) -> WebSocketConnection {
     let (outbound_tx, mut outbound_rx) = mpsc::unbounded_channel();
-    let (inbound_tx, inbound_rx) = mpsc::unbounded_channel();
+    let (inbound_tx, inbound_rx) = mpsc::channel(INBOUND_CAPACITY);
     let state = Arc::new(AtomicU8::new(ConnectionState::Connecting.into()));
+    let subscriptions = SubscriptionHandle {
+        channel,
+        auth,
+        assets: Arc::new(Mutex::new(BTreeSet::new())),
+        outbound_tx: outbound_tx.clone(),
+        state: Arc::clone(&state),
+    };
 
     let url_for_task = url.clone();
     let state_clone = Arc::clone(&state);
+    let subscriptions_for_task = subscriptions.clone();
 
     tokio::spawn(async move {
         let mut attempt: u32 = 0;
+        let mut connected_before = false;
         loop {
             state_clone.store(ConnectionState::Connecting.into(), Ordering::SeqCst);
 
-            match handle_connection(&url_for_task, &mut outbound_rx, &inbound_tx, &state_clone).await {
+            match handle_connection(
+                &url_for_task,
+                &mut outbound_rx,
+                &inbound_tx,
+                &state_clone,
+                &subscriptions_for_task,
+                &mut connected_before,
+            )
+            .await
+            {
                 Ok(()) => {
                     state_clone.store(ConnectionState::Disconnected.into(), Ordering::SeqCst);
                     break;
@@ -155,6 +359,84 @@ pub fn connect_with_retries(url: impl Into<String>) -> WebSocketConnection {
         outbound_tx,
         inbound_rx,
         state,
+        subscriptions,
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handle(state: ConnectionState) -> (SubscriptionHandle, mpsc::UnboundedReceiver<Message>) {
+        let (outbound_tx, outbound_rx) = mpsc::unbounded_channel();
+        let handle = SubscriptionHandle {
+            channel: Channel::Market,
+            auth: None,
+            assets: Arc::new(Mutex::new(BTreeSet::new())),
+            outbound_tx,
+            state: Arc::new(AtomicU8::new(state.into())),
+        };
+        (handle, outbound_rx)
+    }
+
+    #[test]
+    fn subscriptions_are_recorded_while_disconnected() {
+        let (subs, mut outbound_rx) = handle(ConnectionState::Reconnecting);
+        subs.subscribe(["a".to_string(), "b".to_string()]).unwrap();
+        subs.unsubscribe(["a".to_string()]).unwrap();
+
+        assert!(outbound_rx.try_recv().is_err());
+        assert_eq!(subs.assets(), vec!["b".to_string()]);
+
+        match subs.initial_payload() {
+            Some(Message::Text(text)) => {
+                let v: serde_json::Value = serde_json::from_str(&text).unwrap();
+                assert_eq!(v["type"], "market");
+                assert_eq!(v["assets_ids"], serde_json::json!(["b"]));
+            }
+            other => panic!("unexpected initial payload: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn only_new_assets_are_sent_when_connected() {
+        let (subs, mut outbound_rx) = handle(ConnectionState::Connected);
+        subs.subscribe(["a".to_string()]).unwrap();
+        subs.subscribe(["a".to_string(), "b".to_string()]).unwrap();
+
+        let mut sent = Vec::new();
+        while let Ok(Message::Text(text)) = outbound_rx.try_recv() {
+            let v: serde_json::Value = serde_json::from_str(&text).unwrap();
+            assert_eq!(v["operation"], "subscribe");
+            sent.push(v["assets_ids"].clone());
+        }
+        assert_eq!(sent, vec![serde_json::json!(["a"]), serde_json::json!(["b"])]);
+    }
+
+    #[test]
+    fn user_channel_authenticates_without_markets() {
+        let (outbound_tx, _outbound_rx) = mpsc::unbounded_channel();
+        let subs = SubscriptionHandle {
+            channel: Channel::User,
+            auth: Some(Arc::new(UserChannelAuth {
+                api_key: "key".to_string(),
+                secret: "secret".to_string(),
+                passphrase: "pass".to_string(),
+            })),
+            assets: Arc::new(Mutex::new(BTreeSet::new())),
+            outbound_tx,
+            state: Arc::new(AtomicU8::new(ConnectionState::Connected.into())),
+        };
+
+        match subs.initial_payload() {
+            Some(Message::Text(text)) => {
+                let v: serde_json::Value = serde_json::from_str(&text).unwrap();
+                assert_eq!(v["type"], "user");
+                assert_eq!(v["auth"]["apiKey"], "key");
+                assert_eq!(v["auth"]["passphrase"], "pass");
+                assert_eq!(v["markets"], serde_json::json!([]));
+            }
+            other => panic!("unexpected initial payload: {other:?}"),
+        }
+    }
+}
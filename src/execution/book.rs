@@ -0,0 +1,488 @@
+//! Per-token L2 order books maintained from the market WebSocket channel.
+//!
+//! Books are seeded from `book` snapshots and kept current with `price_change` deltas. The
+//! market channel carries no sequence numbers, so event timestamps stand in for them: deltas
+//! older than the last snapshot are dropped. The `hash` the venue attaches to books and deltas
+//! covers fields beyond the price levels, so it cannot be recomputed here and is not checked.
+//! Instead, after every delta the top of book is cross-checked against the `best_bid`/`best_ask`
+//! the venue reports; on divergence (or a crossed book) the token is marked out of sync and
+//! stays that way until a fresh `book` snapshot arrives.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+
+use crate::client::gamma::ResolvedMarket;
+pub use crate::strategy::PriceLevel;
+use crate::strategy::{BookDepth, LegSide, MarketSnapshot};
+
+/// Prices are stored as integer keys at this scale (1e-4) so levels can be ordered exactly.
+const PRICE_SCALE: f64 = 10_000.0;
+
+/// Tolerance used when comparing our top of book with the venue's reported one.
+const PRICE_EPSILON: f64 = 1e-9;
+
+/// Trade prints kept per token, so the paper engine can catch up on every print since its last
+/// pass.
+const RECENT_TRADES: usize = 256;
+
+pub(crate) fn price_key(price: f64) -> u64 {
+    (price * PRICE_SCALE).round() as u64
+}
+
+pub(crate) fn key_price(key: u64) -> f64 {
+    key as f64 / PRICE_SCALE
+}
+
+/// Side of the book a level rests on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BookSide {
+    Bid,
+    Ask,
+}
+
+/// Most recent trade print for a token, as reported by `last_trade_price`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LastTrade {
+    pub price: f64,
+    pub size: f64,
+    /// Aggressor side, if reported.
+    pub side: Option<BookSide>,
+    pub ts: DateTime<Utc>,
+}
+
+/// Result of applying an incremental update to a `TokenBook`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeltaOutcome {
+    Applied,
+    /// The delta predates the current snapshot and was ignored.
+    Stale,
+    /// The book has no valid snapshot yet; the delta was ignored.
+    Unsynced,
+    /// Applying the delta left the book inconsistent; it is now out of sync.
+    Diverged,
+}
+
+/// L2 book for a single outcome token.
+#[derive(Clone, Debug, Default)]
+pub struct TokenBook {
+    bids: BTreeMap<u64, f64>,
+    asks: BTreeMap<u64, f64>,
+    synced: bool,
+    snapshot_ts_ms: i64,
+    last_update_ms: i64,
+    tick_size: Option<f64>,
+    recent_trades: VecDeque<LastTrade>,
+    /// Number of trades recorded so far; numbers each print.
+    trade_seq: u64,
+}
+
+impl TokenBook {
+    /// Replace the book with a full snapshot. Returns `false` if the snapshot is older than
+    /// the state we already hold and was ignored.
+    pub fn apply_snapshot<B, A>(&mut self, bids: B, asks: A, ts_ms: i64) -> bool
+    where
+        B: IntoIterator<Item = PriceLevel>,
+        A: IntoIterator<Item = PriceLevel>,
+    {
+        if self.synced && ts_ms < self.last_update_ms {
+            return false;
+        }
+
+        self.bids = bids
+            .into_iter()
+            .filter(|l| l.size > 0.0)
+            .map(|l| (price_key(l.price), l.size))
+            .collect();
+        self.asks = asks
+            .into_iter()
+            .filter(|l| l.size > 0.0)
+            .map(|l| (price_key(l.price), l.size))
+            .collect();
+        self.snapshot_ts_ms = ts_ms;
+        self.last_update_ms = ts_ms;
+        self.synced = !self.is_crossed();
+        true
+    }
+
+    /// Apply a `price_change` delta. `size` is the new aggregate size at `price`; zero removes
+    /// the level.
+    pub fn apply_delta(
+        &mut self,
+        side: BookSide,
+        price: f64,
+        size: f64,
+        ts_ms: i64,
+    ) -> DeltaOutcome {
+        if !self.synced {
+            return DeltaOutcome::Unsynced;
+        }
+        if ts_ms < self.snapshot_ts_ms {
+            return DeltaOutcome::Stale;
+        }
+
+        let levels = match side {
+            BookSide::Bid => &mut self.bids,
+            BookSide::Ask => &mut self.asks,
+        };
+        let key = price_key(price);
+        if size > 0.0 {
+            levels.insert(key, size);
+        } else {
+            levels.remove(&key);
+        }
+
+        self.last_update_ms = self.last_update_ms.max(ts_ms);
+
+        if self.is_crossed() {
+            self.synced = false;
+            return DeltaOutcome::Diverged;
+        }
+        DeltaOutcome::Applied
+    }
+
+    /// Cross-check our top of book against the venue's reported best bid/ask.
+    ///
+    /// Zero (or, for asks, >= 1) means the venue reported an empty side and is not checked.
+    /// On mismatch the book is marked out of sync and `false` is returned.
+    pub fn verify_top(&mut self, best_bid: f64, best_ask: f64) -> bool {
+        if !self.synced {
+            return false;
+        }
+
+        let bid_ok = best_bid <= 0.0
+            || self
+                .best_bid()
+                .is_some_and(|l| (l.price - best_bid).abs() < PRICE_EPSILON);
+        let ask_ok = best_ask <= 0.0
+            || best_ask >= 1.0
+            || self
+                .best_ask()
+                .is_some_and(|l| (l.price - best_ask).abs() < PRICE_EPSILON);
+
+        if !(bid_ok && ask_ok) {
+            self.synced = false;
+        }
+        self.synced
+    }
+
+    /// Mark the book as out of sync (e.g. after a reconnect); it will be rebuilt from the next
+    /// `book` snapshot.
+    pub fn invalidate(&mut self) {
+        self.synced = false;
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.synced
+    }
+
+    pub fn best_bid(&self) -> Option<PriceLevel> {
+        self.bids.iter().next_back().map(|(k, s)| PriceLevel {
+            price: key_price(*k),
+            size: *s,
+        })
+    }
+
+    pub fn best_ask(&self) -> Option<PriceLevel> {
+        self.asks.iter().next().map(|(k, s)| PriceLevel {
+            price: key_price(*k),
+            size: *s,
+        })
+    }
+
+    /// Top `n` levels on one side, best first.
+    pub fn levels(&self, side: BookSide, n: usize) -> Vec<PriceLevel> {
+        let to_level = |(k, s): (&u64, &f64)| PriceLevel {
+            price: key_price(*k),
+            size: *s,
+        };
+        match side {
+            BookSide::Bid => self.bids.iter().rev().take(n).map(to_level).collect(),
+            BookSide::Ask => self.asks.iter().take(n).map(to_level).collect(),
+        }
+    }
+
+    /// Aggregate size resting at exactly `price` on one side (0 if no level).
+    pub fn size_at(&self, side: BookSide, price: f64) -> f64 {
+        let levels = match side {
+            BookSide::Bid => &self.bids,
+            BookSide::Ask => &self.asks,
+        };
+        levels.get(&price_key(price)).copied().unwrap_or(0.0)
+    }
+
+    pub fn tick_size(&self) -> Option<f64> {
+        self.tick_size
+    }
+
+    pub fn set_tick_size(&mut self, tick_size: f64) {
+        self.tick_size = Some(tick_size);
+    }
+
+    pub fn last_trade(&self) -> Option<LastTrade> {
+        self.recent_trades.back().copied()
+    }
+
+    pub fn record_trade(&mut self, trade: LastTrade) {
+        if self.recent_trades.len() == RECENT_TRADES {
+            self.recent_trades.pop_front();
+        }
+        self.recent_trades.push_back(trade);
+        self.trade_seq += 1;
+    }
+
+    /// Number of trades recorded so far; pass it to `trades_since` to see only newer prints.
+    pub fn trade_seq(&self) -> u64 {
+        self.trade_seq
+    }
+
+    /// Trades recorded after the first `seq`, oldest first (as far back as they are kept).
+    pub fn trades_since(&self, seq: u64) -> impl Iterator<Item = &LastTrade> {
+        let first_kept = self.trade_seq - self.recent_trades.len() as u64;
+        let skip = seq.saturating_sub(first_kept) as usize;
+        self.recent_trades.iter().skip(skip)
+    }
+
+    fn is_crossed(&self) -> bool {
+        match (self.bids.keys().next_back(), self.asks.keys().next()) {
+            (Some(bid), Some(ask)) => bid >= ask,
+            _ => false,
+        }
+    }
+}
+
+/// Books for both outcome tokens of one UP/DOWN market.
+#[derive(Clone, Debug, Default)]
+pub struct MarketBook {
+    pub up: TokenBook,
+    pub down: TokenBook,
+}
+
+impl MarketBook {
+    pub fn side(&self, side: LegSide) -> &TokenBook {
+        match side {
+            LegSide::Up => &self.up,
+            LegSide::Down => &self.down,
+        }
+    }
+
+    pub fn side_mut(&mut self, side: LegSide) -> &mut TokenBook {
+        match side {
+            LegSide::Up => &mut self.up,
+            LegSide::Down => &mut self.down,
+        }
+    }
+
+    /// Build a normalized snapshot once both tokens are in sync and have a two-sided touch.
+    ///
+    /// `depth_levels` > 0 additionally captures that many levels per token and side.
+    pub fn snapshot(
+        &self,
+        market_slug: &str,
+        ts: DateTime<Utc>,
+        depth_levels: usize,
+    ) -> Option<MarketSnapshot> {
+        if !(self.up.is_synced() && self.down.is_synced()) {
+            return None;
+        }
+        let up_bid = self.up.best_bid()?;
+        let up_ask = self.up.best_ask()?;
+        let down_bid = self.down.best_bid()?;
+        let down_ask = self.down.best_ask()?;
+
+        let depth = (depth_levels > 0).then(|| BookDepth {
+            up_bids: self.up.levels(BookSide::Bid, depth_levels),
+            up_asks: self.up.levels(BookSide::Ask, depth_levels),
+            down_bids: self.down.levels(BookSide::Bid, depth_levels),
+            down_asks: self.down.levels(BookSide::Ask, depth_levels),
+        });
+
+        Some(MarketSnapshot {
+            ts,
+            market_slug: market_slug.to_string(),
+            up_bid: up_bid.price,
+            up_ask: up_ask.price,
+            down_bid: down_bid.price,
+            down_ask: down_ask.price,
+            up_bid_size: Some(up_bid.size),
+            up_ask_size: Some(up_ask.size),
+            down_bid_size: Some(down_bid.size),
+            down_ask_size: Some(down_ask.size),
+            depth,
+        })
+    }
+}
+
+/// All books tracked by the bot, keyed by market slug, plus the asset ID → market mapping.
+#[derive(Debug, Default)]
+pub struct OrderBooks {
+    asset_to_market: HashMap<String, (String, LegSide)>,
+    books_by_market: HashMap<String, MarketBook>,
+    depth_levels: usize,
+}
+
+impl OrderBooks {
+    /// Books whose snapshots carry `depth_levels` levels per side (0 = touch only).
+    pub fn new(depth_levels: usize) -> Self {
+        Self {
+            depth_levels,
+            ..Self::default()
+        }
+    }
+
+    /// Track a market, replacing any previous token IDs for the same slug.
+    ///
+    /// Returns the asset IDs that are no longer tracked (e.g. the expired round's tokens).
+    pub fn insert_market(&mut self, market: &ResolvedMarket) -> Vec<String> {
+        let stale: Vec<String> = self
+            .asset_to_market
+            .iter()
+            .filter(|(id, (slug, _))| {
+                *slug == market.slug && **id != market.up_token_id && **id != market.down_token_id
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &stale {
+            self.asset_to_market.remove(id);
+        }
+
+        self.asset_to_market
+            .insert(market.up_token_id.clone(), (market.slug.clone(), LegSide::Up));
+        self.asset_to_market
+            .insert(market.down_token_id.clone(), (market.slug.clone(), LegSide::Down));
+        if !stale.is_empty() || !self.books_by_market.contains_key(&market.slug) {
+            self.books_by_market
+                .insert(market.slug.clone(), MarketBook::default());
+        }
+        stale
+    }
+
+    /// Market slug and leg side an asset ID belongs to.
+    pub fn market_for_asset(&self, asset_id: &str) -> Option<&(String, LegSide)> {
+        self.asset_to_market.get(asset_id)
+    }
+
+    /// Book for a single token.
+    pub fn token(&self, asset_id: &str) -> Option<&TokenBook> {
+        let (slug, side) = self.asset_to_market.get(asset_id)?;
+        Some(self.books_by_market.get(slug)?.side(*side))
+    }
+
+    /// Mutable book for a single token, together with its market slug.
+    pub fn token_mut(&mut self, asset_id: &str) -> Option<(&str, &mut TokenBook)> {
+        let (slug, side) = self.asset_to_market.get(asset_id)?;
+        let book = self.books_by_market.get_mut(slug)?;
+        Some((slug.as_str(), book.side_mut(*side)))
+    }
+
+    pub fn market(&self, market_slug: &str) -> Option<&MarketBook> {
+        self.books_by_market.get(market_slug)
+    }
+
+    pub fn snapshot(&self, market_slug: &str, ts: DateTime<Utc>) -> Option<MarketSnapshot> {
+        self.books_by_market
+            .get(market_slug)?
+            .snapshot(market_slug, ts, self.depth_levels)
+    }
+
+    /// Invalidate every book, e.g. after the feed reconnects.
+    pub fn invalidate_all(&mut self) {
+        for book in self.books_by_market.values_mut() {
+            book.up.invalidate();
+            book.down.invalidate();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lvl(price: f64, size: f64) -> PriceLevel {
+        PriceLevel { price, size }
+    }
+
+    fn seeded() -> TokenBook {
+        let mut book = TokenBook::default();
+        assert!(book.apply_snapshot(
+            [lvl(0.48, 30.0), lvl(0.49, 20.0)],
+            [lvl(0.52, 25.0), lvl(0.51, 10.0)],
+            1_000,
+        ));
+        book
+    }
+
+    #[test]
+    fn snapshot_orders_levels() {
+        let book = seeded();
+        assert!(book.is_synced());
+        assert_eq!(book.best_bid(), Some(lvl(0.49, 20.0)));
+        assert_eq!(book.best_ask(), Some(lvl(0.51, 10.0)));
+        assert_eq!(
+            book.levels(BookSide::Bid, 5),
+            vec![lvl(0.49, 20.0), lvl(0.48, 30.0)]
+        );
+    }
+
+    #[test]
+    fn deltas_update_and_remove_levels() {
+        let mut book = seeded();
+        assert_eq!(
+            book.apply_delta(BookSide::Ask, 0.51, 0.0, 1_001),
+            DeltaOutcome::Applied
+        );
+        assert_eq!(book.best_ask(), Some(lvl(0.52, 25.0)));
+        assert!(book.verify_top(0.49, 0.52));
+
+        assert_eq!(
+            book.apply_delta(BookSide::Bid, 0.50, 5.0, 999),
+            DeltaOutcome::Stale
+        );
+        assert_eq!(book.best_bid(), Some(lvl(0.49, 20.0)));
+    }
+
+    #[test]
+    fn divergence_requires_new_snapshot() {
+        let mut book = seeded();
+        assert!(!book.verify_top(0.50, 0.51));
+        assert!(!book.is_synced());
+        assert_eq!(
+            book.apply_delta(BookSide::Bid, 0.47, 1.0, 1_002),
+            DeltaOutcome::Unsynced
+        );
+
+        assert!(book.apply_snapshot([lvl(0.50, 1.0)], [lvl(0.51, 1.0)], 1_003));
+        assert!(book.is_synced());
+        assert_eq!(
+            book.apply_delta(BookSide::Bid, 0.55, 1.0, 1_004),
+            DeltaOutcome::Diverged
+        );
+    }
+
+    #[test]
+    fn rollover_replaces_assets() {
+        let mut books = OrderBooks::default();
+        let first = ResolvedMarket {
+            slug: "BTC".to_string(),
+            up_token_id: "u1".to_string(),
+            down_token_id: "d1".to_string(),
+            neg_risk: false,
+        };
+        assert!(books.insert_market(&first).is_empty());
+
+        let next = ResolvedMarket {
+            slug: "BTC".to_string(),
+            up_token_id: "u2".to_string(),
+            down_token_id: "d2".to_string(),
+            neg_risk: false,
+        };
+        let mut stale = books.insert_market(&next);
+        stale.sort();
+        assert_eq!(stale, vec!["d1".to_string(), "u1".to_string()]);
+        assert!(books.market_for_asset("u1").is_none());
+        assert_eq!(
+            books.market_for_asset("d2"),
+            Some(&("BTC".to_string(), LegSide::Down))
+        );
+    }
+}
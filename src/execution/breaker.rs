@@ -0,0 +1,334 @@
+//! Circuit breakers that stop order flow to a market after repeated backend failures.
+//!
+//! Failures are counted separately per market and per failure class, so a run of rejected
+//! orders in one market does not stop another, and venue outages are told apart from auth
+//! problems. A tripped breaker stays open for a cooldown, then lets a single probe through:
+//! success closes it, failure opens it again.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use tracing::warn;
+
+use crate::client::ClientError;
+use crate::monitoring::metrics::METRICS;
+use crate::types::BreakerConfig;
+
+use super::executor::ExecutionError;
+
+/// Scope for calls that are not tied to one market, such as listing open orders.
+pub(crate) const ACCOUNT_SCOPE: &str = "*";
+
+/// Kind of backend failure a breaker counts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FailureClass {
+    /// Credentials or signing refused.
+    Auth,
+    /// Venue unavailable: 5xx responses, transport errors, unreadable replies.
+    Server,
+    /// The venue answered but refused the request.
+    Rejection,
+}
+
+impl FailureClass {
+    const ALL: [FailureClass; 3] = [
+        FailureClass::Auth,
+        FailureClass::Server,
+        FailureClass::Rejection,
+    ];
+
+    /// Class of a backend error; `None` for errors that say nothing about the venue's health.
+    pub fn of(err: &ExecutionError) -> Option<Self> {
+        match err {
+            ExecutionError::Client(ClientError::HttpStatus { status, .. }) => {
+                if status.as_u16() == 401 || status.as_u16() == 403 {
+                    Some(FailureClass::Auth)
+                } else if status.is_server_error() {
+                    Some(FailureClass::Server)
+                } else {
+                    Some(FailureClass::Rejection)
+                }
+            }
+            ExecutionError::Client(
+                ClientError::Hmac(_) | ClientError::Eip712(_) | ClientError::Config(_),
+            ) => Some(FailureClass::Auth),
+            ExecutionError::Client(_) => Some(FailureClass::Server),
+            ExecutionError::Other(_) => Some(FailureClass::Rejection),
+            ExecutionError::Config(_)
+            | ExecutionError::CircuitOpen
+            | ExecutionError::OrderNotFound(_)
+            | ExecutionError::NotCancelable { .. }
+            | ExecutionError::InsufficientPosition { .. } => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FailureClass::Auth => "auth",
+            FailureClass::Server => "server",
+            FailureClass::Rejection => "rejection",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BreakerState {
+    Closed,
+    Open,
+    /// Cooldown over; one probe may go through.
+    HalfOpen,
+}
+
+impl BreakerState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BreakerState::Closed => "closed",
+            BreakerState::Open => "open",
+            BreakerState::HalfOpen => "half_open",
+        }
+    }
+}
+
+/// Consecutive-failure breaker for one market and failure class.
+#[derive(Debug)]
+struct CircuitBreaker {
+    failures: u32,
+    threshold: u32,
+    cooldown: Duration,
+    state: BreakerState,
+    opened_at: Option<Instant>,
+    /// A half-open probe has been let through and has not reported back yet.
+    probing: bool,
+}
+
+impl CircuitBreaker {
+    fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failures: 0,
+            threshold,
+            cooldown,
+            state: BreakerState::Closed,
+            opened_at: None,
+            probing: false,
+        }
+    }
+
+    fn cooled_down(&self) -> bool {
+        self.opened_at
+            .is_none_or(|opened| opened.elapsed() >= self.cooldown)
+    }
+
+    /// Whether this breaker refuses a request right now.
+    fn blocks(&self) -> bool {
+        match self.state {
+            BreakerState::Closed => false,
+            BreakerState::Open => !self.cooled_down(),
+            BreakerState::HalfOpen => self.probing,
+        }
+    }
+
+    /// Let a request through; an open breaker past its cooldown turns it into the probe.
+    fn admit(&mut self) {
+        if self.state == BreakerState::Open {
+            self.state = BreakerState::HalfOpen;
+        }
+        if self.state == BreakerState::HalfOpen {
+            self.probing = true;
+        }
+    }
+
+    fn on_success(&mut self) {
+        self.failures = 0;
+        self.state = BreakerState::Closed;
+        self.opened_at = None;
+        self.probing = false;
+    }
+
+    /// Record a failure; returns true if this opened (tripped) the breaker.
+    fn on_failure(&mut self) -> bool {
+        self.failures = self.failures.saturating_add(1);
+        self.probing = false;
+        // A failed probe reopens straight away.
+        if self.state == BreakerState::HalfOpen || self.failures >= self.threshold {
+            self.state = BreakerState::Open;
+            self.opened_at = Some(Instant::now());
+            return true;
+        }
+        false
+    }
+
+    /// The probe ended without saying anything about this class; allow another.
+    fn release(&mut self) {
+        self.probing = false;
+    }
+}
+
+/// Breakers keyed by scope (a market slug, or [`ACCOUNT_SCOPE`]) and failure class.
+#[derive(Debug)]
+pub(crate) struct Breakers {
+    threshold: u32,
+    cooldown: Duration,
+    breakers: HashMap<(String, FailureClass), CircuitBreaker>,
+}
+
+impl Breakers {
+    pub fn new(config: &BreakerConfig) -> Self {
+        Self {
+            threshold: config.failure_threshold.max(1),
+            cooldown: Duration::from_secs(config.cooldown_secs),
+            breakers: HashMap::new(),
+        }
+    }
+
+    fn breaker(&mut self, scope: &str, class: FailureClass) -> &mut CircuitBreaker {
+        let (threshold, cooldown) = (self.threshold, self.cooldown);
+        self.breakers
+            .entry((scope.to_string(), class))
+            .or_insert_with(|| CircuitBreaker::new(threshold, cooldown))
+    }
+
+    /// Whether any breaker of `scope` is open or half-open.
+    pub fn is_open(&self, scope: &str) -> bool {
+        self.breakers
+            .iter()
+            .any(|((s, _), b)| s == scope && b.state != BreakerState::Closed)
+    }
+
+    /// Admit a request for `scope` if none of its breakers refuses it.
+    ///
+    /// Breakers past their cooldown go half-open and count the request as their probe; it must
+    /// be followed by `on_success`, `on_failure` or `release`.
+    pub fn allow(&mut self, scope: &str) -> bool {
+        if self
+            .breakers
+            .iter()
+            .any(|((s, _), b)| s == scope && b.blocks())
+        {
+            return false;
+        }
+        for class in FailureClass::ALL {
+            let breaker = self.breaker(scope, class);
+            let before = breaker.state;
+            breaker.admit();
+            let after = breaker.state;
+            record_transition(scope, class, before, after);
+        }
+        true
+    }
+
+    /// A request for `scope` went through, so none of the failure classes applies.
+    pub fn on_success(&mut self, scope: &str) {
+        for class in FailureClass::ALL {
+            let breaker = self.breaker(scope, class);
+            let before = breaker.state;
+            breaker.on_success();
+            record_transition(scope, class, before, BreakerState::Closed);
+        }
+    }
+
+    /// Count a failure; returns true if it tripped the breaker.
+    pub fn on_failure(&mut self, scope: &str, class: FailureClass) -> bool {
+        self.release(scope);
+        let breaker = self.breaker(scope, class);
+        let before = breaker.state;
+        let tripped = breaker.on_failure();
+        if tripped {
+            warn!(
+                target: "execution",
+                scope,
+                class = class.as_str(),
+                failures = breaker.failures,
+                "execution circuit breaker opened"
+            );
+        }
+        let after = breaker.state;
+        record_transition(scope, class, before, after);
+        tripped
+    }
+
+    /// End a probe whose outcome says nothing about the venue.
+    pub fn release(&mut self, scope: &str) {
+        for ((s, _), breaker) in self.breakers.iter_mut() {
+            if s == scope {
+                breaker.release();
+            }
+        }
+    }
+}
+
+fn record_transition(scope: &str, class: FailureClass, from: BreakerState, to: BreakerState) {
+    if from != to {
+        METRICS.record_breaker_state(scope, class.as_str(), from.as_str(), to.as_str());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breakers(threshold: u32, cooldown_secs: u64) -> Breakers {
+        Breakers::new(&BreakerConfig {
+            failure_threshold: threshold,
+            cooldown_secs,
+        })
+    }
+
+    #[test]
+    fn circuit_breaker_opens_after_failures() {
+        let mut b = breakers(3, 60);
+        assert!(b.allow("BTC"));
+        b.on_failure("BTC", FailureClass::Server);
+        b.on_failure("BTC", FailureClass::Server);
+        assert!(b.allow("BTC"));
+        assert!(b.on_failure("BTC", FailureClass::Server));
+        assert!(b.is_open("BTC"));
+        assert!(!b.allow("BTC"));
+        // Other markets keep trading.
+        assert!(b.allow("ETH"));
+    }
+
+    #[test]
+    fn failures_count_per_class() {
+        let mut b = breakers(2, 60);
+        b.on_failure("BTC", FailureClass::Server);
+        assert!(!b.on_failure("BTC", FailureClass::Rejection));
+        assert!(!b.is_open("BTC"));
+        assert!(b.on_failure("BTC", FailureClass::Rejection));
+        assert!(!b.allow("BTC"));
+    }
+
+    #[test]
+    fn half_open_lets_one_probe_through() {
+        let mut b = breakers(1, 0);
+        assert!(b.on_failure("BTC", FailureClass::Server));
+
+        // Cooldown over: one probe, and nothing else until it reports back.
+        assert!(b.allow("BTC"));
+        assert!(!b.allow("BTC"));
+        assert!(b.on_failure("BTC", FailureClass::Server));
+
+        assert!(b.allow("BTC"));
+        b.release("BTC");
+        assert!(b.allow("BTC"));
+        b.on_success("BTC");
+        assert!(!b.is_open("BTC"));
+        assert!(b.allow("BTC"));
+        assert!(b.allow("BTC"));
+    }
+
+    #[test]
+    fn classifies_backend_errors() {
+        let status = |code| {
+            ExecutionError::Client(ClientError::HttpStatus {
+                status: reqwest::StatusCode::from_u16(code).unwrap(),
+                body: String::new(),
+            })
+        };
+        assert_eq!(FailureClass::of(&status(401)), Some(FailureClass::Auth));
+        assert_eq!(FailureClass::of(&status(503)), Some(FailureClass::Server));
+        assert_eq!(FailureClass::of(&status(400)), Some(FailureClass::Rejection));
+        let rejected = ExecutionError::Other("order rejected: not enough balance".to_string());
+        assert_eq!(FailureClass::of(&rejected), Some(FailureClass::Rejection));
+        assert_eq!(FailureClass::of(&ExecutionError::CircuitOpen), None);
+    }
+}
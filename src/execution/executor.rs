@@ -1,19 +1,28 @@
-use std::collections::HashMap;
-use std::time::{Duration, Instant};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
 
+use crate::client::auth::{OrderArgs, OrderBuilder, Side, SignedOrder};
 use crate::client::clob::ClobClient;
 use crate::client::gamma::ResolvedMarket;
 use crate::client::ClientError;
 use crate::monitoring::metrics::METRICS;
-use crate::strategy::{LegSide, TwoLegDecision};
-use crate::types::{AppConfig, ExecutionMode};
+use crate::strategy::{LegSide, OrderIntent, TradeAction};
+use crate::types::{ApiConfig, AppConfig, ExecutionMode, PaperConfig, SignatureType};
 
+use super::book::OrderBooks;
+use super::breaker::{Breakers, FailureClass, ACCOUNT_SCOPE};
 use super::order::{
-    Order, OrderId, OrderRequest, OrderSide, OrderStatus, OrderType, TimeInForce,
+    Fill, Liquidity, Order, OrderId, OrderRequest, OrderSide, OrderStatus, OrderType,
+    TimeInForce,
 };
+use super::paper::PaperMatchingEngine;
+use super::portfolio::Portfolio;
+use super::user_channel::UserEvent;
 
 #[derive(Debug, thiserror::Error)]
 pub enum ExecutionError {
@@ -29,74 +38,106 @@ pub enum ExecutionError {
     #[error("order not found: {0}")]
     OrderNotFound(String),
 
+    #[error("order {id} cannot be canceled in state {status:?}")]
+    NotCancelable { id: String, status: OrderStatus },
+
+    #[error("cannot sell {requested} shares with only {available} held and not already offered")]
+    InsufficientPosition { requested: f64, available: f64 },
+
     #[error("other execution error: {0}")]
     Other(String),
 }
 
 pub type ExecutionResult<T> = Result<T, ExecutionError>;
 
-/// Simple on-process circuit breaker for execution failures.
-#[derive(Debug)]
-struct CircuitBreaker {
-    failures: u32,
-    threshold: u32,
-    cooldown: Duration,
-    opened_at: Option<Instant>,
-}
+/// Tick size assumed until the book reports one for a token.
+const DEFAULT_TICK_SIZE: f64 = 0.01;
 
-impl CircuitBreaker {
-    fn new(threshold: u32, cooldown: Duration) -> Self {
-        Self {
-            failures: 0,
-            threshold,
-            cooldown,
-            opened_at: None,
-        }
-    }
+/// User channel events kept for orders whose placement has not been acknowledged yet.
+const MAX_UNMATCHED_EVENTS: usize = 256;
 
-    fn is_open(&self) -> bool {
-        match self.opened_at {
-            None => false,
-            Some(opened) => opened.elapsed() < self.cooldown,
-        }
-    }
+/// Backend for execution – either simulated (paper) or live CLOB.
+enum ExecutionBackend {
+    Paper(PaperExecutor),
+    Live(Arc<LiveExecutor>),
+}
 
-    fn allow(&mut self) -> bool {
-        if self.is_open() {
-            return false;
-        }
-        true
-    }
+/// Venue round-trip of a live submission, runnable on any task.
+pub type PendingAck = BoxFuture<'static, SubmissionAck>;
 
-    fn on_success(&mut self) {
-        self.failures = 0;
-        self.opened_at = None;
-    }
+/// The venue's answer to a submission.
+pub struct SubmissionAck {
+    /// Replaced orders the venue canceled before placing the new one.
+    pub(crate) canceled: Vec<OrderId>,
+    pub(crate) placed: ExecutionResult<Order>,
+}
 
-    fn on_failure(&mut self) {
-        self.failures = self.failures.saturating_add(1);
-        if self.failures >= self.threshold {
-            self.opened_at = Some(Instant::now());
-            warn!(
-                failures = self.failures,
-                "execution circuit breaker opened after consecutive failures"
-            );
-        }
-    }
+/// Venue round-trip of a cancel or order refresh, runnable on any task.
+pub type PendingCall = BoxFuture<'static, CallReply>;
+
+/// The venue's answer to a cancel or order refresh.
+pub enum CallReply {
+    /// Venue IDs of the orders a cancel took off the book; failures count against `scope`.
+    Canceled {
+        scope: String,
+        result: ExecutionResult<Vec<String>>,
+    },
+    /// Open orders as the venue reports them.
+    Orders(ExecutionResult<Vec<Order>>),
+    /// One order as the venue reports it.
+    Refreshed(ExecutionResult<Order>),
+    /// Cash and token balances as held on the venue.
+    Balances(ExecutionResult<Portfolio>),
 }
 
-/// Backend for execution – either simulated (paper) or live CLOB.
-enum ExecutionBackend {
-    Paper(PaperExecutor),
-    Live(LiveExecutor),
+/// A cancel or order refresh handed to the backend.
+pub enum VenueCall {
+    /// The backend answered on the spot; holds the orders it touched, already updated locally.
+    Done(Vec<OrderId>),
+    /// Outstanding venue round-trip; its answer must be passed to
+    /// [`OrderExecutor::on_call_reply`].
+    Pending(PendingCall),
+}
+
+/// An order entered into the local book and handed to the backend.
+pub struct Submission {
+    pub id: OrderId,
+    /// Outstanding venue round-trip; `None` when the backend took the order on the spot.
+    pub ack: Option<PendingAck>,
 }
 
-/// High-level order executor that owns backend adapter, circuit breaker and local order book.
+/// High-level order executor that owns backend adapter, circuit breakers and local order book.
 pub struct OrderExecutor {
     backend: ExecutionBackend,
-    breaker: CircuitBreaker,
+    breakers: Breakers,
     markets_by_slug: HashMap<String, ResolvedMarket>,
     orders: HashMap<OrderId, Order>,
+    /// Strategy intent behind each order, so fills can be routed back.
+    intents: HashMap<OrderId, OrderIntent>,
+    /// Venue order hash to local order ID.
+    venue_ids: HashMap<String, OrderId>,
+    /// (trade ID, venue order ID) pairs already seen, with the fill applied for each if any;
+    /// trades are re-sent on every status change and the fill is taken back if one fails.
+    seen_trades: HashMap<(String, String), Option<Fill>>,
+    /// Terminal orders whose outcome has already been handed out by `take_finished`.
+    finished: HashSet<OrderId>,
+    /// Submitted orders still waiting for the venue's acknowledgement.
+    in_flight: HashSet<OrderId>,
+    /// In-flight orders a market or account-wide cancel went out for before the venue knew
+    /// them; each is canceled by venue ID once its acknowledgement arrives.
+    cancel_on_ack: HashSet<OrderId>,
+    /// Venue calls the executor started on its own (cancels of orders flagged in
+    /// `cancel_on_ack`, or after a breaker trip), waiting for the caller to run them.
+    calls: Vec<PendingCall>,
+    /// User channel events for unknown venue orders seen while placements were in flight;
+    /// they may belong to an order whose venue ID is not known yet.
+    unmatched_events: Vec<UserEvent>,
+    /// Fills found by comparing venue order state with the local book rather than reported by
+    /// the user channel, waiting for [`Self::take_missed_fills`].
+    missed_fills: Vec<Fill>,
+    /// Latest tick size per token, fed from the market data stream; live orders are signed
+    /// with it.
+    tick_sizes: HashMap<String, f64>,
 }
 
 impl OrderExecutor {
@@ -116,18 +157,31 @@ impl OrderExecutor {
             .collect::<HashMap<_, _>>();
 
         let backend = match cfg.execution.mode {
-            ExecutionMode::Paper => ExecutionBackend::Paper(PaperExecutor::new()),
+            ExecutionMode::Paper => {
+                ExecutionBackend::Paper(PaperExecutor::new(cfg.execution.paper.clone()))
+            }
             ExecutionMode::Live => {
                 let clob = ClobClient::new(&cfg.api)?;
-                ExecutionBackend::Live(LiveExecutor::new(clob))
+                let builder = OrderBuilder::from_config(&cfg.api)?;
+                ExecutionBackend::Live(Arc::new(LiveExecutor::new(clob, builder, &cfg.api)))
             }
         };
 
         Ok(Self {
             backend,
-            breaker: CircuitBreaker::new(5, Duration::from_secs(30)),
+            breakers: Breakers::new(&cfg.execution.breaker),
             markets_by_slug,
             orders: HashMap::new(),
+            intents: HashMap::new(),
+            venue_ids: HashMap::new(),
+            seen_trades: HashMap::new(),
+            finished: HashSet::new(),
+            in_flight: HashSet::new(),
+            cancel_on_ack: HashSet::new(),
+            calls: Vec::new(),
+            unmatched_events: Vec::new(),
+            missed_fills: Vec::new(),
+            tick_sizes: HashMap::new(),
         })
     }
 
@@ -148,62 +202,188 @@ impl OrderExecutor {
                     slug: m.slug.clone(),
                     up_token_id: up.clone(),
                     down_token_id: down.clone(),
+                    neg_risk: m.neg_risk,
                 })
             })
             .collect::<ExecutionResult<Vec<_>>>()?;
         Self::from_config_and_resolved(cfg, resolved)
     }
 
-    /// Convert a high-level strategy decision into an order request and send it to the backend.
-    pub async fn execute_decision(&mut self, decision: TwoLegDecision) -> ExecutionResult<OrderId> {
-        if !self.breaker.allow() {
-            return Err(ExecutionError::CircuitOpen);
+    /// Convert a strategy order intent into an order request, send it to the backend and wait
+    /// for its answer.
+    pub async fn execute_decision(&mut self, intent: OrderIntent) -> ExecutionResult<OrderId> {
+        let submission = self.submit(intent)?;
+        if let Some(ack) = submission.ack {
+            let ack = ack.await;
+            self.complete(submission.id, ack)?;
         }
+        Ok(submission.id)
+    }
 
-        let req = self.decision_to_order_request(&decision)?;
+    /// Enter an intent's order into the local book and hand it to the backend.
+    ///
+    /// Paper orders are placed on the spot. Live orders come back with their venue round-trip
+    /// (cancel the orders the intent replaces, then place it), which may run on another task
+    /// while the executor keeps serving; its answer must be passed to [`Self::complete`].
+    pub fn submit(&mut self, intent: OrderIntent) -> ExecutionResult<Submission> {
+        let scope = intent.market_slug.clone();
+        if !self.breakers.allow(&scope) {
+            return Err(ExecutionError::CircuitOpen);
+        }
 
-        let result = match &self.backend {
-            ExecutionBackend::Paper(paper) => paper.execute_order(&req).await,
-            ExecutionBackend::Live(live) => live.execute_order(&req).await,
+        let (order, ack) = match self.start_order(&intent) {
+            Ok(started) => started,
+            Err(err) => {
+                self.breakers.release(&scope);
+                return Err(err);
+            }
         };
 
-        match result {
-            Ok(mut order) => {
+        let id = order.id;
+        self.orders.insert(id, order);
+        self.intents.insert(id, intent);
+        if ack.is_some() {
+            self.in_flight.insert(id);
+        } else {
+            self.breakers.on_success(&scope);
+        }
+        Ok(Submission { id, ack })
+    }
+
+    /// Build the order for an intent and start placing it, replacing the working orders it
+    /// names.
+    ///
+    /// Fails if one of them can no longer be canceled, e.g. because it just filled, so the
+    /// replacement is not placed on top of it.
+    fn start_order(
+        &mut self,
+        intent: &OrderIntent,
+    ) -> ExecutionResult<(Order, Option<PendingAck>)> {
+        let req = self.decision_to_order_request(intent)?;
+        let replaced = self.replaced_orders(intent);
+
+        match &mut self.backend {
+            ExecutionBackend::Paper(paper) => {
+                for id in replaced {
+                    if let Err(err) = paper.engine.cancel(id) {
+                        if let (ExecutionError::NotCancelable { status, .. }, Some(order)) =
+                            (&err, self.orders.get_mut(&id))
+                        {
+                            order.status = *status;
+                        }
+                        return Err(err);
+                    }
+                    if let Some(order) = self.orders.get_mut(&id) {
+                        order.status = OrderStatus::Canceled;
+                    }
+                }
+                let mut order = paper.engine.submit(&req, Utc::now());
                 // Newly submitted orders start as Open or Filled depending on backend behavior.
                 if matches!(order.status, OrderStatus::New) {
                     order.status = OrderStatus::Open;
                 }
-                let id = order.id;
-                self.orders.insert(id, order);
-                self.breaker.on_success();
-                Ok(id)
+                Ok((order, None))
+            }
+            ExecutionBackend::Live(live) => {
+                let cancels = replaced
+                    .into_iter()
+                    .map(|id| venue_order_id(&self.orders, id).map(|v| (id, v.to_string())))
+                    .collect::<ExecutionResult<Vec<_>>>()?;
+                let tick_size = self
+                    .tick_sizes
+                    .get(&req.token_id)
+                    .copied()
+                    .unwrap_or(DEFAULT_TICK_SIZE);
+                let order = Order::new(OrderId::new_v4(), req.clone());
+                let live = Arc::clone(live);
+                let ack = async move {
+                    let mut canceled = Vec::new();
+                    for (id, venue_id) in cancels {
+                        if let Err(err) = live.cancel_order(&venue_id).await {
+                            return SubmissionAck {
+                                canceled,
+                                placed: Err(err),
+                            };
+                        }
+                        canceled.push(id);
+                    }
+                    let placed = live.execute_order(&req, tick_size).await;
+                    SubmissionAck { canceled, placed }
+                };
+                Ok((order, Some(Box::pin(ack))))
+            }
+        }
+    }
+
+    /// Apply the venue's answer to a live submission.
+    ///
+    /// Returns the fills of user channel events that arrived for the order while it was in
+    /// flight. A failed submission leaves the order rejected, to be reported by
+    /// `take_finished` like any other outcome. Cancels it sets off are left for
+    /// [`Self::take_calls`].
+    pub fn complete(
+        &mut self,
+        id: OrderId,
+        ack: SubmissionAck,
+    ) -> ExecutionResult<Vec<Fill>> {
+        self.in_flight.remove(&id);
+        self.mark_canceled(&ack.canceled);
+        let scope = self.scope_of(&id);
+
+        let result = match ack.placed {
+            Ok(placed) => {
+                if let Some(order) = self.orders.get_mut(&id) {
+                    // The ack only says the venue took the order; fills and cancels arrive as
+                    // user channel events or from `refresh_order`.
+                    order.status = OrderStatus::Open;
+                    order.venue_order_id = placed.venue_order_id;
+                    if let Some(venue_id) = &order.venue_order_id {
+                        self.venue_ids.insert(venue_id.clone(), id);
+                    }
+                }
+                self.breakers.on_success(&scope);
+                let events = std::mem::take(&mut self.unmatched_events);
+                Ok(events
+                    .into_iter()
+                    .flat_map(|event| self.on_user_event(event))
+                    .collect())
             }
             Err(err) => {
-                self.breaker.on_failure();
+                if let Some(order) = self.orders.get_mut(&id) {
+                    order.status = OrderStatus::Rejected;
+                }
+                self.on_backend_failure(&scope, &err);
                 // Tag failures with market slug so monitoring and alerting can react.
-                let market_slug = match &decision {
-                    TwoLegDecision::OpenLeg1 { market_slug, .. } => market_slug.as_str(),
-                    TwoLegDecision::OpenLeg2 { market_slug, .. } => market_slug.as_str(),
-                };
-                METRICS.record_order_failed(market_slug, &err.to_string());
+                METRICS.record_order_failed(&scope, &err.to_string());
                 Err(err)
             }
+        };
+        if self.in_flight.is_empty() {
+            self.unmatched_events.clear();
         }
+        if self.cancel_on_ack.remove(&id) && result.is_ok() {
+            if let VenueCall::Pending(call) = self.start_cancel_orders(vec![id]) {
+                self.calls.push(call);
+            }
+        }
+        result
     }
 
     /// Cancel an existing order if supported by backend.
+    ///
+    /// Cancels are protective, so they go out even while the market's breaker is open; only
+    /// their failures are counted.
     pub async fn cancel_order(&mut self, id: OrderId) -> ExecutionResult<()> {
-        if !self.breaker.allow() {
-            return Err(ExecutionError::CircuitOpen);
-        }
-
-        if !self.orders.contains_key(&id) {
+        let Some(scope) = self.orders.get(&id).map(|o| o.request.market_slug.clone()) else {
             return Err(ExecutionError::OrderNotFound(id.to_string()));
-        }
+        };
 
-        let result = match &self.backend {
+        let result = match &mut self.backend {
             ExecutionBackend::Paper(paper) => paper.cancel_order(id).await,
-            ExecutionBackend::Live(live) => live.cancel_order(id).await,
+            ExecutionBackend::Live(live) => match venue_order_id(&self.orders, id) {
+                Ok(venue_id) => live.cancel_order(venue_id).await,
+                Err(err) => Err(err),
+            },
         };
 
         match result {
@@ -215,181 +395,836 @@ impl OrderExecutor {
                         order.status = OrderStatus::Canceled;
                     }
                 }
-                self.breaker.on_success();
                 Ok(())
             }
+            Err(ExecutionError::NotCancelable { id: order_ref, status }) => {
+                // The backend answered; only our local view of the order was stale.
+                if let Some(order) = self.orders.get_mut(&id) {
+                    order.status = status;
+                }
+                Err(ExecutionError::NotCancelable { id: order_ref, status })
+            }
             Err(err) => {
-                self.breaker.on_failure();
+                self.on_backend_failure(&scope, &err);
+                Err(err)
+            }
+        }
+    }
+
+    /// Working orders `intent` replaces: same strategy, market and round, with one of the
+    /// labels it names.
+    fn replaced_orders(&self, intent: &OrderIntent) -> Vec<OrderId> {
+        if intent.replaces.is_empty() {
+            return Vec::new();
+        }
+        self.intents
+            .iter()
+            .filter(|(id, prev)| {
+                prev.strategy == intent.strategy
+                    && prev.market_slug == intent.market_slug
+                    && prev.round_start == intent.round_start
+                    && intent.replaces.contains(&prev.label)
+                    && self.orders.get(id).is_some_and(|o| !o.is_terminal())
+            })
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Cancel every live order on both tokens of a market.
+    ///
+    /// Cancels are protective, so they go out even while the circuit breaker is open.
+    pub async fn cancel_market(&mut self, market_slug: &str) -> ExecutionResult<Vec<OrderId>> {
+        let call = self.start_cancel_market(market_slug)?;
+        self.finish_call(call).await
+    }
+
+    /// Start canceling every live order on both tokens of a market; see [`Self::cancel_market`].
+    pub fn start_cancel_market(&mut self, market_slug: &str) -> ExecutionResult<VenueCall> {
+        let market = self
+            .markets_by_slug
+            .get(market_slug)
+            .ok_or_else(|| ExecutionError::Config(format!("unknown market slug: {market_slug}")))?;
+        let tokens = [market.up_token_id.clone(), market.down_token_id.clone()];
+        // Placements still in flight are not on the venue yet; pull them once they are.
+        let pending: Vec<OrderId> = self
+            .in_flight
+            .iter()
+            .filter(|id| self.orders.get(id).is_some_and(|o| o.request.market_slug == market_slug))
+            .copied()
+            .collect();
+        self.cancel_on_ack.extend(pending);
+
+        let scope = market_slug.to_string();
+        match &mut self.backend {
+            ExecutionBackend::Paper(paper) => {
+                let canceled = paper
+                    .engine
+                    .cancel_where(|o| tokens.contains(&o.request.token_id));
+                Ok(VenueCall::Done(self.apply_canceled(&scope, canceled)))
+            }
+            ExecutionBackend::Live(live) => {
+                let live = Arc::clone(live);
+                Ok(VenueCall::Pending(Box::pin(async move {
+                    let mut canceled = Vec::new();
+                    for token_id in &tokens {
+                        match live.cancel_market(token_id).await {
+                            Ok(ids) => canceled.extend(ids),
+                            Err(err) => {
+                                return CallReply::Canceled {
+                                    scope,
+                                    result: Err(err),
+                                }
+                            }
+                        }
+                    }
+                    CallReply::Canceled {
+                        scope,
+                        result: Ok(canceled),
+                    }
+                })))
+            }
+        }
+    }
+
+    /// Cancel every live order on the account.
+    ///
+    /// Runs on shutdown and whenever an account-level breaker trips, so it ignores the breakers.
+    pub async fn cancel_all(&mut self) -> ExecutionResult<Vec<OrderId>> {
+        let call = self.start_cancel_all();
+        self.finish_call(call).await
+    }
+
+    /// Start canceling every live order on the account; see [`Self::cancel_all`].
+    pub fn start_cancel_all(&mut self) -> VenueCall {
+        // Placements still in flight are not on the venue yet; pull them once they are.
+        self.cancel_on_ack.extend(self.in_flight.iter().copied());
+        match &mut self.backend {
+            ExecutionBackend::Paper(paper) => {
+                let canceled = paper.engine.cancel_where(|_| true);
+                VenueCall::Done(self.apply_canceled(ACCOUNT_SCOPE, canceled))
+            }
+            ExecutionBackend::Live(live) => {
+                let live = Arc::clone(live);
+                VenueCall::Pending(Box::pin(async move {
+                    CallReply::Canceled {
+                        scope: ACCOUNT_SCOPE.to_string(),
+                        result: live.cancel_all().await,
+                    }
+                }))
+            }
+        }
+    }
+
+    /// Start canceling the given orders; those already finished are skipped and those still
+    /// in flight are canceled once the venue acknowledges them.
+    pub fn start_cancel_orders(&mut self, ids: Vec<OrderId>) -> VenueCall {
+        let (pending, ids): (Vec<OrderId>, Vec<OrderId>) = ids
+            .into_iter()
+            .filter(|id| self.orders.get(id).is_some_and(|o| !o.is_terminal()))
+            .partition(|id| self.in_flight.contains(id));
+        self.cancel_on_ack.extend(pending);
+
+        let scopes: HashSet<String> = ids.iter().map(|id| self.scope_of(id)).collect();
+        let scope = match scopes.len() {
+            1 => scopes.into_iter().next().unwrap_or_default(),
+            _ => ACCOUNT_SCOPE.to_string(),
+        };
+        match &mut self.backend {
+            ExecutionBackend::Paper(paper) => {
+                let mut canceled = Vec::new();
+                for id in ids {
+                    match paper.engine.cancel(id) {
+                        Ok(()) => canceled.push(id),
+                        Err(ExecutionError::NotCancelable { status, .. }) => {
+                            if let Some(order) = self.orders.get_mut(&id) {
+                                order.status = status;
+                            }
+                        }
+                        Err(_) => {}
+                    }
+                }
+                VenueCall::Done(self.apply_canceled(&scope, canceled))
+            }
+            ExecutionBackend::Live(live) => {
+                let venue_ids: Vec<String> = ids
+                    .iter()
+                    .filter_map(|id| venue_order_id(&self.orders, *id).ok())
+                    .map(str::to_string)
+                    .collect();
+                if venue_ids.is_empty() {
+                    return VenueCall::Done(Vec::new());
+                }
+                let live = Arc::clone(live);
+                VenueCall::Pending(Box::pin(async move {
+                    CallReply::Canceled {
+                        scope,
+                        result: live.cancel_orders(&venue_ids).await,
+                    }
+                }))
+            }
+        }
+    }
+
+    /// Apply the venue's answer to a cancel or order refresh.
+    ///
+    /// Returns the local orders it canceled or refreshed. Failures count against the call's
+    /// breaker scope; cancels a breaker trip sets off are left for [`Self::take_calls`].
+    pub fn on_call_reply(&mut self, reply: CallReply) -> ExecutionResult<Vec<OrderId>> {
+        match reply {
+            CallReply::Canceled {
+                scope,
+                result: Ok(venue_ids),
+            } => {
+                let canceled = self.resolve_order_ids(&venue_ids);
+                Ok(self.apply_canceled(&scope, canceled))
+            }
+            CallReply::Canceled {
+                scope,
+                result: Err(err),
+            } => {
+                self.on_backend_failure(&scope, &err);
+                Err(err)
+            }
+            CallReply::Orders(Ok(orders)) => {
+                let ids: Vec<OrderId> = orders
+                    .into_iter()
+                    .map(|order| self.merge_backend_order(order).id)
+                    .collect();
+                self.breakers.on_success(ACCOUNT_SCOPE);
+                self.refresh_unlisted(&ids);
+                Ok(ids)
+            }
+            CallReply::Refreshed(Ok(order)) => {
+                self.breakers.on_success(ACCOUNT_SCOPE);
+                Ok(vec![self.merge_backend_order(order).id])
+            }
+            CallReply::Refreshed(Err(err)) => {
+                self.on_backend_failure(ACCOUNT_SCOPE, &err);
+                Err(err)
+            }
+            CallReply::Orders(Err(err)) => {
+                self.on_backend_failure(ACCOUNT_SCOPE, &err);
                 Err(err)
             }
+            CallReply::Balances(result) => self.on_balances(result).map(|_| Vec::new()),
+        }
+    }
+
+    /// Account for the answer to a balance fetch and pass the venue's portfolio on.
+    pub fn on_balances(
+        &mut self,
+        result: ExecutionResult<Portfolio>,
+    ) -> ExecutionResult<Portfolio> {
+        match &result {
+            Ok(_) => self.breakers.on_success(ACCOUNT_SCOPE),
+            Err(err) => self.on_backend_failure(ACCOUNT_SCOPE, err),
+        }
+        result
+    }
+
+    /// Working orders missing from the venue's open orders finished without the user channel
+    /// telling us, e.g. while it was down; fetch each of them to learn how.
+    fn refresh_unlisted(&mut self, listed: &[OrderId]) {
+        let ExecutionBackend::Live(live) = &self.backend else {
+            return;
+        };
+        let listed: HashSet<&OrderId> = listed.iter().collect();
+        for order in self.orders.values() {
+            let unseen = !listed.contains(&order.id) && !self.in_flight.contains(&order.id);
+            if order.is_terminal() || !unseen {
+                continue;
+            }
+            let Some(venue_id) = order.venue_order_id.clone() else {
+                continue;
+            };
+            let live = Arc::clone(live);
+            self.calls.push(Box::pin(async move {
+                CallReply::Refreshed(live.refresh_order(&venue_id).await)
+            }));
+        }
+    }
+
+    /// Fills picked up from venue order state since the last call, with the local book already
+    /// updated; the user channel never reported them.
+    pub fn take_missed_fills(&mut self) -> Vec<Fill> {
+        std::mem::take(&mut self.missed_fills)
+    }
+
+    /// Venue calls the executor started on its own since the last call; the caller runs each
+    /// and passes its answer to [`Self::on_call_reply`].
+    pub fn take_calls(&mut self) -> Vec<PendingCall> {
+        std::mem::take(&mut self.calls)
+    }
+
+    /// Wait for a call's answer and apply it.
+    async fn finish_call(&mut self, call: VenueCall) -> ExecutionResult<Vec<OrderId>> {
+        match call {
+            VenueCall::Done(ids) => Ok(ids),
+            VenueCall::Pending(call) => {
+                let reply = call.await;
+                self.on_call_reply(reply)
+            }
+        }
+    }
+
+    /// Mark orders a cancel took off the book; returns them.
+    fn apply_canceled(&mut self, scope: &str, canceled: Vec<OrderId>) -> Vec<OrderId> {
+        self.mark_canceled(&canceled);
+        info!(
+            target: "execution",
+            scope,
+            canceled = canceled.len(),
+            "canceled orders"
+        );
+        canceled
+    }
+
+    /// Map venue order IDs reported by a bulk cancel back to local orders.
+    fn resolve_order_ids(&self, venue_ids: &[String]) -> Vec<OrderId> {
+        venue_ids
+            .iter()
+            .filter_map(|venue_id| self.venue_ids.get(venue_id))
+            .copied()
+            .collect()
+    }
+
+    /// Fold the backend's view of an order into the local book and return the merged order.
+    ///
+    /// Orders are matched by venue ID when they have one, so state fetched from the venue
+    /// updates the order we submitted (keeping its local ID and original request). Orders the
+    /// executor has never seen, e.g. placed by a previous run, are adopted as-is.
+    fn merge_backend_order(&mut self, order: Order) -> Order {
+        let local_id = order
+            .venue_order_id
+            .as_ref()
+            .and_then(|venue_id| self.venue_ids.get(venue_id).copied())
+            .unwrap_or(order.id);
+
+        match self.orders.get_mut(&local_id) {
+            Some(local) if order.venue_order_id.is_some() => {
+                // Fills already applied from trades carry their real prices; what the backend
+                // knows of beyond them was missed and is taken at its price.
+                let missed = order.filled_size - local.filled_size;
+                if missed > 0.0 {
+                    local.apply_fill(order.avg_fill_price, missed, 0.0);
+                    self.missed_fills.push(Fill {
+                        order_id: local_id,
+                        price: order.avg_fill_price,
+                        size: missed,
+                        fee: 0.0,
+                        liquidity: Liquidity::Maker,
+                        ts: Utc::now(),
+                    });
+                }
+                local.status = order.status;
+                local.clone()
+            }
+            _ => {
+                let mut order = order;
+                if order.request.market_slug.is_empty() {
+                    if let Some(market) = self.markets_by_slug.values().find(|m| {
+                        m.up_token_id == order.request.token_id
+                            || m.down_token_id == order.request.token_id
+                    }) {
+                        order.request.market_slug = market.slug.clone();
+                        order.request.neg_risk = market.neg_risk;
+                    }
+                }
+                if let Some(venue_id) = &order.venue_order_id {
+                    self.venue_ids.insert(venue_id.clone(), local_id);
+                }
+                self.orders.insert(local_id, order.clone());
+                order
+            }
+        }
+    }
+
+    fn mark_canceled(&mut self, ids: &[OrderId]) {
+        for id in ids {
+            if let Some(order) = self.orders.get_mut(id) {
+                if !order.is_terminal() {
+                    order.status = OrderStatus::Canceled;
+                }
+            }
         }
     }
 
+    /// Count a backend failure against `scope` and pull the scope's resting orders if it trips
+    /// a breaker: the market's orders, or all of them for account-level calls.
+    ///
+    /// Live cancels are left for [`Self::take_calls`].
+    fn on_backend_failure(&mut self, scope: &str, err: &ExecutionError) {
+        let Some(class) = FailureClass::of(err) else {
+            self.breakers.release(scope);
+            return;
+        };
+        if !self.breakers.on_failure(scope, class) {
+            return;
+        }
+        let call = if scope == ACCOUNT_SCOPE {
+            Ok(self.start_cancel_all())
+        } else {
+            self.start_cancel_market(scope)
+        };
+        match call {
+            Ok(VenueCall::Pending(call)) => self.calls.push(call),
+            Ok(VenueCall::Done(_)) => {}
+            Err(err) => warn!(
+                target: "execution",
+                scope,
+                error = %err,
+                "cancel after circuit breaker trip failed"
+            ),
+        }
+    }
+
+    /// Breaker scope of an order: its market, or the account if the market is unknown.
+    fn scope_of(&self, id: &OrderId) -> String {
+        self.orders
+            .get(id)
+            .map(|o| o.request.market_slug.clone())
+            .filter(|slug| self.markets_by_slug.contains_key(slug))
+            .unwrap_or_else(|| ACCOUNT_SCOPE.to_string())
+    }
+
     /// Refresh local view of an order from the backend, if supported.
     pub async fn reconcile_order(&mut self, id: OrderId) -> ExecutionResult<Order> {
-        if !self.breaker.allow() {
+        let scope = self.scope_of(&id);
+        if !self.breakers.allow(&scope) {
             return Err(ExecutionError::CircuitOpen);
         }
 
         let result = match &self.backend {
-            ExecutionBackend::Paper(paper) => paper.refresh_order(id).await,
-            ExecutionBackend::Live(live) => live.refresh_order(id).await,
+            ExecutionBackend::Paper(paper) => paper.refresh_order(id),
+            ExecutionBackend::Live(live) => match venue_order_id(&self.orders, id) {
+                Ok(venue_id) => live.refresh_order(venue_id).await,
+                Err(err) => Err(err),
+            },
         };
 
         match result {
             Ok(order) => {
-                self.orders.insert(id, order.clone());
-                self.breaker.on_success();
+                let order = self.merge_backend_order(order);
+                self.breakers.on_success(&scope);
                 Ok(order)
             }
             Err(err) => {
-                self.breaker.on_failure();
+                self.on_backend_failure(&scope, &err);
                 Err(err)
             }
         }
     }
 
+    /// Fetch every order the backend still considers live and refresh the local book with it.
+    pub async fn open_orders(&mut self) -> ExecutionResult<Vec<Order>> {
+        let call = self.start_open_orders()?;
+        let ids = self.finish_call(call).await?;
+        Ok(ids.iter().filter_map(|id| self.orders.get(id)).cloned().collect())
+    }
+
+    /// Cancel whatever the market's `ended` round and the rounds before it still have working.
+    pub fn start_cancel_round(&mut self, market_slug: &str, ended: DateTime<Utc>) -> VenueCall {
+        let ids: Vec<OrderId> = self
+            .intents
+            .iter()
+            .filter(|(_, intent)| intent.market_slug == market_slug && intent.round_start <= ended)
+            .map(|(id, _)| *id)
+            .filter(|id| self.orders.get(id).is_some_and(|o| !o.is_terminal()))
+            .collect();
+        if ids.is_empty() {
+            return VenueCall::Done(Vec::new());
+        }
+        self.start_cancel_orders(ids)
+    }
+
+    /// Start fetching the backend's live orders; see [`Self::open_orders`].
+    pub fn start_open_orders(&mut self) -> ExecutionResult<VenueCall> {
+        if !self.breakers.allow(ACCOUNT_SCOPE) {
+            return Err(ExecutionError::CircuitOpen);
+        }
+
+        match &self.backend {
+            ExecutionBackend::Paper(paper) => {
+                let orders = paper.engine.open_orders();
+                Ok(VenueCall::Done(self.on_call_reply(CallReply::Orders(Ok(orders)))?))
+            }
+            ExecutionBackend::Live(live) => {
+                let live = Arc::clone(live);
+                Ok(VenueCall::Pending(Box::pin(async move {
+                    CallReply::Orders(live.open_orders().await)
+                })))
+            }
+        }
+    }
+
+    /// Whether a circuit breaker currently stops orders to a market.
+    pub fn breaker_open(&self, market_slug: &str) -> bool {
+        self.breakers.is_open(market_slug)
+    }
+
     /// Read-only access to an order in the local book.
     pub fn order(&self, id: &OrderId) -> Option<&Order> {
         self.orders.get(id)
     }
 
-    fn decision_to_order_request(&self, decision: &TwoLegDecision) -> ExecutionResult<OrderRequest> {
-        let (market_slug, round_start, leg_side, shares, limit_price, expected_profit, leg_label) =
-            match decision {
-                TwoLegDecision::OpenLeg1 {
-                    market_slug,
-                    round_start,
-                    side,
-                    shares,
-                    limit_price,
-                } => (
-                    market_slug.clone(),
-                    *round_start,
-                    *side,
-                    *shares,
-                    *limit_price,
-                    None,
-                    "leg1",
-                ),
-                TwoLegDecision::OpenLeg2 {
-                    market_slug,
-                    round_start,
-                    side,
-                    shares,
-                    limit_price,
-                    expected_locked_profit,
-                } => (
-                    market_slug.clone(),
-                    *round_start,
-                    *side,
-                    *shares,
-                    *limit_price,
-                    Some(*expected_locked_profit),
-                    "leg2",
-                ),
-            };
+    /// The strategy intent an order was submitted for.
+    pub fn intent(&self, id: &OrderId) -> Option<&OrderIntent> {
+        self.intents.get(id)
+    }
+
+    /// Drop terminal orders of rounds that started before `before` once their outcome has been
+    /// handed out, together with the venue IDs and trades kept to match events to them.
+    /// Orders adopted from the venue have no round and go as soon as they are reported.
+    /// Returns how many orders were dropped.
+    pub fn prune_orders(&mut self, before: DateTime<Utc>) -> usize {
+        let pruned: Vec<OrderId> = self
+            .orders
+            .values()
+            .filter(|o| o.is_terminal() && self.finished.contains(&o.id))
+            .filter(|o| self.intents.get(&o.id).is_none_or(|i| i.round_start < before))
+            .map(|o| o.id)
+            .collect();
+        if pruned.is_empty() {
+            return 0;
+        }
 
+        let mut venue_ids = HashSet::new();
+        for id in &pruned {
+            self.intents.remove(id);
+            self.finished.remove(id);
+            if let Some(venue_id) = self.orders.remove(id).and_then(|o| o.venue_order_id) {
+                self.venue_ids.remove(&venue_id);
+                venue_ids.insert(venue_id);
+            }
+        }
+        self.seen_trades
+            .retain(|(_, venue_id), _| !venue_ids.contains(venue_id));
+        if let ExecutionBackend::Paper(paper) = &mut self.backend {
+            paper.engine.forget(&pruned);
+        }
+        pruned.len()
+    }
+
+    /// Orders that reached a terminal state since the last call, each returned exactly once.
+    pub fn take_finished(&mut self) -> Vec<OrderId> {
+        let finished: Vec<OrderId> = self
+            .orders
+            .values()
+            .filter(|o| o.is_terminal() && !self.finished.contains(&o.id))
+            .map(|o| o.id)
+            .collect();
+        self.finished.extend(finished.iter().copied());
+        finished
+    }
+
+    /// Correct the live client's clock against the CLOB's server time; a no-op in paper mode.
+    pub async fn sync_clock(&self) -> ExecutionResult<()> {
+        if let ExecutionBackend::Live(live) = &self.backend {
+            live.clob.sync_time().await?;
+        }
+        Ok(())
+    }
+
+    /// Cash and the given tokens' balances as held on the venue; `None` in paper mode.
+    pub async fn venue_portfolio(
+        &self,
+        token_ids: &[String],
+    ) -> ExecutionResult<Option<Portfolio>> {
+        match &self.backend {
+            ExecutionBackend::Paper(_) => Ok(None),
+            ExecutionBackend::Live(live) => live.portfolio(token_ids).await.map(Some),
+        }
+    }
+
+    /// Start fetching the venue's balances; see [`Self::venue_portfolio`]. The answer comes back
+    /// as [`CallReply::Balances`]; `None` in paper mode, where the local portfolio is the truth.
+    pub fn start_venue_portfolio(&self, token_ids: Vec<String>) -> Option<PendingCall> {
+        match &self.backend {
+            ExecutionBackend::Paper(_) => None,
+            ExecutionBackend::Live(live) => {
+                let live = Arc::clone(live);
+                Some(Box::pin(async move {
+                    CallReply::Balances(live.portfolio(&token_ids).await)
+                }))
+            }
+        }
+    }
+
+    /// Token IDs of every market currently traded.
+    pub fn token_ids(&self) -> Vec<String> {
+        self.markets_by_slug
+            .values()
+            .flat_map(|m| [m.up_token_id.clone(), m.down_token_id.clone()])
+            .collect()
+    }
+
+    /// Estimated size queued ahead of a resting paper order at its price (`None` in live mode).
+    pub fn queue_ahead(&self, id: &OrderId) -> Option<f64> {
+        match &self.backend {
+            ExecutionBackend::Paper(paper) => paper.engine.queue_ahead(id),
+            ExecutionBackend::Live(_) => None,
+        }
+    }
+
+    /// Let the backend react to book changes.
+    ///
+    /// In paper mode this runs the matching engine and returns the resulting fills, with the
+    /// local order book already updated. Live fills arrive from the venue instead, so live mode
+    /// only picks up tick sizes for order signing and returns nothing.
+    pub fn on_market_data(&mut self, books: &OrderBooks, now: DateTime<Utc>) -> Vec<Fill> {
+        let paper = match &mut self.backend {
+            ExecutionBackend::Paper(paper) => paper,
+            ExecutionBackend::Live(_) => {
+                // Live orders must be signed at the market's current tick size.
+                for market in self.markets_by_slug.values() {
+                    for token_id in [&market.up_token_id, &market.down_token_id] {
+                        if let Some(tick) = books.token(token_id).and_then(|b| b.tick_size()) {
+                            self.tick_sizes.insert(token_id.clone(), tick);
+                        }
+                    }
+                }
+                return Vec::new();
+            }
+        };
+
+        let fills = paper.engine.match_orders(books, now);
+        // Matching also expires unfilled IOC/FOK orders, so sync every working order.
+        for order in self.orders.values_mut().filter(|o| !o.is_terminal()) {
+            if let Some(engine_order) = paper.engine.order(&order.id) {
+                *order = engine_order.clone();
+            }
+        }
+        fills
+    }
+
+    /// Apply an event from the authenticated user channel.
+    ///
+    /// Events for orders this executor did not place are ignored; while placements are in
+    /// flight they are held back until the venue IDs are known. Returns the fills it produced,
+    /// with the local order book already updated.
+    pub(crate) fn on_user_event(&mut self, event: UserEvent) -> Vec<Fill> {
+        match event {
+            UserEvent::Order(update) => {
+                let Some(&id) = self.venue_ids.get(&update.venue_order_id) else {
+                    self.hold_unmatched(UserEvent::Order(update));
+                    return Vec::new();
+                };
+                if let (Some(order), Some(status)) = (self.orders.get_mut(&id), update.status) {
+                    // A late placement ack must not undo fills already applied from trades.
+                    let applies = status != OrderStatus::Open || order.status == OrderStatus::New;
+                    if applies && !order.is_terminal() {
+                        order.status = status;
+                    }
+                }
+                Vec::new()
+            }
+            UserEvent::Fill(fill) => {
+                let Some(&id) = self.venue_ids.get(&fill.venue_order_id) else {
+                    self.hold_unmatched(UserEvent::Fill(fill));
+                    return Vec::new();
+                };
+                let key = (fill.trade_id.clone(), fill.venue_order_id.clone());
+                if self.seen_trades.contains_key(&key) {
+                    return Vec::new();
+                }
+                let Some(order) = self.orders.get_mut(&id) else {
+                    return Vec::new();
+                };
+
+                // Venue order state merged in ahead of the trade may already hold the fill.
+                let size = fill.size.min(order.remaining());
+                if size <= 0.0 {
+                    self.seen_trades.insert(key, None);
+                    return Vec::new();
+                }
+                let fee = fill.price * size * fill.fee_rate_bps / 10_000.0;
+                order.apply_fill(fill.price, size, fee);
+                let applied = Fill {
+                    order_id: id,
+                    price: fill.price,
+                    size,
+                    fee,
+                    liquidity: fill.liquidity,
+                    ts: fill.ts,
+                };
+                self.seen_trades.insert(key, Some(applied.clone()));
+                vec![applied]
+            }
+            UserEvent::TradeFailed(fill) => {
+                let Some(&id) = self.venue_ids.get(&fill.venue_order_id) else {
+                    self.hold_unmatched(UserEvent::TradeFailed(fill));
+                    return Vec::new();
+                };
+                // A failure reported before the match leaves nothing to take back, and keeps a
+                // late match from being applied.
+                let key = (fill.trade_id.clone(), fill.venue_order_id.clone());
+                let (Some(applied), Some(order)) =
+                    (self.seen_trades.insert(key, None).flatten(), self.orders.get_mut(&id))
+                else {
+                    return Vec::new();
+                };
+
+                let was_filled = order.status == OrderStatus::Filled;
+                order.reverse_fill(applied.price, applied.size, applied.fee);
+                // The order's outcome changed, so it is reported again.
+                if was_filled {
+                    self.finished.remove(&id);
+                }
+                warn!(
+                    target: "execution",
+                    trade_id = %fill.trade_id,
+                    venue_order_id = %fill.venue_order_id,
+                    size = applied.size,
+                    "trade failed; reversing its fill"
+                );
+                vec![Fill {
+                    size: -applied.size,
+                    fee: -applied.fee,
+                    ts: fill.ts,
+                    ..applied
+                }]
+            }
+        }
+    }
+
+    /// Keep an event for an unknown venue order while placements are in flight; the venue may
+    /// report an order before answering the request that placed it.
+    fn hold_unmatched(&mut self, event: UserEvent) {
+        if self.in_flight.is_empty() {
+            return;
+        }
+        if self.unmatched_events.len() >= MAX_UNMATCHED_EVENTS {
+            self.unmatched_events.remove(0);
+        }
+        self.unmatched_events.push(event);
+    }
+
+    /// Swap in freshly resolved token IDs for a market (e.g. at a 15m round rollover).
+    ///
+    /// Returns the previous resolution for the same slug, if any.
+    pub fn replace_market(&mut self, market: ResolvedMarket) -> Option<ResolvedMarket> {
+        self.markets_by_slug.insert(market.slug.clone(), market)
+    }
+
+    fn decision_to_order_request(&self, intent: &OrderIntent) -> ExecutionResult<OrderRequest> {
+        let market_slug = &intent.market_slug;
         let market = self
             .markets_by_slug
-            .get(&market_slug)
+            .get(market_slug)
             .ok_or_else(|| ExecutionError::Config(format!("unknown market slug: {market_slug}")))?;
 
-        let token_id = match leg_side {
+        let token_id = match intent.side {
             LegSide::Up => market.up_token_id.clone(),
             LegSide::Down => market.down_token_id.clone(),
         };
 
-        let client_order_id = format!(
-            "{}-{}-{}",
-            market_slug,
-            round_start.to_rfc3339(),
-            match decision {
-                TwoLegDecision::OpenLeg1 { .. } => "leg1",
-                TwoLegDecision::OpenLeg2 { .. } => "leg2",
+        // Only shares the strategy bought in this round can be sold; shorting is not supported.
+        if intent.action == TradeAction::Sell {
+            let available = self.sellable_shares(intent);
+            if intent.shares > available + 1e-9 {
+                return Err(ExecutionError::InsufficientPosition {
+                    requested: intent.shares,
+                    available,
+                });
             }
-        );
-
-        if let Some(p) = expected_profit {
-            info!(
-                market = %market_slug,
-                %round_start,
-                %token_id,
-                side = ?leg_side,
-                shares,
-                price = limit_price,
-                expected_profit = p,
-                "submitting hedge order (Leg2)"
-            );
-        } else {
-            info!(
-                market = %market_slug,
-                %round_start,
-                %token_id,
-                side = ?leg_side,
-                shares,
-                price = limit_price,
-                "submitting directional order (Leg1)"
-            );
         }
 
+        let client_order_id = client_order_id(intent);
+
+        info!(
+            strategy = %intent.strategy,
+            market = %market_slug,
+            round_start = %intent.round_start,
+            %token_id,
+            side = ?intent.side,
+            action = ?intent.action,
+            leg = %intent.label,
+            shares = intent.shares,
+            price = intent.limit_price,
+            expected_profit = ?intent.expected_locked_profit,
+            "submitting order"
+        );
+
         // Emit high-level metrics hook for monitoring.
-        METRICS.record_order_submitted(&market_slug, leg_label);
+        METRICS.record_order_submitted(market_slug, &intent.label);
 
         Ok(OrderRequest {
-            market_slug,
+            market_slug: market_slug.clone(),
             token_id,
-            side: OrderSide::Buy,
-            price: limit_price,
-            size: shares,
+            side: match intent.action {
+                TradeAction::Buy => OrderSide::Buy,
+                TradeAction::Sell => OrderSide::Sell,
+            },
+            price: intent.limit_price,
+            size: intent.shares,
             client_order_id,
             order_type: OrderType::Limit,
-            time_in_force: TimeInForce::Gtc,
+            time_in_force: if intent.immediate_or_cancel {
+                TimeInForce::Ioc
+            } else {
+                TimeInForce::Gtc
+            },
+            neg_risk: market.neg_risk,
         })
     }
-}
-
-/// Extremely simple paper-trading adapter: fills all orders immediately at limit price.
-struct PaperExecutor;
 
-impl PaperExecutor {
-    fn new() -> Self {
-        Self
+    /// Shares of the intent's token the strategy holds in its market and round, less what its
+    /// working sell orders already offer.
+    fn sellable_shares(&self, intent: &OrderIntent) -> f64 {
+        self.intents
+            .iter()
+            .filter(|(_, prev)| {
+                prev.strategy == intent.strategy
+                    && prev.market_slug == intent.market_slug
+                    && prev.round_start == intent.round_start
+                    && prev.side == intent.side
+            })
+            .filter_map(|(id, prev)| self.orders.get(id).map(|order| (prev.action, order)))
+            .map(|(action, order)| match action {
+                TradeAction::Buy => order.filled_size,
+                TradeAction::Sell if order.is_terminal() => -order.filled_size,
+                TradeAction::Sell => -order.request.size,
+            })
+            .sum::<f64>()
+            .max(0.0)
     }
+}
 
-    async fn execute_order(&self, req: &OrderRequest) -> ExecutionResult<Order> {
-        // Simulate small network/venue latency.
-        tokio::time::sleep(Duration::from_millis(5)).await;
+/// Client order ID for an intent, unique per strategy, market, round, leg and attempt.
+///
+/// Prefixed with the strategy instance so engines sharing a market never collide.
+pub(crate) fn client_order_id(intent: &OrderIntent) -> String {
+    format!(
+        "{}-{}-{}-{}-{}",
+        intent.strategy,
+        intent.market_slug,
+        intent.round_start.to_rfc3339(),
+        intent.label,
+        intent.attempt
+    )
+}
 
-        let id = OrderId::new_v4();
-        let mut order = Order::new(id, req.clone());
-        order.status = OrderStatus::Filled;
-        order.filled_size = req.size;
-        order.avg_fill_price = req.price;
+/// Paper-trading adapter: orders rest in a local matching engine fed by the live book.
+struct PaperExecutor {
+    engine: PaperMatchingEngine,
+}
 
-        Ok(order)
+impl PaperExecutor {
+    fn new(config: PaperConfig) -> Self {
+        Self {
+            engine: PaperMatchingEngine::new(config),
+        }
     }
 
-    async fn cancel_order(&self, _id: OrderId) -> ExecutionResult<()> {
-        // Paper mode treats cancellation as always-successful.
-        Ok(())
+    async fn cancel_order(&mut self, id: OrderId) -> ExecutionResult<()> {
+        self.engine.cancel(id)
     }
 
-    async fn refresh_order(&self, id: OrderId) -> ExecutionResult<Order> {
-        // In pure paper mode, everything is filled immediately; synthesize a filled order.
-        let dummy_req = OrderRequest {
-            market_slug: "paper".to_string(),
-            token_id: "paper".to_string(),
-            side: OrderSide::Buy,
-            price: 0.5,
-            size: 0.0,
-            client_order_id: id.to_string(),
-            order_type: OrderType::Limit,
-            time_in_force: TimeInForce::Gtc,
-        };
-        let mut order = Order::new(id, dummy_req);
-        order.status = OrderStatus::Filled;
-        Ok(order)
+    fn refresh_order(&self, id: OrderId) -> ExecutionResult<Order> {
+        self.engine
+            .order(&id)
+            .cloned()
+            .ok_or_else(|| ExecutionError::OrderNotFound(id.to_string()))
     }
 }
 
@@ -397,136 +1232,341 @@ impl PaperExecutor {
 /// response model minimal; it can be extended as we integrate more endpoints.
 struct LiveExecutor {
     clob: ClobClient,
+    builder: OrderBuilder,
+    /// API key that owns the orders, sent as `owner` on placement.
+    api_key: String,
+    signature_type: SignatureType,
+    fee_rate_bps: u32,
 }
 
 impl LiveExecutor {
-    fn new(clob: ClobClient) -> Self {
-        Self { clob }
+    fn new(clob: ClobClient, builder: OrderBuilder, api: &ApiConfig) -> Self {
+        Self {
+            clob,
+            builder,
+            api_key: api.api_key.clone(),
+            signature_type: api.signature_type,
+            fee_rate_bps: api.fee_rate_bps,
+        }
     }
 
-    async fn execute_order(&self, req: &OrderRequest) -> ExecutionResult<Order> {
+    async fn execute_order(&self, req: &OrderRequest, tick_size: f64) -> ExecutionResult<Order> {
         #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
         struct PlaceOrderRequest<'a> {
-            token_id: &'a str,
-            side: &'a str,
-            price: f64,
-            size: f64,
-            client_order_id: &'a str,
-            #[serde(rename = "type")]
+            order: SignedOrder,
+            owner: &'a str,
             order_type: &'a str,
-            time_in_force: &'a str,
         }
 
         #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
         struct PlaceOrderResponse {
-            id: String,
+            #[serde(default)]
+            success: bool,
+            #[serde(default)]
+            error_msg: String,
+            #[serde(default, rename = "orderID")]
+            order_id: String,
+            #[serde(default)]
             status: String,
-            filled_size: Option<f64>,
-            avg_fill_price: Option<f64>,
         }
 
-        let payload = PlaceOrderRequest {
-            token_id: &req.token_id,
+        let args = OrderArgs {
+            token_id: req.token_id.clone(),
             side: match req.side {
-                OrderSide::Buy => "buy",
-                OrderSide::Sell => "sell",
+                OrderSide::Buy => Side::Buy,
+                OrderSide::Sell => Side::Sell,
             },
             price: req.price,
             size: req.size,
-            client_order_id: &req.client_order_id,
-            order_type: "limit",
-            time_in_force: match req.time_in_force {
-                TimeInForce::Gtc => "gtc",
-                TimeInForce::Ioc => "ioc",
-                TimeInForce::Fok => "fok",
+            tick_size,
+            fee_rate_bps: self.fee_rate_bps,
+            nonce: 0,
+            expiration: 0,
+            neg_risk: req.neg_risk,
+        };
+        let order = self.builder.create_order(&args).await?;
+
+        let payload = PlaceOrderRequest {
+            order,
+            owner: &self.api_key,
+            order_type: match req.time_in_force {
+                TimeInForce::Gtc => "GTC",
+                TimeInForce::Ioc => "FAK",
+                TimeInForce::Fok => "FOK",
             },
         };
 
-        let resp: PlaceOrderResponse = self.clob.post_private("/orders", &payload).await?;
+        let resp: PlaceOrderResponse = self.clob.post_private("/order", &payload).await?;
+        if !resp.success {
+            return Err(ExecutionError::Other(format!(
+                "order rejected: {}",
+                resp.error_msg
+            )));
+        }
 
-        let id = resp
-            .id
-            .parse::<OrderId>()
-            .unwrap_or_else(|_| OrderId::new_v4());
+        if resp.order_id.is_empty() {
+            return Err(ExecutionError::Other(
+                "order accepted without a venue order id".to_string(),
+            ));
+        }
 
-        let mut order = Order::new(id, req.clone());
-        order.status = map_status(&resp.status);
-        order.filled_size = resp.filled_size.unwrap_or(0.0);
-        order.avg_fill_price = resp.avg_fill_price.unwrap_or(0.0);
+        // `matched`, `delayed` and `unmatched` acks all leave the order working: what filled
+        // is reported by the trade events that follow.
+        debug!(
+            target: "execution",
+            venue_order_id = %resp.order_id,
+            status = %resp.status,
+            "order placed"
+        );
+        let mut order = Order::new(OrderId::new_v4(), req.clone());
+        order.venue_order_id = Some(resp.order_id);
+        order.status = OrderStatus::Open;
 
         Ok(order)
     }
 
-    async fn cancel_order(&self, id: OrderId) -> ExecutionResult<()> {
-        #[derive(Deserialize)]
-        struct CancelResponse {
-            status: String,
+    async fn cancel_order(&self, venue_id: &str) -> ExecutionResult<()> {
+        #[derive(Serialize)]
+        struct CancelRequest<'a> {
+            #[serde(rename = "orderID")]
+            order_id: &'a str,
         }
 
-        let path = format!("/orders/{id}");
-        // Polymarket CLOB typically uses DELETE for cancellation; treat non-success as error.
-        let resp: CancelResponse =
-            self.clob
-                .post_private::<(), _>(&path, &())
-                .await
-                .map_err(ExecutionError::from)?;
+        let resp: CancelResponse = self
+            .clob
+            .delete_private("/order", Some(&CancelRequest { order_id: venue_id }))
+            .await?;
 
-        if resp.status.to_lowercase() == "canceled" {
+        if resp.canceled.iter().any(|id| id == venue_id) {
             Ok(())
         } else {
+            let reason = resp
+                .not_canceled
+                .get(venue_id)
+                .cloned()
+                .unwrap_or_else(|| "not acknowledged".to_string());
             Err(ExecutionError::Other(format!(
-                "unexpected cancel status {status}",
-                status = resp.status
+                "cancel of {venue_id} failed: {reason}"
             )))
         }
     }
 
-    async fn refresh_order(&self, id: OrderId) -> ExecutionResult<Order> {
+    /// Cancel the given orders in one request; returns the venue IDs that were canceled.
+    async fn cancel_orders(&self, venue_ids: &[String]) -> ExecutionResult<Vec<String>> {
+        let resp: CancelResponse = self.clob.delete_private("/orders", Some(venue_ids)).await?;
+        Ok(resp.canceled)
+    }
+
+    /// Cancel all orders on one token; returns the venue IDs that were canceled.
+    async fn cancel_market(&self, token_id: &str) -> ExecutionResult<Vec<String>> {
+        #[derive(Serialize)]
+        struct CancelMarketRequest<'a> {
+            asset_id: &'a str,
+        }
+
+        let resp: CancelResponse = self
+            .clob
+            .delete_private(
+                "/cancel-market-orders",
+                Some(&CancelMarketRequest { asset_id: token_id }),
+            )
+            .await?;
+        Ok(resp.canceled)
+    }
+
+    async fn cancel_all(&self) -> ExecutionResult<Vec<String>> {
+        let resp: CancelResponse = self
+            .clob
+            .delete_private("/cancel-all", Option::<&()>::None)
+            .await?;
+        Ok(resp.canceled)
+    }
+
+    async fn refresh_order(&self, venue_id: &str) -> ExecutionResult<Order> {
+        let path = format!("/data/order/{venue_id}");
+        // Unknown order IDs come back as `null` rather than an error status.
+        let resp: Option<OrderResponse> = self.clob.get_private(&path).await?;
+        resp.ok_or_else(|| ExecutionError::OrderNotFound(venue_id.to_string()))?
+            .into_order()
+    }
+
+    /// Every live order of the account, following `next_cursor` through all pages.
+    async fn open_orders(&self) -> ExecutionResult<Vec<Order>> {
+        let mut orders = Vec::new();
+        let mut cursor = FIRST_CURSOR.to_string();
+        loop {
+            let path = format!("/data/orders?next_cursor={cursor}");
+            let page: OrdersPage = self.clob.get_private(&path).await?;
+            for resp in page.data {
+                orders.push(resp.into_order()?);
+            }
+            if page.next_cursor.is_empty()
+                || page.next_cursor == END_CURSOR
+                || page.next_cursor == cursor
+            {
+                return Ok(orders);
+            }
+            cursor = page.next_cursor;
+        }
+    }
+
+    /// Cash and the balances of `token_ids`.
+    async fn portfolio(&self, token_ids: &[String]) -> ExecutionResult<Portfolio> {
+        let mut portfolio = Portfolio::new(self.balance(None).await?);
+        for token_id in token_ids {
+            portfolio = portfolio.with_position(token_id, self.balance(Some(token_id)).await?);
+        }
+        Ok(portfolio)
+    }
+
+    /// USDC balance, or shares of `token_id` when given, converted from 6-decimal base units.
+    async fn balance(&self, token_id: Option<&str>) -> ExecutionResult<f64> {
         #[derive(Deserialize)]
-        struct OrderResponse {
-            id: String,
-            status: String,
-            filled_size: Option<f64>,
-            avg_fill_price: Option<f64>,
-            token_id: String,
-            price: f64,
-            size: f64,
+        struct BalanceResponse {
+            balance: String,
         }
 
-        let path = format!("/orders/{id}");
-        let resp: OrderResponse = self.clob.get_private(&path).await?;
+        let signature_type = self.signature_type.as_u8();
+        let path = match token_id {
+            None => {
+                format!("/balance-allowance?asset_type=COLLATERAL&signature_type={signature_type}")
+            }
+            Some(token_id) => format!(
+                "/balance-allowance?asset_type=CONDITIONAL&token_id={token_id}\
+                 &signature_type={signature_type}"
+            ),
+        };
+        let resp: BalanceResponse = self.clob.get_private(&path).await?;
+        let units: f64 = resp.balance.parse().map_err(|_| {
+            ExecutionError::Other(format!("unparseable balance: {}", resp.balance))
+        })?;
+        Ok(units / 1_000_000.0)
+    }
+}
+
+/// Response shared by every cancel endpoint.
+#[derive(Deserialize)]
+struct CancelResponse {
+    #[serde(default)]
+    canceled: Vec<String>,
+    /// Venue order ID to the reason it could not be canceled.
+    #[serde(default)]
+    not_canceled: HashMap<String, String>,
+}
+
+/// Cursor of the first page of a paginated CLOB listing.
+const FIRST_CURSOR: &str = "MA==";
+/// Cursor the CLOB returns after the last page.
+const END_CURSOR: &str = "LTE=";
+
+/// One page of `/data/orders`.
+#[derive(Debug, Deserialize)]
+struct OrdersPage {
+    #[serde(default)]
+    data: Vec<OrderResponse>,
+    #[serde(default)]
+    next_cursor: String,
+}
 
-        let parsed_id = resp
-            .id
-            .parse::<OrderId>()
-            .unwrap_or_else(|_| OrderId::new_v4());
+/// Order as returned by `/data/order/{id}` and in `/data/orders` pages.
+#[derive(Debug, Deserialize)]
+struct OrderResponse {
+    id: String,
+    status: String,
+    asset_id: String,
+    side: String,
+    #[serde(deserialize_with = "number_from_string")]
+    price: f64,
+    #[serde(deserialize_with = "number_from_string")]
+    original_size: f64,
+    #[serde(default, deserialize_with = "number_from_string")]
+    size_matched: f64,
+    #[serde(default)]
+    order_type: Option<String>,
+}
 
+/// The CLOB sends prices and sizes as decimal strings; plain numbers are accepted too.
+fn number_from_string<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Number {
+        Str(String),
+        Num(f64),
+    }
+
+    match Number::deserialize(deserializer)? {
+        Number::Num(n) => Ok(n),
+        Number::Str(s) => s.trim().parse().map_err(serde::de::Error::custom),
+    }
+}
+
+impl OrderResponse {
+    /// Venue order as a fresh local order; the executor re-keys it if the hash is known and
+    /// fills in the market from the token.
+    fn into_order(self) -> ExecutionResult<Order> {
+        let side = match self.side.to_ascii_uppercase().as_str() {
+            "BUY" => OrderSide::Buy,
+            "SELL" => OrderSide::Sell,
+            other => {
+                return Err(ExecutionError::Other(format!(
+                    "order {} has unknown side {other}",
+                    self.id
+                )))
+            }
+        };
+        let time_in_force = match self.order_type.as_deref().map(str::to_ascii_uppercase) {
+            Some(t) if t == "FOK" => TimeInForce::Fok,
+            Some(t) if t == "FAK" => TimeInForce::Ioc,
+            _ => TimeInForce::Gtc,
+        };
+
+        let local_id = OrderId::new_v4();
         let req = OrderRequest {
-            market_slug: "unknown".to_string(),
-            token_id: resp.token_id,
-            side: OrderSide::Buy, // direction not strictly needed for reconciliation here
-            price: resp.price,
-            size: resp.size,
-            client_order_id: parsed_id.to_string(),
+            market_slug: String::new(),
+            token_id: self.asset_id,
+            side,
+            price: self.price,
+            size: self.original_size,
+            client_order_id: local_id.to_string(),
             order_type: OrderType::Limit,
-            time_in_force: TimeInForce::Gtc,
+            time_in_force,
+            neg_risk: false,
         };
 
-        let mut order = Order::new(parsed_id, req);
-        order.status = map_status(&resp.status);
-        order.filled_size = resp.filled_size.unwrap_or(0.0);
-        order.avg_fill_price = resp.avg_fill_price.unwrap_or(0.0);
-
+        let mut order = Order::new(local_id, req);
+        order.venue_order_id = Some(self.id);
+        order.status = match map_status(&self.status) {
+            OrderStatus::Open if self.size_matched > 0.0 => OrderStatus::PartiallyFilled,
+            status => status,
+        };
+        order.filled_size = self.size_matched;
+        // The venue reports no fill price; resting orders fill at their limit.
+        if self.size_matched > 0.0 {
+            order.avg_fill_price = self.price;
+        }
         Ok(order)
     }
 }
 
+/// Venue ID of a locally known order, which live calls must address it by.
+fn venue_order_id(orders: &HashMap<OrderId, Order>, id: OrderId) -> ExecutionResult<&str> {
+    orders
+        .get(&id)
+        .and_then(|o| o.venue_order_id.as_deref())
+        .ok_or_else(|| ExecutionError::OrderNotFound(format!("{id} has no venue order id")))
+}
+
 fn map_status(s: &str) -> OrderStatus {
     match s.to_lowercase().as_str() {
         "new" => OrderStatus::New,
-        "open" => OrderStatus::Open,
+        "open" | "live" | "delayed" | "unmatched" => OrderStatus::Open,
         "partially_filled" | "partially-filled" => OrderStatus::PartiallyFilled,
-        "filled" => OrderStatus::Filled,
+        "filled" | "matched" => OrderStatus::Filled,
         "canceled" | "cancelled" => OrderStatus::Canceled,
         "rejected" => OrderStatus::Rejected,
         _ => OrderStatus::Failed,
@@ -536,7 +1576,10 @@ fn map_status(s: &str) -> OrderStatus {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{BotConfig, ExecutionConfig, MarketsConfig, PostgresConfig, RedisConfig};
+    use crate::types::{
+        BotConfig, BreakerConfig, ExecutionConfig, KillSwitchConfig, MarketsConfig, PaperConfig,
+        PostgresConfig, RateLimitConfig, RedisConfig, RiskConfig, SignatureType,
+    };
 
     fn dummy_app_config(mode: ExecutionMode) -> AppConfig {
         AppConfig {
@@ -549,11 +1592,16 @@ mod tests {
             api: crate::types::ApiConfig {
                 base_url: "https://clob.polymarket.com".to_string(),
                 ws_url: "wss://clob.polymarket.com/ws".to_string(),
+                user_ws_url: None,
                 api_key: "key".to_string(),
                 api_secret: "secret".to_string(),
                 api_passphrase: "pass".to_string(),
                 wallet_private_key: "priv".to_string(),
                 gnosis_safe_address: Some("0x0000000000000000000000000000000000000001".to_string()),
+                chain_id: 137,
+                signature_type: SignatureType::Eoa,
+                fee_rate_bps: 0,
+                rate_limit: RateLimitConfig::default(),
             },
             bot: BotConfig {
                 shares: 10.0,
@@ -564,6 +1612,16 @@ mod tests {
                 risk_per_trade_pct: 2.0,
                 fee_rate: 0.02,
                 min_profit_usd: 0.1,
+                leg1_up_enabled: true,
+                leg1_down_enabled: true,
+                max_order_retries: 2,
+                hedge_requote_threshold: 0.01,
+                hedge_escalation_secs: 300,
+                hedge_taker_secs: 60,
+                hedge_max_sum: 1.0,
+                stop_loss_pct: None,
+                exit_before_end_secs: None,
+                emergency_hedge_max_loss_usd: None,
             },
             markets: MarketsConfig {
                 markets: vec![crate::types::MarketConfig {
@@ -571,42 +1629,521 @@ mod tests {
                     coin: None,
                     up_token_id: Some("BTC_15M_UP".to_string()),
                     down_token_id: Some("BTC_15M_DOWN".to_string()),
+                    neg_risk: false,
                 }],
+                snapshot_depth: 0,
             },
             execution: ExecutionConfig {
                 mode,
                 max_parallel_orders: 32,
+                max_queued_orders: 256,
+                paper: PaperConfig::default(),
+                breaker: BreakerConfig::default(),
             },
+            risk: RiskConfig::default(),
+            kill_switch: KillSwitchConfig::default(),
+            strategies: vec![],
         }
     }
 
-    #[test]
-    fn circuit_breaker_opens_after_failures() {
-        let mut cb = CircuitBreaker::new(3, Duration::from_secs(60));
-        assert!(cb.allow());
-        cb.on_failure();
-        cb.on_failure();
-        assert!(cb.allow());
-        cb.on_failure();
-        assert!(cb.is_open());
-        assert!(!cb.allow());
-    }
-
     #[test]
     fn map_status_basic() {
         assert_eq!(map_status("new"), OrderStatus::New);
         assert_eq!(map_status("open"), OrderStatus::Open);
+        assert_eq!(map_status("DELAYED"), OrderStatus::Open);
         assert_eq!(map_status("filled"), OrderStatus::Filled);
         assert_eq!(map_status("canceled"), OrderStatus::Canceled);
         assert_eq!(map_status("rejected"), OrderStatus::Rejected);
         assert_eq!(map_status("somethingelse"), OrderStatus::Failed);
     }
 
+    #[test]
+    fn parses_single_order_payload() {
+        // A `/data/order/{id}` reply, in the venue's shape, for a fully matched buy.
+        let resp: Option<OrderResponse> = serde_json::from_str(
+            r#"{"id":"0x5f2d1e1a0c0b7a1d2c7a9b1f0e3d4c5b6a79880e1d2c3b4a5f6e7d8c9b0a1f2e",
+                "status":"MATCHED","owner":"9e1f3c2a-1b7d-4c8e-a2f0-6d5b4c3a2e1f",
+                "maker_address":"0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "market":"0x9c1a7f3e2b5d4c6a8e0f1b3d5c7e9a2b4d6f8a0c2e4b6d8f0a2c4e6b8d0f2a4c",
+                "asset_id":"71321045679252212594626385532706912750332728571942532289631379312455583992563",
+                "side":"BUY","original_size":"10","size_matched":"10","price":"0.47",
+                "associate_trades":["b3a7c1e2-5d4f-4a6b-9c8d-7e6f5a4b3c2d"],"outcome":"Up",
+                "created_at":1704110412,"expiration":"0","order_type":"GTC"}"#,
+        )
+        .unwrap();
+        let order = resp.unwrap().into_order().unwrap();
+        assert_eq!(order.status, OrderStatus::Filled);
+        assert_eq!(order.request.side, OrderSide::Buy);
+        assert!((order.filled_size - 10.0).abs() < 1e-12);
+        assert!((order.avg_fill_price - 0.47).abs() < 1e-12);
+        assert!(order.request.token_id.starts_with("7132104567"));
+
+        let missing: Option<OrderResponse> = serde_json::from_str("null").unwrap();
+        assert!(missing.is_none());
+    }
+
+    #[test]
+    fn parses_paginated_open_orders() {
+        let page: OrdersPage = serde_json::from_str(
+            r#"{"data":[{"id":"0xabc","status":"LIVE","owner":"key","maker_address":"0x1",
+                "market":"0xcond","asset_id":"123","side":"SELL","original_size":"20",
+                "size_matched":"5","price":"0.57","associate_trades":[],"outcome":"Up",
+                "created_at":1704110400,"expiration":"0","order_type":"GTC"}],
+                "next_cursor":"LTE=","limit":100,"count":1}"#,
+        )
+        .unwrap();
+        assert_eq!(page.next_cursor, END_CURSOR);
+
+        let order = page.data.into_iter().next().unwrap().into_order().unwrap();
+        assert_eq!(order.venue_order_id.as_deref(), Some("0xabc"));
+        assert_eq!(order.request.token_id, "123");
+        assert_eq!(order.request.side, OrderSide::Sell);
+        assert!((order.request.price - 0.57).abs() < 1e-12);
+        assert!((order.request.size - 20.0).abs() < 1e-12);
+        assert!((order.filled_size - 5.0).abs() < 1e-12);
+        assert_eq!(order.status, OrderStatus::PartiallyFilled);
+    }
+
     #[test]
     fn build_executor_from_config_paper() {
         let cfg = dummy_app_config(ExecutionMode::Paper);
         let exec = OrderExecutor::from_config(&cfg);
         assert!(exec.is_ok());
     }
-}
 
+    fn intent(side: LegSide, label: &str) -> OrderIntent {
+        OrderIntent {
+            strategy: "two_leg".to_string(),
+            market_slug: "BTC-USD-15MIN".to_string(),
+            round_start: Utc::now(),
+            side,
+            action: TradeAction::Buy,
+            shares: 5.0,
+            limit_price: 0.40,
+            label: label.to_string(),
+            expected_locked_profit: None,
+            attempt: 0,
+            replaces: Vec::new(),
+            immediate_or_cancel: false,
+            reduces_risk: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn replacing_intent_cancels_the_working_order_first() {
+        let cfg = dummy_app_config(ExecutionMode::Paper);
+        let mut exec = OrderExecutor::from_config(&cfg).unwrap();
+        let hedge = intent(LegSide::Down, "leg2");
+        let other_leg = exec.execute_decision(intent(LegSide::Up, "leg1")).await.unwrap();
+        let first = exec.execute_decision(hedge.clone()).await.unwrap();
+
+        let requote = OrderIntent {
+            limit_price: 0.45,
+            attempt: 1,
+            replaces: vec!["leg2".to_string()],
+            ..hedge
+        };
+        let second = exec.execute_decision(requote).await.unwrap();
+
+        assert_eq!(exec.order(&first).unwrap().status, OrderStatus::Canceled);
+        assert_eq!(exec.order(&second).unwrap().status, OrderStatus::Open);
+        assert_eq!(exec.order(&other_leg).unwrap().status, OrderStatus::Open);
+        assert!(exec.order(&second).unwrap().request.client_order_id.ends_with("-leg2-1"));
+        assert_eq!(exec.take_finished(), vec![first]);
+    }
+
+    #[test]
+    fn sells_are_limited_to_the_held_position() {
+        let cfg = dummy_app_config(ExecutionMode::Paper);
+        let mut exec = OrderExecutor::from_config(&cfg).unwrap();
+        let buy = intent(LegSide::Up, "leg1");
+        let req = exec.decision_to_order_request(&buy).unwrap();
+        let mut bought = Order::new(OrderId::new_v4(), req);
+        bought.apply_fill(0.40, 5.0, 0.0);
+        exec.orders.insert(bought.id, bought.clone());
+        exec.intents.insert(bought.id, buy.clone());
+
+        let sell = |shares| OrderIntent {
+            action: TradeAction::Sell,
+            shares,
+            label: "exit".to_string(),
+            immediate_or_cancel: true,
+            reduces_risk: false,
+            ..buy.clone()
+        };
+        assert!(matches!(
+            exec.decision_to_order_request(&sell(6.0)),
+            Err(ExecutionError::InsufficientPosition { available, .. }) if available == 5.0
+        ));
+        // The other token is not held at all.
+        let down = OrderIntent {
+            side: LegSide::Down,
+            ..sell(1.0)
+        };
+        assert!(exec.decision_to_order_request(&down).is_err());
+
+        let req = exec.decision_to_order_request(&sell(5.0)).unwrap();
+        assert_eq!(req.side, OrderSide::Sell);
+        assert_eq!(req.time_in_force, TimeInForce::Ioc);
+
+        // A working sell already offers those shares.
+        let offered = Order::new(OrderId::new_v4(), req);
+        exec.orders.insert(offered.id, offered.clone());
+        exec.intents.insert(offered.id, sell(5.0));
+        assert!(exec.decision_to_order_request(&sell(1.0)).is_err());
+    }
+
+    #[tokio::test]
+    async fn cancel_market_and_cancel_all_pull_resting_paper_orders() {
+        let cfg = dummy_app_config(ExecutionMode::Paper);
+        let mut exec = OrderExecutor::from_config(&cfg).unwrap();
+        let up = exec.execute_decision(intent(LegSide::Up, "leg1")).await.unwrap();
+        let down = exec.execute_decision(intent(LegSide::Down, "leg2")).await.unwrap();
+
+        assert!(exec.cancel_market("ETH-USD-15MIN").await.is_err());
+        let mut canceled = exec.cancel_market("BTC-USD-15MIN").await.unwrap();
+        canceled.sort();
+        let mut expected = vec![up, down];
+        expected.sort();
+        assert_eq!(canceled, expected);
+        assert_eq!(exec.order(&up).unwrap().status, OrderStatus::Canceled);
+
+        let again = exec.execute_decision(intent(LegSide::Up, "leg1")).await.unwrap();
+        assert_eq!(exec.cancel_all().await.unwrap(), vec![again]);
+        assert!(exec.open_orders().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn venue_updates_keep_local_id_and_request() {
+        let cfg = dummy_app_config(ExecutionMode::Paper);
+        let mut exec = OrderExecutor::from_config(&cfg).unwrap();
+        let req = exec.decision_to_order_request(&intent(LegSide::Up, "leg1")).unwrap();
+        let mut submitted = Order::new(OrderId::new_v4(), req);
+        submitted.venue_order_id = Some("0xabc".to_string());
+        exec.venue_ids.insert("0xabc".to_string(), submitted.id);
+        exec.orders.insert(submitted.id, submitted.clone());
+
+        let venue = OrderResponse {
+            id: "0xabc".to_string(),
+            status: "matched".to_string(),
+            asset_id: "BTC_15M_UP".to_string(),
+            side: "BUY".to_string(),
+            price: 0.40,
+            original_size: 5.0,
+            size_matched: 5.0,
+            order_type: None,
+        }
+        .into_order()
+        .unwrap();
+        let merged = exec.merge_backend_order(venue);
+        assert_eq!(merged.id, submitted.id);
+        assert_eq!(merged.request.market_slug, "BTC-USD-15MIN");
+        assert_eq!(merged.status, OrderStatus::Filled);
+        assert_eq!(exec.order(&submitted.id).unwrap().avg_fill_price, 0.40);
+        // The user channel never reported the fill, so it is handed out as missed.
+        let missed = exec.take_missed_fills();
+        assert_eq!(missed.len(), 1);
+        assert_eq!((missed[0].order_id, missed[0].size), (submitted.id, 5.0));
+        assert_eq!(exec.resolve_order_ids(&["0xabc".to_string()]), vec![submitted.id]);
+
+        // Unknown venue orders are adopted under a fresh local ID.
+        let foreign = OrderResponse {
+            id: "0xdef".to_string(),
+            status: "live".to_string(),
+            asset_id: "BTC_15M_DOWN".to_string(),
+            side: "SELL".to_string(),
+            price: 0.55,
+            original_size: 1.0,
+            size_matched: 0.0,
+            order_type: None,
+        }
+        .into_order()
+        .unwrap();
+        let adopted = exec.merge_backend_order(foreign);
+        assert_eq!(adopted.request.market_slug, "BTC-USD-15MIN");
+        assert_eq!(adopted.request.side, OrderSide::Sell);
+        assert_eq!(exec.venue_ids.get("0xdef"), Some(&adopted.id));
+        assert_eq!(exec.orders.len(), 2);
+    }
+
+    #[test]
+    fn user_channel_fills_update_orders_once() {
+        use crate::execution::order::Liquidity;
+        use crate::execution::user_channel::{OrderUpdate, TradeFill};
+
+        let cfg = dummy_app_config(ExecutionMode::Paper);
+        let mut exec = OrderExecutor::from_config(&cfg).unwrap();
+        let req = exec.decision_to_order_request(&intent(LegSide::Up, "leg1")).unwrap();
+        let mut submitted = Order::new(OrderId::new_v4(), req);
+        submitted.status = OrderStatus::Open;
+        submitted.venue_order_id = Some("0xabc".to_string());
+        exec.venue_ids.insert("0xabc".to_string(), submitted.id);
+        exec.orders.insert(submitted.id, submitted.clone());
+
+        let fill = |trade_id: &str, venue_order_id: &str, size: f64| {
+            UserEvent::Fill(TradeFill {
+                trade_id: trade_id.to_string(),
+                venue_order_id: venue_order_id.to_string(),
+                price: 0.40,
+                size,
+                fee_rate_bps: 100.0,
+                liquidity: Liquidity::Maker,
+                ts: Utc::now(),
+            })
+        };
+
+        let fills = exec.on_user_event(fill("t1", "0xabc", 2.0));
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].order_id, submitted.id);
+        assert!((fills[0].fee - 0.008).abs() < 1e-12);
+        // The same trade is re-sent as it moves from MATCHED to MINED to CONFIRMED.
+        assert!(exec.on_user_event(fill("t1", "0xabc", 2.0)).is_empty());
+        // Other accounts' orders in the same trade are ignored.
+        assert!(exec.on_user_event(fill("t1", "0xother", 3.0)).is_empty());
+
+        let order = exec.order(&submitted.id).unwrap();
+        assert_eq!(order.filled_size, 2.0);
+        assert_eq!(order.status, OrderStatus::PartiallyFilled);
+
+        let update = |status| {
+            UserEvent::Order(OrderUpdate {
+                venue_order_id: "0xabc".to_string(),
+                status: Some(status),
+            })
+        };
+        exec.on_user_event(update(OrderStatus::Open));
+        assert_eq!(exec.order(&submitted.id).unwrap().status, OrderStatus::PartiallyFilled);
+        assert!(exec.take_finished().is_empty());
+        exec.on_user_event(update(OrderStatus::Canceled));
+        assert_eq!(exec.order(&submitted.id).unwrap().status, OrderStatus::Canceled);
+        // Terminal orders are handed out once so strategies hear about each outcome once.
+        assert_eq!(exec.take_finished(), vec![submitted.id]);
+        assert!(exec.take_finished().is_empty());
+    }
+
+    #[test]
+    fn failed_trades_take_their_fill_back() {
+        use crate::execution::user_channel::TradeFill;
+
+        let cfg = dummy_app_config(ExecutionMode::Paper);
+        let mut exec = OrderExecutor::from_config(&cfg).unwrap();
+        let req = exec.decision_to_order_request(&intent(LegSide::Up, "leg1")).unwrap();
+        let size = req.size;
+        let mut submitted = Order::new(OrderId::new_v4(), req);
+        submitted.status = OrderStatus::Open;
+        submitted.venue_order_id = Some("0xabc".to_string());
+        exec.venue_ids.insert("0xabc".to_string(), submitted.id);
+        exec.orders.insert(submitted.id, submitted.clone());
+
+        let trade = |trade_id: &str, size: f64| TradeFill {
+            trade_id: trade_id.to_string(),
+            venue_order_id: "0xabc".to_string(),
+            price: 0.40,
+            size,
+            fee_rate_bps: 100.0,
+            liquidity: Liquidity::Taker,
+            ts: Utc::now(),
+        };
+
+        exec.on_user_event(UserEvent::Fill(trade("t1", size)));
+        assert_eq!(exec.take_finished(), vec![submitted.id]);
+
+        let reversed = exec.on_user_event(UserEvent::TradeFailed(trade("t1", size)));
+        assert_eq!(reversed.len(), 1);
+        assert_eq!(reversed[0].size, -size);
+        assert!(reversed[0].fee < 0.0);
+        let order = exec.order(&submitted.id).unwrap();
+        assert_eq!(order.filled_size, 0.0);
+        assert_eq!(order.status, OrderStatus::Canceled);
+        // The order no longer ended filled, so its outcome is handed out again.
+        assert_eq!(exec.take_finished(), vec![submitted.id]);
+        assert!(exec.on_user_event(UserEvent::TradeFailed(trade("t1", size))).is_empty());
+
+        // A trade that fails before its match was seen is never applied.
+        assert!(exec.on_user_event(UserEvent::TradeFailed(trade("t2", 1.0))).is_empty());
+        assert!(exec.on_user_event(UserEvent::Fill(trade("t2", 1.0))).is_empty());
+    }
+
+    #[tokio::test]
+    async fn fills_reported_before_the_placement_ack_are_applied_on_completion() {
+        use crate::execution::order::Liquidity;
+        use crate::execution::user_channel::TradeFill;
+
+        let cfg = dummy_app_config(ExecutionMode::Paper);
+        let mut exec = OrderExecutor::from_config(&cfg).unwrap();
+        let buy = intent(LegSide::Up, "leg1");
+        let req = exec.decision_to_order_request(&buy).unwrap();
+        let pending = Order::new(OrderId::new_v4(), req);
+        exec.orders.insert(pending.id, pending.clone());
+        exec.intents.insert(pending.id, buy);
+        exec.in_flight.insert(pending.id);
+
+        // The venue streams the trade before answering the placement request.
+        let early = exec.on_user_event(UserEvent::Fill(TradeFill {
+            trade_id: "t1".to_string(),
+            venue_order_id: "0xabc".to_string(),
+            price: 0.40,
+            size: 5.0,
+            fee_rate_bps: 0.0,
+            liquidity: Liquidity::Taker,
+            ts: Utc::now(),
+        }));
+        assert!(early.is_empty());
+
+        let mut placed = pending.clone();
+        placed.venue_order_id = Some("0xabc".to_string());
+        let ack = SubmissionAck {
+            canceled: Vec::new(),
+            placed: Ok(placed),
+        };
+        let fills = exec.complete(pending.id, ack).unwrap();
+        assert_eq!(fills.len(), 1);
+        assert_eq!(exec.order(&pending.id).unwrap().status, OrderStatus::Filled);
+        assert!(exec.unmatched_events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn orders_in_flight_during_a_cancel_all_are_canceled_on_ack() {
+        let cfg = dummy_app_config(ExecutionMode::Paper);
+        let mut exec = OrderExecutor::from_config(&cfg).unwrap();
+        let buy = intent(LegSide::Up, "leg1");
+        let req = exec.decision_to_order_request(&buy).unwrap();
+
+        // Stand-in for the order the venue rests once the placement lands.
+        let ExecutionBackend::Paper(paper) = &mut exec.backend else {
+            unreachable!()
+        };
+        let mut pending = paper.engine.submit(&req, Utc::now());
+        pending.status = OrderStatus::New;
+        exec.orders.insert(pending.id, pending.clone());
+        exec.intents.insert(pending.id, buy);
+        exec.in_flight.insert(pending.id);
+        exec.cancel_on_ack.insert(pending.id);
+
+        let mut placed = pending.clone();
+        placed.venue_order_id = Some("0xabc".to_string());
+        let ack = SubmissionAck {
+            canceled: Vec::new(),
+            placed: Ok(placed),
+        };
+        exec.complete(pending.id, ack).unwrap();
+        assert_eq!(exec.order(&pending.id).unwrap().status, OrderStatus::Canceled);
+        assert!(exec.cancel_on_ack.is_empty());
+
+        // A halt's cancel-all flags whatever is still in flight.
+        let req = exec.decision_to_order_request(&intent(LegSide::Down, "leg2")).unwrap();
+        let next = Order::new(OrderId::new_v4(), req);
+        exec.orders.insert(next.id, next.clone());
+        exec.in_flight.insert(next.id);
+        exec.cancel_all().await.unwrap();
+        assert!(exec.cancel_on_ack.contains(&next.id));
+        assert_eq!(exec.order(&next.id).unwrap().status, OrderStatus::New);
+    }
+
+    #[tokio::test]
+    async fn cancels_skip_finished_orders_and_wait_for_in_flight_ones() {
+        let cfg = dummy_app_config(ExecutionMode::Paper);
+        let mut exec = OrderExecutor::from_config(&cfg).unwrap();
+        let finished = exec.execute_decision(intent(LegSide::Up, "leg1")).await.unwrap();
+        exec.cancel_market("BTC-USD-15MIN").await.unwrap();
+        let resting = exec.execute_decision(intent(LegSide::Up, "leg2")).await.unwrap();
+        let req = exec.decision_to_order_request(&intent(LegSide::Down, "leg2")).unwrap();
+        let pending = Order::new(OrderId::new_v4(), req);
+        exec.orders.insert(pending.id, pending.clone());
+        exec.in_flight.insert(pending.id);
+
+        let call = exec.start_cancel_orders(vec![finished, resting, pending.id]);
+        let VenueCall::Done(canceled) = call else {
+            panic!("paper cancels answer on the spot");
+        };
+        assert_eq!(canceled, vec![resting]);
+        assert!(exec.cancel_on_ack.contains(&pending.id));
+        assert!(exec.take_calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn ended_rounds_have_their_resting_orders_canceled() {
+        let cfg = dummy_app_config(ExecutionMode::Paper);
+        let mut exec = OrderExecutor::from_config(&cfg).unwrap();
+        let id = exec.execute_decision(intent(LegSide::Up, "leg1")).await.unwrap();
+        let round_start = exec.intent(&id).unwrap().round_start;
+
+        let earlier = round_start - chrono::Duration::minutes(15);
+        let VenueCall::Done(canceled) = exec.start_cancel_round("BTC-USD-15MIN", earlier) else {
+            panic!("nothing to cancel for an earlier round");
+        };
+        assert!(canceled.is_empty());
+
+        let VenueCall::Done(canceled) = exec.start_cancel_round("BTC-USD-15MIN", round_start)
+        else {
+            panic!("paper cancels answer on the spot");
+        };
+        assert_eq!(canceled, vec![id]);
+        assert_eq!(exec.take_finished(), vec![id]);
+    }
+
+    #[tokio::test]
+    async fn finished_orders_are_dropped_once_reported_and_their_round_is_past() {
+        let cfg = dummy_app_config(ExecutionMode::Paper);
+        let mut exec = OrderExecutor::from_config(&cfg).unwrap();
+        let id = exec.execute_decision(intent(LegSide::Up, "leg1")).await.unwrap();
+        let round_start = exec.intent(&id).unwrap().round_start;
+        let later = round_start + chrono::Duration::seconds(1);
+        exec.cancel_market("BTC-USD-15MIN").await.unwrap();
+
+        // Not handed out yet.
+        assert_eq!(exec.prune_orders(later), 0);
+        assert_eq!(exec.take_finished(), vec![id]);
+        assert_eq!(exec.prune_orders(round_start), 0);
+
+        assert_eq!(exec.prune_orders(later), 1);
+        assert!(exec.order(&id).is_none() && exec.intent(&id).is_none());
+        assert!(exec.finished.is_empty());
+        let ExecutionBackend::Paper(paper) = &exec.backend else {
+            panic!("paper executor");
+        };
+        assert!(paper.engine.order(&id).is_none());
+    }
+
+    #[tokio::test]
+    async fn placement_ack_never_finishes_an_order() {
+        let cfg = dummy_app_config(ExecutionMode::Paper);
+        let mut exec = OrderExecutor::from_config(&cfg).unwrap();
+        let buy = intent(LegSide::Up, "leg1");
+        let req = exec.decision_to_order_request(&buy).unwrap();
+        let pending = Order::new(OrderId::new_v4(), req);
+        exec.orders.insert(pending.id, pending.clone());
+        exec.intents.insert(pending.id, buy);
+        exec.in_flight.insert(pending.id);
+
+        // A `matched` ack comes before the trade that fills the order.
+        let mut placed = pending.clone();
+        placed.venue_order_id = Some("0xabc".to_string());
+        placed.status = OrderStatus::Filled;
+        let ack = SubmissionAck {
+            canceled: Vec::new(),
+            placed: Ok(placed),
+        };
+        assert!(exec.complete(pending.id, ack).unwrap().is_empty());
+        assert_eq!(exec.order(&pending.id).unwrap().status, OrderStatus::Open);
+        assert!(exec.take_finished().is_empty());
+    }
+
+    #[tokio::test]
+    async fn breaker_trip_cancels_the_market_orders() {
+        let cfg = dummy_app_config(ExecutionMode::Paper);
+        let mut exec = OrderExecutor::from_config(&cfg).unwrap();
+        let id = exec.execute_decision(intent(LegSide::Up, "leg1")).await.unwrap();
+
+        let err = ExecutionError::Other("order rejected".to_string());
+        for _ in 0..5 {
+            exec.on_backend_failure("BTC-USD-15MIN", &err);
+        }
+        assert!(exec.breaker_open("BTC-USD-15MIN"));
+        assert_eq!(exec.order(&id).unwrap().status, OrderStatus::Canceled);
+        assert!(matches!(
+            exec.execute_decision(intent(LegSide::Up, "leg1")).await,
+            Err(ExecutionError::CircuitOpen)
+        ));
+    }
+}
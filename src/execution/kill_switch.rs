@@ -0,0 +1,353 @@
+//! Kill switch: stop new order flow without restarting the bot.
+//!
+//! Operators can trip it three ways: a flag file, a Redis key, or the admin HTTP endpoint.
+//! Each source holds its own mode and the most restrictive one wins, so a switch can only be
+//! released through the source that tripped it. Sources report changes to the main loop, which
+//! applies them to the risk layer, records the transition and cancels resting orders on a halt.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, watch};
+use tracing::{debug, info, warn};
+
+use crate::storage::state::RedisStateManager;
+use crate::types::{KillSwitchConfig, RedisConfig};
+
+/// What the execution path lets through.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradingMode {
+    /// Normal trading.
+    #[default]
+    Active,
+    /// No new Leg 1s or other new risk; hedges and exits still go out.
+    HedgeOnly,
+    /// No orders at all.
+    Halted,
+}
+
+impl TradingMode {
+    /// Parse a mode written by an operator. Anything unrecognized halts, so a typo fails safe.
+    pub fn parse(s: &str) -> Self {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "active" | "resume" | "off" => TradingMode::Active,
+            "hedge_only" | "hedge-only" => TradingMode::HedgeOnly,
+            _ => TradingMode::Halted,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TradingMode::Active => "active",
+            TradingMode::HedgeOnly => "hedge_only",
+            TradingMode::Halted => "halted",
+        }
+    }
+}
+
+/// Where a kill switch command came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KillSource {
+    File,
+    Redis,
+    Admin,
+}
+
+impl KillSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            KillSource::File => "file",
+            KillSource::Redis => "redis",
+            KillSource::Admin => "admin",
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            KillSource::File => 0,
+            KillSource::Redis => 1,
+            KillSource::Admin => 2,
+        }
+    }
+}
+
+/// A source asking for a trading mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KillCommand {
+    pub source: KillSource,
+    pub mode: TradingMode,
+}
+
+/// Change of the effective trading mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ModeTransition {
+    pub source: KillSource,
+    pub from: TradingMode,
+    pub to: TradingMode,
+}
+
+/// Mode requested by each source; the effective mode is the most restrictive of them.
+#[derive(Debug, Default)]
+pub struct KillSwitch {
+    modes: [TradingMode; 3],
+}
+
+impl KillSwitch {
+    pub fn mode(&self) -> TradingMode {
+        self.modes.iter().copied().max().unwrap_or_default()
+    }
+
+    /// Record a command; returns the transition if the effective mode changed.
+    pub fn apply(&mut self, command: KillCommand) -> Option<ModeTransition> {
+        let from = self.mode();
+        self.modes[command.source.index()] = command.mode;
+        let to = self.mode();
+        (from != to).then_some(ModeTransition {
+            source: command.source,
+            from,
+            to,
+        })
+    }
+}
+
+/// Spawn the configured kill switch sources.
+///
+/// Returns the command stream for the main loop and the sender it publishes the effective mode
+/// on, which the admin endpoint reports. With no source configured nothing is spawned.
+pub(crate) fn spawn_kill_switch(
+    cfg: &KillSwitchConfig,
+    redis: &RedisConfig,
+) -> (mpsc::Receiver<KillCommand>, watch::Sender<TradingMode>) {
+    let (tx, rx) = mpsc::channel(8);
+    let (mode_tx, mode_rx) = watch::channel(TradingMode::Active);
+
+    if cfg.file.is_some() || cfg.redis_key.is_some() {
+        let file = cfg.file.clone().map(PathBuf::from);
+        let redis_key = cfg.redis_key.clone();
+        let redis = redis.clone();
+        let interval = Duration::from_secs(cfg.poll_secs.max(1));
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            poll_sources(file, redis_key, redis, interval, tx).await;
+        });
+    }
+
+    if let Some(addr) = cfg.admin_addr.clone() {
+        tokio::spawn(async move {
+            if let Err(err) = serve_admin(&addr, tx, mode_rx).await {
+                warn!(
+                    target: "bot",
+                    addr = %addr,
+                    error = %err,
+                    "kill switch admin endpoint failed"
+                );
+            }
+        });
+    }
+
+    (rx, mode_tx)
+}
+
+/// Poll the flag file and Redis key, reporting each source's mode whenever it changes.
+async fn poll_sources(
+    file: Option<PathBuf>,
+    redis_key: Option<String>,
+    redis: RedisConfig,
+    interval: Duration,
+    tx: mpsc::Sender<KillCommand>,
+) {
+    let mut state: Option<RedisStateManager> = None;
+    let mut last_file = TradingMode::Active;
+    let mut last_redis = TradingMode::Active;
+
+    loop {
+        if let Some(path) = &file {
+            // The file's presence trips the switch; its content may narrow it to hedge-only.
+            let mode = match tokio::fs::read_to_string(path).await {
+                Ok(content) if content.trim().is_empty() => TradingMode::Halted,
+                Ok(content) => TradingMode::parse(&content),
+                Err(_) => TradingMode::Active,
+            };
+            if mode != last_file {
+                last_file = mode;
+                let command = KillCommand {
+                    source: KillSource::File,
+                    mode,
+                };
+                if tx.send(command).await.is_err() {
+                    break;
+                }
+            }
+        }
+
+        if let Some(key) = &redis_key {
+            if state.is_none() {
+                match RedisStateManager::new(&redis).await {
+                    Ok(manager) => state = Some(manager),
+                    Err(err) => {
+                        warn!(target: "bot", error = %err, "kill switch cannot reach Redis")
+                    }
+                }
+            }
+            if let Some(manager) = state.as_mut() {
+                match manager.load_flag(key).await {
+                    Ok(value) => {
+                        let mode = value
+                            .as_deref()
+                            .map(TradingMode::parse)
+                            .unwrap_or_default();
+                        if mode != last_redis {
+                            last_redis = mode;
+                            let command = KillCommand {
+                                source: KillSource::Redis,
+                                mode,
+                            };
+                            if tx.send(command).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                    // Keep the last known mode; a Redis outage must not release the switch.
+                    Err(err) => {
+                        warn!(target: "bot", error = %err, "failed to read kill switch key")
+                    }
+                }
+            }
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+    debug!(target: "bot", "kill switch receiver dropped; stopping poller");
+}
+
+/// Minimal HTTP endpoint for operators.
+///
+/// - `GET /kill-switch` reports the effective mode.
+/// - `POST /kill-switch/halt`, `/kill-switch/hedge-only` and `/kill-switch/resume` set the
+///   admin source's mode.
+async fn serve_admin(
+    addr: &str,
+    tx: mpsc::Sender<KillCommand>,
+    mode_rx: watch::Receiver<TradingMode>,
+) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!(target: "bot", addr = %addr, "kill switch admin endpoint listening");
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let tx = tx.clone();
+        let mode_rx = mode_rx.clone();
+        tokio::spawn(async move {
+            if let Err(err) = handle_admin_request(stream, tx, mode_rx).await {
+                debug!(target: "bot", error = %err, "admin request failed");
+            }
+        });
+    }
+}
+
+async fn handle_admin_request(
+    mut stream: TcpStream,
+    tx: mpsc::Sender<KillCommand>,
+    mode_rx: watch::Receiver<TradingMode>,
+) -> std::io::Result<()> {
+    let mut buf = [0u8; 1024];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let mut parts = request.lines().next().unwrap_or_default().split_whitespace();
+    let (method, path) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+
+    let (status, mode) = match admin_route(method, path) {
+        AdminRoute::Status => ("200 OK", Some(*mode_rx.borrow())),
+        AdminRoute::Set(mode) => {
+            let command = KillCommand {
+                source: KillSource::Admin,
+                mode,
+            };
+            match tx.send(command).await {
+                Ok(()) => ("202 Accepted", Some(mode)),
+                Err(_) => ("503 Service Unavailable", None),
+            }
+        }
+        AdminRoute::NotFound => ("404 Not Found", None),
+    };
+
+    let body = match mode {
+        Some(mode) => serde_json::json!({ "mode": mode }).to_string(),
+        None => serde_json::json!({ "error": status }).to_string(),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum AdminRoute {
+    Status,
+    Set(TradingMode),
+    NotFound,
+}
+
+fn admin_route(method: &str, path: &str) -> AdminRoute {
+    match (method, path.trim_end_matches('/')) {
+        ("GET", "/kill-switch") => AdminRoute::Status,
+        ("POST", "/kill-switch/halt") => AdminRoute::Set(TradingMode::Halted),
+        ("POST", "/kill-switch/hedge-only") => AdminRoute::Set(TradingMode::HedgeOnly),
+        ("POST", "/kill-switch/resume") => AdminRoute::Set(TradingMode::Active),
+        _ => AdminRoute::NotFound,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn most_restrictive_source_wins() {
+        let mut switch = KillSwitch::default();
+        let set = |source, mode| KillCommand { source, mode };
+
+        let tripped = switch.apply(set(KillSource::File, TradingMode::HedgeOnly));
+        assert_eq!(
+            tripped,
+            Some(ModeTransition {
+                source: KillSource::File,
+                from: TradingMode::Active,
+                to: TradingMode::HedgeOnly,
+            })
+        );
+        let halted = switch.apply(set(KillSource::Admin, TradingMode::Halted)).unwrap();
+        assert_eq!(halted.to, TradingMode::Halted);
+
+        // Resuming from the admin endpoint leaves the file's hedge-only mode in force.
+        let resumed = switch.apply(set(KillSource::Admin, TradingMode::Active)).unwrap();
+        assert_eq!(resumed.to, TradingMode::HedgeOnly);
+        assert_eq!(switch.apply(set(KillSource::Redis, TradingMode::Active)), None);
+        let cleared = switch.apply(set(KillSource::File, TradingMode::Active)).unwrap();
+        assert_eq!(cleared.to, TradingMode::Active);
+    }
+
+    #[test]
+    fn parses_operator_modes_failing_safe() {
+        assert_eq!(TradingMode::parse(" hedge_only\n"), TradingMode::HedgeOnly);
+        assert_eq!(TradingMode::parse("RESUME"), TradingMode::Active);
+        assert_eq!(TradingMode::parse("halt"), TradingMode::Halted);
+        assert_eq!(TradingMode::parse("stop please"), TradingMode::Halted);
+    }
+
+    #[test]
+    fn routes_admin_requests() {
+        assert_eq!(admin_route("GET", "/kill-switch"), AdminRoute::Status);
+        assert_eq!(
+            admin_route("POST", "/kill-switch/hedge-only/"),
+            AdminRoute::Set(TradingMode::HedgeOnly)
+        );
+        assert_eq!(admin_route("GET", "/kill-switch/halt"), AdminRoute::NotFound);
+    }
+}
@@ -1,42 +1,90 @@
+pub mod book;
+mod breaker;
 mod executor;
+mod kill_switch;
 pub mod order;
+mod paper;
+mod pipeline;
+mod portfolio;
+mod resolution;
+mod risk;
+mod rollover;
+mod strategy_stage;
+mod user_channel;
 
-use std::collections::HashMap;
 use std::time::Duration;
 
 use anyhow::Result;
 use chrono::{DateTime, TimeZone, Utc};
 use serde::Deserialize;
+use tokio::sync::watch;
 use tokio_tungstenite::tungstenite::protocol::Message;
 use tracing::{debug, info, warn};
 
-use crate::client::gamma::{resolve_15m_market, ResolvedMarket};
-use crate::client::websocket::connect_with_retries;
+use crate::client::gamma::{resolve_15m_market, ResolvedMarket, ROUND_SECS};
+use crate::client::websocket::{
+    connect_user_channel, connect_with_retries, Channel, SubscriptionHandle, UserChannelAuth,
+    WebSocketConnection, WsEvent,
+};
 use crate::monitoring::{dashboard, metrics::METRICS};
-use crate::storage::{
-    create_pg_pool,
-    recorder::{SnapshotRecorder, TradeRecorder},
+use crate::storage::{create_pg_pool, models::TradeEventRow, writer::StorageWriter};
+use crate::strategy::{
+    MarketSnapshot, OrderIntent, OrderOutcome, OrderUpdate, StrategyFill, StrategySet,
+    TradeAction,
+};
+use crate::types::{AppConfig, ExecutionMode, KillSwitchConfig, MarketConfig};
+use crate::utils::time::round_end;
+
+pub use executor::{
+    CallReply, ExecutionError, ExecutionResult, OrderExecutor, PendingAck, PendingCall,
+    Submission, SubmissionAck, VenueCall,
 };
-use crate::strategy::{LegSide, MarketSnapshot, TwoLegDecision, TwoLegEngine, TwoLegParams};
-use crate::types::{AppConfig, MarketConfig};
+pub use kill_switch::{KillCommand, KillSource, KillSwitch, ModeTransition, TradingMode};
+pub use portfolio::Portfolio;
+pub use risk::{RiskManager, RiskViolation};
+
+use book::{BookSide, DeltaOutcome, LastTrade, OrderBooks, PriceLevel};
+use executor::client_order_id;
+use order::{Fill, Order, OrderStatus};
+
+use kill_switch::spawn_kill_switch;
+use pipeline::{SubmissionQueue, VenueAnswer};
+use resolution::spawn_resolution_task;
+use rollover::{spawn_rollover_task, MarketRollover};
+use strategy_stage::StrategyStage;
+use user_channel::parse_user_events;
 
-pub use executor::{ExecutionError, ExecutionResult, OrderExecutor};
+#[derive(Debug, Deserialize)]
+struct WsLevel {
+    price: String,
+    size: String,
+}
 
-#[derive(Clone, Copy, Debug, Default)]
-struct SideBook {
-    best_bid: f64,
-    best_ask: f64,
+impl WsLevel {
+    fn parse(&self) -> Option<PriceLevel> {
+        Some(PriceLevel {
+            price: self.price.parse().ok()?,
+            size: self.size.parse().ok()?,
+        })
+    }
 }
 
-#[derive(Clone, Debug, Default)]
-struct MarketBook {
-    up: SideBook,
-    down: SideBook,
+#[derive(Debug, Deserialize)]
+struct BookEvent {
+    asset_id: String,
+    #[serde(default, alias = "buys")]
+    bids: Vec<WsLevel>,
+    #[serde(default, alias = "sells")]
+    asks: Vec<WsLevel>,
+    timestamp: String,
 }
 
 #[derive(Debug, Deserialize)]
 struct PriceChangeItem {
     asset_id: String,
+    price: String,
+    size: String,
+    side: String,
     #[serde(default)]
     best_bid: Option<String>,
     #[serde(default)]
@@ -45,17 +93,24 @@ struct PriceChangeItem {
 
 #[derive(Debug, Deserialize)]
 struct PriceChangeEvent {
-    _event_type: String,
     price_changes: Vec<PriceChangeItem>,
     timestamp: String,
 }
 
 #[derive(Debug, Deserialize)]
-struct BestBidAskEvent {
-    _event_type: String,
+struct TickSizeChangeEvent {
+    asset_id: String,
+    new_tick_size: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastTradePriceEvent {
     asset_id: String,
-    best_bid: String,
-    best_ask: String,
+    price: String,
+    #[serde(default)]
+    size: Option<String>,
+    #[serde(default)]
+    side: Option<String>,
     timestamp: String,
 }
 
@@ -85,230 +140,686 @@ fn parse_millis_timestamp(ts: &str) -> DateTime<Utc> {
         .unwrap_or_else(Utc::now)
 }
 
-fn update_book_and_build_snapshot(
-    books_by_market: &mut HashMap<String, MarketBook>,
-    market_slug: &str,
-    side: LegSide,
-    best_bid: f64,
-    best_ask: f64,
-    ts: DateTime<Utc>,
-) -> Option<MarketSnapshot> {
-    let book = books_by_market
-        .entry(market_slug.to_string())
-        .or_insert_with(MarketBook::default);
-
-    let side_book = match side {
-        LegSide::Up => &mut book.up,
-        LegSide::Down => &mut book.down,
-    };
+fn parse_price(s: Option<&str>) -> f64 {
+    s.and_then(|s| s.parse::<f64>().ok()).unwrap_or(0.0)
+}
 
-    if best_bid > 0.0 {
-        side_book.best_bid = best_bid;
-    }
-    if best_ask > 0.0 {
-        side_book.best_ask = best_ask;
+fn parse_book_side(side: &str) -> Option<BookSide> {
+    match side.to_ascii_uppercase().as_str() {
+        "BUY" => Some(BookSide::Bid),
+        "SELL" => Some(BookSide::Ask),
+        _ => None,
     }
+}
 
-    if book.up.best_bid > 0.0
-        && book.up.best_ask > 0.0
-        && book.down.best_bid > 0.0
-        && book.down.best_ask > 0.0
-    {
-        Some(MarketSnapshot {
-            ts,
-            market_slug: market_slug.to_string(),
-            up_bid: book.up.best_bid,
-            up_ask: book.up.best_ask,
-            down_bid: book.down.best_bid,
-            down_ask: book.down.best_ask,
-        })
-    } else {
-        None
+/// Remember that a market's book changed at `ts`, keeping one entry per market.
+fn touch(touched: &mut Vec<(String, DateTime<Utc>)>, market_slug: &str, ts: DateTime<Utc>) {
+    match touched.iter_mut().find(|(slug, _)| slug == market_slug) {
+        Some(entry) => entry.1 = entry.1.max(ts),
+        None => touched.push((market_slug.to_string(), ts)),
     }
 }
 
-async fn process_snapshot(
-    snapshot: MarketSnapshot,
-    engine: &mut TwoLegEngine,
-    executor: &mut OrderExecutor,
-    snapshot_recorder: &SnapshotRecorder,
-    trade_recorder: &TradeRecorder,
-    available_capital: f64,
+/// Apply a single market-channel event to the books.
+///
+/// Markets whose books changed are added to `touched`; tokens whose book fell out of sync are
+/// added to `resync` so a fresh `book` snapshot can be requested.
+fn apply_ws_event(
+    v: serde_json::Value,
+    books: &mut OrderBooks,
+    touched: &mut Vec<(String, DateTime<Utc>)>,
+    resync: &mut Vec<String>,
 ) -> Result<()> {
-    let market_slug = snapshot.market_slug.clone();
+    let event_type = v
+        .get("event_type")
+        .and_then(|e| e.as_str())
+        .unwrap_or_default()
+        .to_string();
 
-    METRICS.record_snapshot(&market_slug);
+    match event_type.as_str() {
+        "book" => {
+            let ev: BookEvent = serde_json::from_value(v)?;
+            let ts = parse_millis_timestamp(&ev.timestamp);
+            if let Some((market_slug, book)) = books.token_mut(&ev.asset_id) {
+                let bids = ev.bids.iter().filter_map(WsLevel::parse);
+                let asks = ev.asks.iter().filter_map(WsLevel::parse);
+                if book.apply_snapshot(bids, asks, ts.timestamp_millis()) {
+                    touch(touched, market_slug, ts);
+                } else {
+                    debug!(
+                        target: "bot",
+                        asset = %truncate_token(&ev.asset_id),
+                        "ignoring book snapshot older than current state"
+                    );
+                }
+            }
+        }
+        "price_change" => {
+            let ev: PriceChangeEvent = serde_json::from_value(v)?;
+            let ts = parse_millis_timestamp(&ev.timestamp);
 
-    if let Err(err) = snapshot_recorder.record_snapshot(&snapshot).await {
-        warn!(
-            target: "storage",
-            error = %err,
-            market = %market_slug,
-            "failed to record snapshot"
-        );
+            for change in ev.price_changes {
+                let Some(side) = parse_book_side(&change.side) else {
+                    continue;
+                };
+                let Some((market_slug, book)) = books.token_mut(&change.asset_id) else {
+                    continue;
+                };
+
+                let outcome = book.apply_delta(
+                    side,
+                    parse_price(Some(&change.price)),
+                    parse_price(Some(&change.size)),
+                    ts.timestamp_millis(),
+                );
+                let in_sync = match outcome {
+                    DeltaOutcome::Applied => book.verify_top(
+                        parse_price(change.best_bid.as_deref()),
+                        parse_price(change.best_ask.as_deref()),
+                    ),
+                    DeltaOutcome::Diverged => false,
+                    // Already waiting for a snapshot, or an out-of-order delta: nothing to do.
+                    DeltaOutcome::Stale | DeltaOutcome::Unsynced => continue,
+                };
+
+                if in_sync {
+                    touch(touched, market_slug, ts);
+                } else {
+                    warn!(
+                        target: "bot",
+                        market = %market_slug,
+                        asset = %truncate_token(&change.asset_id),
+                        "order book diverged from venue; requesting fresh snapshot"
+                    );
+                    resync.push(change.asset_id);
+                }
+            }
+        }
+        "tick_size_change" => {
+            let ev: TickSizeChangeEvent = serde_json::from_value(v)?;
+            if let Some((market_slug, book)) = books.token_mut(&ev.asset_id) {
+                let tick_size = parse_price(Some(&ev.new_tick_size));
+                if tick_size > 0.0 {
+                    info!(
+                        target: "bot",
+                        market = %market_slug,
+                        asset = %truncate_token(&ev.asset_id),
+                        tick_size,
+                        "tick size changed"
+                    );
+                    book.set_tick_size(tick_size);
+                }
+            }
+        }
+        "last_trade_price" => {
+            let ev: LastTradePriceEvent = serde_json::from_value(v)?;
+            if let Some((_, book)) = books.token_mut(&ev.asset_id) {
+                book.record_trade(LastTrade {
+                    price: parse_price(Some(&ev.price)),
+                    size: parse_price(ev.size.as_deref()),
+                    side: ev.side.as_deref().and_then(parse_book_side),
+                    ts: parse_millis_timestamp(&ev.timestamp),
+                });
+            }
+        }
+        _ => {
+            // `best_bid_ask` and other events are redundant with the L2 book; ignore them.
+        }
     }
 
-    let decisions = engine.on_snapshot(snapshot.clone(), available_capital);
+    Ok(())
+}
+
+/// Most queued market messages folded into one round of snapshots when the loop falls behind.
+const MAX_COALESCED_MESSAGES: usize = 256;
 
-    for decision in decisions {
-        let (round_start, leg_label, expected_locked_profit) = match &decision {
-            TwoLegDecision::OpenLeg1 { round_start, .. } => (*round_start, "leg1", None),
-            TwoLegDecision::OpenLeg2 {
-                round_start,
-                expected_locked_profit,
-                ..
-            } => (*round_start, "leg2", Some(*expected_locked_profit)),
-        };
+/// How long shutdown waits for in-flight placements before canceling everything.
+const SHUTDOWN_ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often live cash and positions are re-read from the venue.
+const BALANCE_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often finished orders of past rounds are dropped from the executor.
+const ORDER_PRUNE_INTERVAL: Duration = Duration::from_secs(ROUND_SECS as u64);
+
+fn process_snapshot(
+    snapshot: MarketSnapshot,
+    strategies: &StrategyStage,
+    storage: &StorageWriter,
+    portfolio: &Portfolio,
+) {
+    METRICS.record_snapshot(&snapshot.market_slug);
+    storage.record_snapshot(&snapshot);
+
+    strategies.on_snapshot(snapshot, |strategy, allocation| {
+        portfolio.strategy_capital(strategy, allocation)
+    });
+}
+
+/// Queue the intents the strategies emitted and submit what may go out now.
+///
+/// Intents of a round that ended while they were on their way are dropped: the strategy has
+/// let go of the round, and its market already trades the next one.
+async fn queue_intents(
+    intents: Vec<OrderIntent>,
+    strategies: &StrategyStage,
+    executor: &mut OrderExecutor,
+    storage: &StorageWriter,
+    portfolio: &mut Portfolio,
+    risk: &mut RiskManager,
+    queue: &mut SubmissionQueue,
+) {
+    let now = Utc::now();
+    for intent in intents {
+        if round_end(intent.round_start) <= now {
+            debug!(
+                target: "execution",
+                strategy = %intent.strategy,
+                market = %intent.market_slug,
+                leg = %intent.label,
+                "dropping intent of an ended round"
+            );
+            continue;
+        }
+        if let Some(intent) = queue.push(intent) {
+            warn!(
+                target: "execution",
+                strategy = %intent.strategy,
+                market = %intent.market_slug,
+                leg = %intent.label,
+                "order queue full; blocking intent"
+            );
+            record_rejected_intent(storage, now, &intent).await;
+            strategies
+                .on_order_update(OrderUpdate::from_intent(&intent, OrderOutcome::Blocked, 0.0))
+                .await;
+        }
+    }
+    dispatch_orders(now, queue, executor, strategies, storage, portfolio, risk).await;
+}
 
-        let market_for_trade = match &decision {
-            TwoLegDecision::OpenLeg1 { market_slug, .. }
-            | TwoLegDecision::OpenLeg2 { market_slug, .. } => market_slug.clone(),
+/// Submit every queued intent that may go out now.
+///
+/// Risk limits are checked at submission, against the book as it stands then. Paper orders
+/// are accepted on the spot; live placements run on their own tasks and report back through
+/// the queue.
+async fn dispatch_orders(
+    ts: DateTime<Utc>,
+    queue: &mut SubmissionQueue,
+    executor: &mut OrderExecutor,
+    strategies: &StrategyStage,
+    storage: &StorageWriter,
+    portfolio: &mut Portfolio,
+    risk: &mut RiskManager,
+) {
+    while let Some(intent) = queue.next_ready() {
+        // Limits may shrink the intent; strategies learn the final size from the update.
+        let intent = match risk.check(intent.clone(), ts) {
+            Ok(intent) => intent,
+            Err(_) => {
+                record_rejected_intent(storage, ts, &intent).await;
+                strategies
+                    .on_order_update(OrderUpdate::from_intent(&intent, OrderOutcome::Blocked, 0.0))
+                    .await;
+                continue;
+            }
         };
+        let submitted = intent.clone();
 
-        match executor.execute_decision(decision).await {
-            Ok(order_id) => {
-                if let Some(order) = executor.order(&order_id).cloned() {
-                    let side_str = match order.request.side {
-                        order::OrderSide::Buy => "buy",
-                        order::OrderSide::Sell => "sell",
-                    };
-                    let status_str = format!("{:?}", order.status).to_lowercase();
-
-                    if let Err(err) = trade_recorder
-                        .record_trade(
-                            snapshot.ts,
-                            &market_for_trade,
-                            round_start,
-                            leg_label,
-                            &order.request.client_order_id,
-                            side_str,
-                            order.avg_fill_price,
-                            order.filled_size,
-                            &status_str,
-                            expected_locked_profit,
-                        )
-                        .await
-                    {
-                        warn!(
-                            target: "storage",
-                            error = %err,
-                            market = %market_for_trade,
-                            "failed to record trade"
-                        );
-                    }
+        match executor.submit(intent) {
+            Ok(Submission { id, ack }) => {
+                let (Some(order), Some(intent)) = (executor.order(&id), executor.intent(&id))
+                else {
+                    continue;
+                };
+                // Working orders count against cash and limits from the moment they are sent.
+                portfolio.on_order_accepted(&intent.strategy, order);
+                risk.on_order_accepted(intent, order, ts);
+                match ack {
+                    Some(ack) => queue.start(intent, id, ack),
+                    None => report_accepted(ts, id, executor, strategies, storage).await,
                 }
             }
             Err(err) => {
                 warn!(
                     target: "execution",
                     error = %err,
-                    market = %market_for_trade,
+                    strategy = %submitted.strategy,
+                    market = %submitted.market_slug,
                     "failed to execute decision"
                 );
+                record_rejected_intent(storage, ts, &submitted).await;
+                let outcome = match err {
+                    ExecutionError::CircuitOpen => OrderOutcome::Blocked,
+                    _ => OrderOutcome::Rejected,
+                };
+                strategies
+                    .on_order_update(OrderUpdate::from_intent(&submitted, outcome, 0.0))
+                    .await;
             }
         }
     }
+    // Orders replaced by a requote were canceled along the way.
+    report_finished_orders(ts, executor, strategies, storage, portfolio, risk).await;
+}
 
-    Ok(())
+/// Tell the owning strategy its order reached the book and record the submission.
+async fn report_accepted(
+    ts: DateTime<Utc>,
+    id: order::OrderId,
+    executor: &OrderExecutor,
+    strategies: &StrategyStage,
+    storage: &StorageWriter,
+) {
+    let (Some(order), Some(intent)) = (executor.order(&id), executor.intent(&id)) else {
+        return;
+    };
+    strategies
+        .on_order_update(OrderUpdate::from_intent(intent, OrderOutcome::Accepted, 0.0))
+        .await;
+    record_order_event(storage, ts, order, intent, order.avg_fill_price, order.filled_size).await;
 }
 
-async fn handle_ws_text(
+/// Apply the venue's answer to a live submission.
+///
+/// A placed order is reported as accepted, followed by any fills the user channel delivered
+/// while it was in flight; a failed one is reported as rejected with the finished orders.
+async fn handle_ack(
+    id: order::OrderId,
+    ack: SubmissionAck,
+    executor: &mut OrderExecutor,
+    strategies: &StrategyStage,
+    storage: &StorageWriter,
+    portfolio: &mut Portfolio,
+    risk: &mut RiskManager,
+) {
+    match executor.complete(id, ack) {
+        Ok(fills) => {
+            report_accepted(Utc::now(), id, executor, strategies, storage).await;
+            handle_fills(fills, executor, strategies, storage, portfolio, risk).await;
+        }
+        Err(err) => {
+            if let Some(intent) = executor.intent(&id) {
+                warn!(
+                    target: "execution",
+                    error = %err,
+                    strategy = %intent.strategy,
+                    market = %intent.market_slug,
+                    "failed to execute decision"
+                );
+            }
+            report_finished_orders(Utc::now(), executor, strategies, storage, portfolio, risk)
+                .await;
+        }
+    }
+}
+
+/// Tell strategies about orders that can no longer fill, so they can retry or move on, and
+/// release the cash they reserved.
+///
+/// Orders that ended without filling completely are recorded in `trade_events` with the
+/// size left unfilled; fills are recorded as they happen.
+async fn report_finished_orders(
+    ts: DateTime<Utc>,
+    executor: &mut OrderExecutor,
+    strategies: &StrategyStage,
+    storage: &StorageWriter,
+    portfolio: &mut Portfolio,
+    risk: &mut RiskManager,
+) {
+    for id in executor.take_finished() {
+        let (Some(order), Some(intent)) = (executor.order(&id), executor.intent(&id)) else {
+            continue;
+        };
+        portfolio.on_order_finished(order);
+        risk.on_order_finished(intent, order);
+        let outcome = match order.status {
+            OrderStatus::Filled => OrderOutcome::Filled,
+            OrderStatus::Canceled => OrderOutcome::Canceled,
+            _ => OrderOutcome::Rejected,
+        };
+        if outcome != OrderOutcome::Filled {
+            let unfilled = (order.request.size - order.filled_size).max(0.0);
+            record_order_event(storage, ts, order, intent, order.request.price, unfilled).await;
+        }
+        strategies
+            .on_order_update(OrderUpdate::from_intent(intent, outcome, order.filled_size))
+            .await;
+    }
+}
+
+/// Persist an intent the backend refused before an order existed for it.
+async fn record_rejected_intent(storage: &StorageWriter, ts: DateTime<Utc>, intent: &OrderIntent) {
+    let side_str = match intent.action {
+        TradeAction::Buy => "buy",
+        TradeAction::Sell => "sell",
+    };
+    storage
+        .record_trade(TradeEventRow {
+            ts,
+            market_slug: intent.market_slug.clone(),
+            round_start: intent.round_start,
+            leg: intent.label.clone(),
+            client_order_id: client_order_id(intent),
+            side: side_str.to_string(),
+            price: intent.limit_price,
+            size: intent.shares,
+            status: "rejected".to_string(),
+            expected_locked_profit: intent.expected_locked_profit,
+        })
+        .await;
+}
+
+/// Persist an order event (submission or fill) to `trade_events`.
+async fn record_order_event(
+    storage: &StorageWriter,
+    ts: DateTime<Utc>,
+    order: &Order,
+    intent: &OrderIntent,
+    price: f64,
+    size: f64,
+) {
+    let side_str = match order.request.side {
+        order::OrderSide::Buy => "buy",
+        order::OrderSide::Sell => "sell",
+    };
+    storage
+        .record_trade(TradeEventRow {
+            ts,
+            market_slug: intent.market_slug.clone(),
+            round_start: intent.round_start,
+            leg: intent.label.clone(),
+            client_order_id: order.request.client_order_id.clone(),
+            side: side_str.to_string(),
+            price,
+            size,
+            status: format!("{:?}", order.status).to_lowercase(),
+            expected_locked_profit: intent.expected_locked_profit,
+        })
+        .await;
+}
+
+/// Record fills reported by the execution backend and hand them to the owning strategies,
+/// followed by the outcome of every order that finished along the way.
+async fn handle_fills(
+    fills: Vec<Fill>,
+    executor: &mut OrderExecutor,
+    strategies: &StrategyStage,
+    storage: &StorageWriter,
+    portfolio: &mut Portfolio,
+    risk: &mut RiskManager,
+) {
+    for fill in fills {
+        let (Some(order), Some(intent)) =
+            (executor.order(&fill.order_id), executor.intent(&fill.order_id))
+        else {
+            continue;
+        };
+        info!(
+            target: "execution",
+            strategy = %intent.strategy,
+            market = %intent.market_slug,
+            leg = %intent.label,
+            price = fill.price,
+            size = fill.size,
+            fee = fill.fee,
+            liquidity = ?fill.liquidity,
+            status = ?order.status,
+            "order filled"
+        );
+        portfolio.on_fill(&intent.strategy, order, &fill);
+        risk.on_fill(intent, order, &fill);
+        record_order_event(storage, fill.ts, order, intent, fill.price, fill.size).await;
+        strategies
+            .on_fill(StrategyFill::from_intent(intent, fill.price, fill.size, fill.ts))
+            .await;
+    }
+    report_finished_orders(Utc::now(), executor, strategies, storage, portfolio, risk).await;
+}
+
+/// Apply order and trade events from the user channel and report resulting fills.
+async fn handle_user_text(
     text: &str,
-    asset_to_market: &HashMap<String, (String, LegSide)>,
-    books_by_market: &mut HashMap<String, MarketBook>,
-    engine: &mut TwoLegEngine,
     executor: &mut OrderExecutor,
-    snapshot_recorder: &SnapshotRecorder,
-    trade_recorder: &TradeRecorder,
-    available_capital: f64,
-) -> Result<()> {
+    strategies: &StrategyStage,
+    storage: &StorageWriter,
+    portfolio: &mut Portfolio,
+    risk: &mut RiskManager,
+) {
+    let fills = parse_user_events(text)
+        .into_iter()
+        .flat_map(|event| executor.on_user_event(event))
+        .collect();
+    handle_fills(fills, executor, strategies, storage, portfolio, risk).await;
+}
+
+/// Next event from the user channel, or never when there is no user connection.
+async fn recv_user_event(conn: &mut Option<WebSocketConnection>) -> Option<WsEvent> {
+    match conn {
+        Some(conn) => conn.receiver().recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Apply one market-channel message to the books, collecting what changed.
+fn apply_ws_text(
+    text: &str,
+    books: &mut OrderBooks,
+    touched: &mut Vec<(String, DateTime<Utc>)>,
+    resync: &mut Vec<String>,
+) {
     let v: serde_json::Value = match serde_json::from_str(text) {
         Ok(v) => v,
         Err(_) => {
             // Ignore non-JSON messages such as raw PING/PONG echoes.
-            return Ok(());
+            return;
         }
     };
 
-    let event_type = v
-        .get("event_type")
-        .and_then(|e| e.as_str())
-        .unwrap_or_default();
+    // The initial subscription reply batches `book` snapshots into a JSON array.
+    let events = match v {
+        serde_json::Value::Array(items) => items,
+        other => vec![other],
+    };
 
-    match event_type {
-        "price_change" => {
-            let ev: PriceChangeEvent = serde_json::from_value(v)?;
-            let ts = parse_millis_timestamp(&ev.timestamp);
+    // One malformed event must not cost the rest of the batch.
+    for ev in events {
+        if let Err(err) = apply_ws_event(ev, books, touched, resync) {
+            warn!(target: "bot", error = %err, "skipping malformed market event");
+        }
+    }
+}
 
-            for change in ev.price_changes {
-                let best_bid = change
-                    .best_bid
-                    .as_deref()
-                    .and_then(|s| s.parse::<f64>().ok())
-                    .unwrap_or(0.0);
-                let best_ask = change
-                    .best_ask
-                    .as_deref()
-                    .and_then(|s| s.parse::<f64>().ok())
-                    .unwrap_or(0.0);
-
-                if let Some((market_slug, side)) = asset_to_market.get(&change.asset_id) {
-                    if let Some(snapshot) = update_book_and_build_snapshot(
-                        books_by_market,
-                        market_slug,
-                        *side,
-                        best_bid,
-                        best_ask,
-                        ts,
-                    ) {
-                        process_snapshot(
-                            snapshot,
-                            engine,
-                            executor,
-                            snapshot_recorder,
-                            trade_recorder,
-                            available_capital,
-                        )
-                        .await?;
-                    }
-                }
+/// Apply `first` and every market message already queued behind it to the books.
+///
+/// When the loop falls behind the feed, intermediate states of a book are never looked at:
+/// the backlog is folded into one snapshot per market. Returns how many messages were
+/// applied.
+fn apply_queued_messages(
+    first: &str,
+    inbound_rx: &mut tokio::sync::mpsc::Receiver<WsEvent>,
+    books: &mut OrderBooks,
+    touched: &mut Vec<(String, DateTime<Utc>)>,
+    resync: &mut Vec<String>,
+) -> usize {
+    let mut apply = |text: &str, books: &mut OrderBooks| {
+        apply_ws_text(text, books, touched, resync);
+    };
+
+    apply(first, books);
+    let mut applied = 1;
+    while applied < MAX_COALESCED_MESSAGES {
+        match inbound_rx.try_recv() {
+            Ok(WsEvent::Message(Message::Text(text))) => {
+                apply(&text, books);
+                applied += 1;
             }
-        }
-        "best_bid_ask" => {
-            let ev: BestBidAskEvent = serde_json::from_value(v)?;
-            let ts = parse_millis_timestamp(&ev.timestamp);
-            let best_bid = ev.best_bid.parse::<f64>().unwrap_or(0.0);
-            let best_ask = ev.best_ask.parse::<f64>().unwrap_or(0.0);
-
-            if let Some((market_slug, side)) = asset_to_market.get(&ev.asset_id) {
-                if let Some(snapshot) = update_book_and_build_snapshot(
-                    books_by_market,
-                    market_slug,
-                    *side,
-                    best_bid,
-                    best_ask,
-                    ts,
-                ) {
-                    process_snapshot(
-                        snapshot,
-                        engine,
-                        executor,
-                        snapshot_recorder,
-                        trade_recorder,
-                        available_capital,
-                    )
-                    .await?;
-                }
+            Ok(WsEvent::Message(_)) => {}
+            Ok(WsEvent::Reconnected) => {
+                warn!(target: "bot", "websocket reconnected; resetting order books");
+                books.invalidate_all();
             }
+            Err(_) => break,
         }
-        _ => {
-            // Ignore other event types for now (book, tick_size_change, last_trade_price, etc.).
+    }
+    applied
+}
+
+/// React to changed books: resubscribe diverged tokens, match resting paper orders, then hand
+/// the strategies one fresh snapshot per touched market.
+#[allow(clippy::too_many_arguments)]
+async fn on_books_changed(
+    touched: Vec<(String, DateTime<Utc>)>,
+    mut resync: Vec<String>,
+    books: &OrderBooks,
+    subscriptions: &SubscriptionHandle,
+    strategies: &StrategyStage,
+    executor: &mut OrderExecutor,
+    storage: &StorageWriter,
+    portfolio: &mut Portfolio,
+    risk: &mut RiskManager,
+) -> Result<()> {
+    if !resync.is_empty() {
+        resync.sort();
+        resync.dedup();
+        subscriptions.resubscribe(resync)?;
+    }
+
+    // Resting paper orders match against the updated books before strategies react.
+    let fills = executor.on_market_data(books, Utc::now());
+    handle_fills(fills, executor, strategies, storage, portfolio, risk).await;
+
+    for (market_slug, ts) in touched {
+        if let Some(snapshot) = books.snapshot(&market_slug, ts) {
+            process_snapshot(snapshot, strategies, storage, portfolio);
         }
     }
 
     Ok(())
 }
 
+/// Start re-reading live cash and the balances of every traded or held token from the venue.
+fn start_balance_refresh(executor: &OrderExecutor, portfolio: &Portfolio, queue: &SubmissionQueue) {
+    let mut token_ids = executor.token_ids();
+    for token_id in portfolio.held_tokens() {
+        if !token_ids.contains(&token_id) {
+            token_ids.push(token_id);
+        }
+    }
+    if let Some(call) = executor.start_venue_portfolio(token_ids) {
+        queue.start_call(call);
+    }
+}
+
+/// Move markets over to a new 15m round: re-point asset IDs, reset their books, drop intents
+/// still queued for the ended round, cancel its resting orders, update the executor and shift
+/// the WebSocket subscription from the expired tokens to the new ones.
+async fn apply_rollover(
+    rollover: MarketRollover,
+    books: &mut OrderBooks,
+    executor: &mut OrderExecutor,
+    strategies: &StrategyStage,
+    risk: &mut RiskManager,
+    queue: &mut SubmissionQueue,
+    subscriptions: &SubscriptionHandle,
+) -> Result<()> {
+    let mut subscribe = Vec::new();
+    let mut unsubscribe = Vec::new();
+
+    let ended_round = Utc
+        .timestamp_opt(rollover.round_ts - ROUND_SECS, 0)
+        .single()
+        .ok_or_else(|| anyhow::anyhow!("invalid round timestamp {}", rollover.round_ts))?;
+
+    for m in rollover.markets {
+        strategies.on_round_end(&m.slug, ended_round).await;
+        // The result is not published yet: what the round still holds is marked at its last
+        // mids, read before the books move to the new tokens.
+        let mids = books
+            .snapshot(&m.slug, Utc::now())
+            .map(|snapshot| [snapshot.mid_up(), snapshot.mid_down()]);
+        risk.on_round_end(&m.slug, ended_round, mids, Utc::now());
+        let dropped = queue.drop_ended_round(&m.slug, ended_round);
+        if dropped > 0 {
+            info!(
+                target: "bot",
+                slug = %m.slug,
+                dropped,
+                "dropped queued orders of the ended round"
+            );
+        }
+        // Resting orders of the ended round can no longer fill into anything useful and would
+        // hold their reservations forever.
+        if let VenueCall::Pending(call) = executor.start_cancel_round(&m.slug, ended_round) {
+            queue.start_call(call);
+        }
+        if let Some(prev) = executor.replace_market(m.clone()) {
+            if prev.up_token_id == m.up_token_id && prev.down_token_id == m.down_token_id {
+                continue;
+            }
+        }
+        unsubscribe.extend(books.insert_market(&m));
+
+        info!(
+            target: "bot",
+            slug = %m.slug,
+            round_ts = rollover.round_ts,
+            up_token_prefix = %truncate_token(&m.up_token_id),
+            down_token_prefix = %truncate_token(&m.down_token_id),
+            "market rolled over to new round"
+        );
+        subscribe.push(m.up_token_id);
+        subscribe.push(m.down_token_id);
+    }
+
+    // Subscribe to the new tokens before dropping the old ones so the feed never goes quiet.
+    subscriptions.subscribe(subscribe)?;
+    subscriptions.unsubscribe(unsubscribe)?;
+
+    Ok(())
+}
+
+/// Put a kill switch transition into effect: gate the risk layer, pull resting orders on a
+/// halt if configured, and record the change in `audit_events`.
+///
+/// A live cancel-all runs on its own task; what it canceled is reported when it answers.
+async fn apply_trading_mode(
+    transition: ModeTransition,
+    cfg: &KillSwitchConfig,
+    risk: &mut RiskManager,
+    executor: &mut OrderExecutor,
+    queue: &mut SubmissionQueue,
+    storage: &StorageWriter,
+    mode_tx: &watch::Sender<TradingMode>,
+) {
+    warn!(
+        target: "bot",
+        source = transition.source.as_str(),
+        from = transition.from.as_str(),
+        to = transition.to.as_str(),
+        "kill switch changed trading mode"
+    );
+    risk.set_trading_mode(transition.to);
+    mode_tx.send_replace(transition.to);
+
+    let cancel_all = transition.to == TradingMode::Halted && cfg.cancel_on_halt;
+    if cancel_all {
+        if let VenueCall::Pending(call) = executor.start_cancel_all() {
+            queue.start_call(call);
+        }
+    }
+
+    let detail = serde_json::json!({
+        "source": transition.source.as_str(),
+        "from": transition.from,
+        "to": transition.to,
+        "cancel_all": cancel_all,
+    });
+    storage
+        .record_audit_event(Utc::now(), "kill_switch", detail)
+        .await;
+}
+
 /// Resolve markets to token IDs: from Gamma API for 15m (when `coin` is set), else from config.
 async fn resolve_markets(
     http: &reqwest::Client,
@@ -357,6 +868,7 @@ async fn resolve_markets(
                 slug: m.slug.clone(),
                 up_token_id: up.clone(),
                 down_token_id: down.clone(),
+                neg_risk: m.neg_risk,
             });
         } else {
             warn!(
@@ -371,9 +883,12 @@ async fn resolve_markets(
 
 /// Entrypoint used by `main.rs` to start the trading bot.
 ///
-/// This wires together WebSocket ingestion, strategy engine, execution, storage,
-/// and monitoring into a single event-driven loop. For 15m markets, set `coin` in config
-/// (e.g. "btc", "eth", "sol") to resolve token IDs from the Gamma API at startup.
+/// This wires together WebSocket ingestion, strategy engine, execution, storage, and
+/// monitoring. The WebSocket client, the strategies, live order placements and cancels, and
+/// Postgres writes run on their own tasks, joined to the event loop by bounded channels; the
+/// loop itself keeps the books, portfolio, risk and order state. For 15m markets, set `coin` in config
+/// (e.g. "btc", "eth", "sol") to resolve token IDs from the Gamma API at startup; those
+/// markets are then rolled over to the next round automatically at every 15m boundary.
 pub async fn run_bot(cfg: AppConfig) -> anyhow::Result<()> {
     info!(target: "bot", "run_bot starting");
 
@@ -394,19 +909,32 @@ pub async fn run_bot(cfg: AppConfig) -> anyhow::Result<()> {
     }
     info!(target: "bot", count = resolved.len(), "markets resolved");
 
+    // Pre-resolve upcoming rounds for dynamic 15m markets so token IDs never go stale.
+    let mut rollover_rx = spawn_rollover_task(http.clone(), &cfg.markets.markets);
+    // Results of ended rounds, which settle what they still hold.
+    let mut resolution_rx = spawn_resolution_task(http.clone(), &cfg.markets.markets);
+
     // Storage backends.
     info!(target: "bot", "connecting to Postgres");
     let pool = create_pg_pool(&cfg.postgres).await?;
     info!(target: "bot", "Postgres connected");
-    let snapshot_recorder = SnapshotRecorder::new(pool.clone());
-    let trade_recorder = TradeRecorder::new(pool.clone());
+    let storage = StorageWriter::spawn(pool);
 
-    // Strategy engine.
-    let params = TwoLegParams::from(&cfg.bot);
-    let mut engine = TwoLegEngine::new(params);
+    // Strategy engines, each sized against its own capital allocation, on a task of their own.
+    let (strategies, mut intents_rx) =
+        StrategyStage::spawn(StrategySet::from_config(&cfg.bot, &cfg.strategies)?);
+    info!(
+        target: "bot",
+        strategies = ?strategies.names().collect::<Vec<_>>(),
+        "strategies initialized"
+    );
 
     // Execution engine (paper or live) using resolved markets.
     let mut executor = OrderExecutor::from_config_and_resolved(&cfg, resolved.clone())?;
+    let mut queue = SubmissionQueue::new(
+        cfg.execution.max_parallel_orders,
+        cfg.execution.max_queued_orders,
+    );
     let mode = match cfg.execution.mode {
         crate::types::ExecutionMode::Paper => "paper",
         crate::types::ExecutionMode::Live => "live",
@@ -420,14 +948,10 @@ pub async fn run_bot(cfg: AppConfig) -> anyhow::Result<()> {
         "execution engine initialized"
     );
 
-    // Map asset IDs to (market_slug, leg side) using resolved markets.
-    let mut asset_to_market: HashMap<String, (String, LegSide)> = HashMap::new();
-    let mut books_by_market: HashMap<String, MarketBook> = HashMap::new();
-
+    // L2 books per market, keyed by asset ID via the resolved markets.
+    let mut books = OrderBooks::new(cfg.markets.snapshot_depth);
     for m in &resolved {
-        asset_to_market.insert(m.up_token_id.clone(), (m.slug.clone(), LegSide::Up));
-        asset_to_market.insert(m.down_token_id.clone(), (m.slug.clone(), LegSide::Down));
-        books_by_market.insert(m.slug.clone(), MarketBook::default());
+        books.insert_market(m);
     }
 
     // WebSocket ingest.
@@ -438,41 +962,280 @@ pub async fn run_bot(cfg: AppConfig) -> anyhow::Result<()> {
         "connecting to Polymarket market WebSocket"
     );
 
-    let mut conn = connect_with_retries(ws_url);
-    let sender = conn.sender();
-    let inbound_rx = conn.receiver();
+    let mut conn = connect_with_retries(ws_url, Channel::Market);
+    let subscriptions = conn.subscriptions();
 
     let assets: Vec<String> = resolved
         .iter()
         .flat_map(|m| [m.up_token_id.clone(), m.down_token_id.clone()])
         .collect();
 
-    let sub = serde_json::json!({
-        "assets_ids": assets,
-        "type": "market",
-        "custom_feature_enabled": true
-    });
-    let sub_payload = sub.to_string();
-    debug!(target: "bot", assets_count = assets.len(), "sending WebSocket subscription");
-    if let Err(err) = sender.send(Message::Text(sub_payload.clone())) {
-        return Err(anyhow::anyhow!(format!(
-            "failed to send market subscription: {err}"
-        )));
+    // The connection replays its subscription set on every (re)connect.
+    debug!(target: "bot", assets_count = assets.len(), "subscribing to market channel");
+    subscriptions.subscribe(assets)?;
+    let inbound_rx = conn.receiver();
+
+    // Live orders are filled and canceled on the venue; their updates arrive on the
+    // authenticated user channel. Paper fills come from the local matching engine instead.
+    let mut user_conn = match cfg.execution.mode {
+        ExecutionMode::Live => {
+            let url = cfg.api.user_channel_url()?;
+            info!(target: "bot", ws_url = %url, "connecting to Polymarket user WebSocket");
+            Some(connect_user_channel(
+                url,
+                UserChannelAuth {
+                    api_key: cfg.api.api_key.clone(),
+                    secret: cfg.api.api_secret.clone(),
+                    passphrase: cfg.api.api_passphrase.clone(),
+                },
+            ))
+        }
+        ExecutionMode::Paper => None,
+    };
+
+    // Signed requests carry a timestamp the venue checks against its own clock.
+    if let Err(err) = executor.sync_clock().await {
+        warn!(target: "bot", error = %err, "server time sync failed; signing with local clock");
     }
 
-    // Until we have a proper runtime risk/state manager, use a fixed notional
-    // capital assumption for sizing.
-    let available_capital = 10_000.0_f64;
+    // Cash and positions strategies size against: venue balances live, `starting_cash` on paper.
+    let token_ids: Vec<String> = resolved
+        .iter()
+        .flat_map(|m| [m.up_token_id.clone(), m.down_token_id.clone()])
+        .collect();
+    let mut portfolio = match executor.venue_portfolio(&token_ids).await? {
+        Some(portfolio) => portfolio,
+        None => Portfolio::new(cfg.execution.paper.starting_cash),
+    };
+    info!(
+        target: "bot",
+        cash = portfolio.cash(),
+        "portfolio initialized"
+    );
+
+    // Pre-trade limits between the strategies and the executor.
+    let mut risk = RiskManager::new(cfg.risk.clone());
+
+    // Kill switch sources report mode changes to the loop, which gates the risk layer.
+    let mut kill_switch = KillSwitch::default();
+    let (mut kill_rx, mode_tx) = spawn_kill_switch(&cfg.kill_switch, &cfg.redis);
+
+    // Live cash and positions also change off the bot's own books (settlement, redemption,
+    // transfers), so they are re-read from the venue now and then.
+    let mut balance_refresh = tokio::time::interval_at(
+        tokio::time::Instant::now() + BALANCE_REFRESH_INTERVAL,
+        BALANCE_REFRESH_INTERVAL,
+    );
+
+    let mut order_prune = tokio::time::interval_at(
+        tokio::time::Instant::now() + ORDER_PRUNE_INTERVAL,
+        ORDER_PRUNE_INTERVAL,
+    );
+
+    let shutdown = tokio::signal::ctrl_c();
+    tokio::pin!(shutdown);
 
     info!(target: "bot", "entering main message loop");
     let mut msg_count: u64 = 0;
     loop {
         METRICS.heartbeat();
+        // Cancels the executor set off itself, e.g. after a breaker trip.
+        for call in executor.take_calls() {
+            queue.start_call(call);
+        }
 
-        let msg = match inbound_rx.recv().await {
-            Some(m) => m,
-            None => {
-                warn!(target: "bot", "websocket channel closed; exiting run loop");
+        let msg = tokio::select! {
+            maybe_msg = inbound_rx.recv() => match maybe_msg {
+                Some(WsEvent::Message(m)) => m,
+                Some(WsEvent::Reconnected) => {
+                    // Books built from the old socket may have missed updates; rebuild from scratch.
+                    warn!(target: "bot", "websocket reconnected; resetting order books");
+                    books.invalidate_all();
+                    continue;
+                }
+                None => {
+                    warn!(target: "bot", "websocket channel closed; exiting run loop");
+                    break;
+                }
+            },
+            Some(intents) = intents_rx.recv() => {
+                queue_intents(
+                    intents,
+                    &strategies,
+                    &mut executor,
+                    &storage,
+                    &mut portfolio,
+                    &mut risk,
+                    &mut queue,
+                )
+                .await;
+                continue;
+            }
+            Some(answer) = queue.next_answer() => {
+                match answer {
+                    VenueAnswer::Placed(id, ack) => {
+                        handle_ack(
+                            id,
+                            ack,
+                            &mut executor,
+                            &strategies,
+                            &storage,
+                            &mut portfolio,
+                            &mut risk,
+                        )
+                        .await;
+                        // A finished placement frees a slot and may unblock its round's next
+                        // order.
+                        dispatch_orders(
+                            Utc::now(),
+                            &mut queue,
+                            &mut executor,
+                            &strategies,
+                            &storage,
+                            &mut portfolio,
+                            &mut risk,
+                        )
+                        .await;
+                    }
+                    VenueAnswer::Call(CallReply::Balances(result)) => {
+                        match executor.on_balances(result) {
+                            Ok(venue) => portfolio.sync_balances(venue),
+                            Err(err) => {
+                                warn!(target: "bot", error = %err, "failed to refresh balances");
+                            }
+                        }
+                    }
+                    VenueAnswer::Call(reply) => {
+                        if let Err(err) = executor.on_call_reply(reply) {
+                            warn!(target: "bot", error = %err, "venue call failed");
+                        }
+                        // Refreshed orders may have filled unseen; canceled ones are reported
+                        // like any other outcome.
+                        handle_fills(
+                            executor.take_missed_fills(),
+                            &mut executor,
+                            &strategies,
+                            &storage,
+                            &mut portfolio,
+                            &mut risk,
+                        )
+                        .await;
+                    }
+                }
+                continue;
+            }
+            Some(rollover) = rollover_rx.recv() => {
+                if let Err(err) = apply_rollover(
+                    rollover,
+                    &mut books,
+                    &mut executor,
+                    &strategies,
+                    &mut risk,
+                    &mut queue,
+                    &subscriptions,
+                )
+                .await
+                {
+                    warn!(target: "bot", error = %err, "failed to apply market rollover");
+                }
+                // Paper cancels of the ended rounds' orders are already done.
+                report_finished_orders(
+                    Utc::now(),
+                    &mut executor,
+                    &strategies,
+                    &storage,
+                    &mut portfolio,
+                    &mut risk,
+                )
+                .await;
+                continue;
+            }
+            Some(resolution) = resolution_rx.recv() => {
+                risk.on_round_resolved(
+                    &resolution.market.slug,
+                    resolution.round_start,
+                    resolution.winner,
+                    Utc::now(),
+                );
+                let (winning_token, losing_token) = resolution.tokens();
+                match cfg.execution.mode {
+                    ExecutionMode::Paper => {
+                        let payout = portfolio.on_round_resolved(winning_token, losing_token);
+                        info!(
+                            target: "bot",
+                            slug = %resolution.market.slug,
+                            payout,
+                            cash = portfolio.cash(),
+                            "settled resolved round"
+                        );
+                    }
+                    // Live shares pay out on redemption; the venue balances pick the cash up.
+                    ExecutionMode::Live => portfolio.forget_tokens(&[winning_token, losing_token]),
+                }
+                continue;
+            }
+            _ = balance_refresh.tick(), if cfg.execution.mode == ExecutionMode::Live => {
+                start_balance_refresh(&executor, &portfolio, &queue);
+                continue;
+            }
+            _ = order_prune.tick() => {
+                // Late trade status changes of a round come in well within a round of its end.
+                let before = Utc::now() - chrono::Duration::seconds(2 * ROUND_SECS);
+                let pruned = executor.prune_orders(before);
+                if pruned > 0 {
+                    debug!(target: "bot", pruned, "dropped finished orders of past rounds");
+                }
+                continue;
+            }
+            Some(command) = kill_rx.recv() => {
+                if let Some(transition) = kill_switch.apply(command) {
+                    apply_trading_mode(
+                        transition,
+                        &cfg.kill_switch,
+                        &mut risk,
+                        &mut executor,
+                        &mut queue,
+                        &storage,
+                        &mode_tx,
+                    )
+                    .await;
+                }
+                continue;
+            }
+            Some(event) = recv_user_event(&mut user_conn) => {
+                match event {
+                    WsEvent::Message(Message::Text(text)) => {
+                        handle_user_text(
+                            &text,
+                            &mut executor,
+                            &strategies,
+                            &storage,
+                            &mut portfolio,
+                            &mut risk,
+                        )
+                        .await;
+                    }
+                    WsEvent::Message(_) => {}
+                    WsEvent::Reconnected => {
+                        // Events may have been missed while disconnected; resync from the venue.
+                        warn!(
+                            target: "bot",
+                            "user websocket reconnected; refreshing open orders and balances"
+                        );
+                        match executor.start_open_orders() {
+                            Ok(VenueCall::Pending(call)) => queue.start_call(call),
+                            Ok(VenueCall::Done(_)) => {}
+                            Err(err) => {
+                                warn!(target: "bot", error = %err, "failed to refresh open orders");
+                            }
+                        }
+                        start_balance_refresh(&executor, &portfolio, &queue);
+                    }
+                }
+                continue;
+            }
+            _ = &mut shutdown => {
+                info!(target: "bot", "shutdown requested; leaving run loop");
                 break;
             }
         };
@@ -489,15 +1252,23 @@ pub async fn run_bot(cfg: AppConfig) -> anyhow::Result<()> {
 
         match msg {
             Message::Text(text) => {
-                if let Err(err) = handle_ws_text(
-                    &text,
-                    &asset_to_market,
-                    &mut books_by_market,
-                    &mut engine,
+                let mut touched = Vec::new();
+                let mut resync = Vec::new();
+                let applied =
+                    apply_queued_messages(&text, inbound_rx, &mut books, &mut touched, &mut resync);
+                if applied > 1 {
+                    METRICS.record_snapshots_coalesced(applied as u64 - 1);
+                }
+                if let Err(err) = on_books_changed(
+                    touched,
+                    resync,
+                    &books,
+                    &subscriptions,
+                    &strategies,
                     &mut executor,
-                    &snapshot_recorder,
-                    &trade_recorder,
-                    available_capital,
+                    &storage,
+                    &mut portfolio,
+                    &mut risk,
                 )
                 .await
                 {
@@ -518,6 +1289,161 @@ pub async fn run_bot(cfg: AppConfig) -> anyhow::Result<()> {
         }
     }
 
+    // Let in-flight placements land so the cancel below covers them.
+    let drain = async {
+        while queue.in_flight() > 0 {
+            match queue.next_answer().await {
+                Some(VenueAnswer::Placed(id, ack)) => {
+                    if let Err(err) = executor.complete(id, ack) {
+                        debug!(
+                            target: "bot",
+                            error = %err,
+                            "order placement failed during shutdown"
+                        );
+                    }
+                }
+                Some(VenueAnswer::Call(reply)) => {
+                    let _ = executor.on_call_reply(reply);
+                }
+                None => break,
+            }
+        }
+    };
+    if tokio::time::timeout(SHUTDOWN_ACK_TIMEOUT, drain).await.is_err() {
+        warn!(target: "bot", "timed out waiting for in-flight orders on shutdown");
+    }
+
+    // Never leave resting orders behind once nothing is managing them.
+    match executor.cancel_all().await {
+        Ok(canceled) => info!(
+            target: "bot",
+            canceled = canceled.len(),
+            "canceled open orders on shutdown"
+        ),
+        Err(err) => warn!(
+            target: "bot",
+            error = %err,
+            "failed to cancel open orders on shutdown"
+        ),
+    }
+
     Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn books() -> OrderBooks {
+        let mut books = OrderBooks::default();
+        books.insert_market(&ResolvedMarket {
+            slug: "BTC-USD-15MIN".to_string(),
+            up_token_id: "UP".to_string(),
+            down_token_id: "DOWN".to_string(),
+            neg_risk: false,
+        });
+        books
+    }
+
+    fn apply(books: &mut OrderBooks, text: &str) -> (Vec<(String, DateTime<Utc>)>, Vec<String>) {
+        let v: serde_json::Value = serde_json::from_str(text).unwrap();
+        let events = match v {
+            serde_json::Value::Array(items) => items,
+            other => vec![other],
+        };
+        let mut touched = Vec::new();
+        let mut resync = Vec::new();
+        for ev in events {
+            apply_ws_event(ev, books, &mut touched, &mut resync).unwrap();
+        }
+        (touched, resync)
+    }
+
+    #[test]
+    fn book_then_price_change_builds_snapshot() {
+        let mut books = books();
+        let (touched, _) = apply(
+            &mut books,
+            r#"[
+                {"event_type":"book","asset_id":"UP","market":"0x1","timestamp":"1704110400000","hash":"a",
+                 "bids":[{"price":"0.48","size":"30"}],"asks":[{"price":"0.52","size":"25"}]},
+                {"event_type":"book","asset_id":"DOWN","market":"0x1","timestamp":"1704110400000","hash":"b",
+                 "bids":[{"price":"0.47","size":"10"}],"asks":[{"price":"0.53","size":"12"}]}
+            ]"#,
+        );
+        assert_eq!(touched.len(), 1);
+
+        let (touched, resync) = apply(
+            &mut books,
+            r#"{"event_type":"price_change","market":"0x1","timestamp":"1704110401000",
+                "price_changes":[{"asset_id":"UP","price":"0.50","size":"7","side":"SELL","hash":"c",
+                                  "best_bid":"0.48","best_ask":"0.5"}]}"#,
+        );
+        assert!(resync.is_empty());
+        let snapshot = books.snapshot(&touched[0].0, touched[0].1).unwrap();
+        assert!((snapshot.up_ask - 0.50).abs() < 1e-9);
+        assert!((snapshot.down_bid - 0.47).abs() < 1e-9);
+    }
+
+    #[test]
+    fn malformed_event_does_not_drop_the_rest_of_the_batch() {
+        let mut books = books();
+        let (mut touched, mut resync) = (Vec::new(), Vec::new());
+        apply_ws_text(
+            r#"[
+                {"event_type":"book","asset_id":"UP","market":"0x1"},
+                {"event_type":"book","asset_id":"UP","market":"0x1","timestamp":"1704110400000",
+                 "bids":[{"price":"0.48","size":"30"}],"asks":[{"price":"0.52","size":"25"}]}
+            ]"#,
+            &mut books,
+            &mut touched,
+            &mut resync,
+        );
+        assert_eq!(touched.len(), 1);
+        assert!(books.token("UP").unwrap().is_synced());
+    }
+
+    #[test]
+    fn queued_messages_fold_into_one_snapshot_per_market() {
+        let mut books = books();
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        let change = |ts: u64, price: &str| {
+            WsEvent::Message(Message::Text(format!(
+                r#"{{"event_type":"price_change","market":"0x1","timestamp":"{ts}",
+                    "price_changes":[{{"asset_id":"UP","price":"{price}","size":"5",
+                                      "side":"SELL"}}]}}"#
+            )))
+        };
+        tx.try_send(change(1704110401000, "0.51")).unwrap();
+        tx.try_send(change(1704110402000, "0.50")).unwrap();
+
+        let (mut touched, mut resync) = (Vec::new(), Vec::new());
+        let book = r#"{"event_type":"book","asset_id":"UP","market":"0x1","timestamp":"1704110400000",
+            "bids":[{"price":"0.48","size":"30"}],"asks":[{"price":"0.52","size":"25"}]}"#;
+        let applied = apply_queued_messages(book, &mut rx, &mut books, &mut touched, &mut resync);
+
+        assert_eq!(applied, 3);
+        assert_eq!(touched.len(), 1);
+        assert_eq!(touched[0].1.timestamp_millis(), 1704110402000);
+        let (_, book) = books.token_mut("UP").unwrap();
+        assert!((book.best_ask().unwrap().price - 0.50).abs() < 1e-9);
+    }
+
+    #[test]
+    fn mismatched_top_requests_resync() {
+        let mut books = books();
+        apply(
+            &mut books,
+            r#"{"event_type":"book","asset_id":"UP","market":"0x1","timestamp":"1704110400000",
+                "bids":[{"price":"0.48","size":"30"}],"asks":[{"price":"0.52","size":"25"}]}"#,
+        );
+        let (touched, resync) = apply(
+            &mut books,
+            r#"{"event_type":"price_change","market":"0x1","timestamp":"1704110401000",
+                "price_changes":[{"asset_id":"UP","price":"0.49","size":"5","side":"BUY",
+                                  "best_bid":"0.495","best_ask":"0.52"}]}"#,
+        );
+        assert!(touched.is_empty());
+        assert_eq!(resync, vec!["UP".to_string()]);
+    }
+}
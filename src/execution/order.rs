@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -40,7 +41,7 @@ impl Default for TimeInForce {
     }
 }
 
-/// Identifier used for tracking orders locally and, where supported, with the venue.
+/// Identifier used for tracking orders locally; the venue's own ID is kept on [`Order`].
 pub type OrderId = Uuid;
 
 /// Request to place a new order.
@@ -59,29 +60,110 @@ pub struct OrderRequest {
     pub client_order_id: String,
     pub order_type: OrderType,
     pub time_in_force: TimeInForce,
+    /// Whether the market settles through the neg-risk exchange, which live orders are
+    /// signed for.
+    #[serde(default)]
+    pub neg_risk: bool,
 }
 
 /// Local view of an order, including lifecycle and fill information.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Order {
+    /// Local identifier; stable for the order's lifetime and used for intents and records.
     pub id: OrderId,
+    /// Order hash assigned by the venue; `None` for paper orders or before acknowledgement.
+    #[serde(default)]
+    pub venue_order_id: Option<String>,
     pub request: OrderRequest,
     pub status: OrderStatus,
     /// Total size filled so far.
     pub filled_size: f64,
     /// Average price across fills, if any.
     pub avg_fill_price: f64,
+    /// Fees charged across fills, in quote currency.
+    #[serde(default)]
+    pub fees_paid: f64,
 }
 
+/// Filled sizes within this tolerance of the requested size count as fully filled.
+const FILL_EPSILON: f64 = 1e-9;
+
 impl Order {
     pub fn new(id: OrderId, request: OrderRequest) -> Self {
         Self {
             id,
+            venue_order_id: None,
             request,
             status: OrderStatus::New,
             filled_size: 0.0,
             avg_fill_price: 0.0,
+            fees_paid: 0.0,
         }
     }
+
+    /// Size still to be filled.
+    pub fn remaining(&self) -> f64 {
+        (self.request.size - self.filled_size).max(0.0)
+    }
+
+    /// Whether the order can no longer fill.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self.status,
+            OrderStatus::Filled | OrderStatus::Canceled | OrderStatus::Rejected | OrderStatus::Failed
+        )
+    }
+
+    /// Record an execution, updating the average price and the lifecycle status.
+    pub fn apply_fill(&mut self, price: f64, size: f64, fee: f64) {
+        let filled = self.filled_size + size;
+        if filled > 0.0 {
+            self.avg_fill_price = (self.avg_fill_price * self.filled_size + price * size) / filled;
+        }
+        self.filled_size = filled;
+        self.fees_paid += fee;
+        self.status = if self.remaining() <= FILL_EPSILON {
+            OrderStatus::Filled
+        } else {
+            OrderStatus::PartiallyFilled
+        };
+    }
+
+    /// Take back an execution the venue matched but could not settle. A filled order is gone
+    /// from the book, so it ends up canceled with what is left filled.
+    pub fn reverse_fill(&mut self, price: f64, size: f64, fee: f64) {
+        let filled = (self.filled_size - size).max(0.0);
+        self.avg_fill_price = if filled > FILL_EPSILON {
+            (self.avg_fill_price * self.filled_size - price * size) / filled
+        } else {
+            0.0
+        };
+        self.filled_size = filled;
+        self.fees_paid -= fee;
+        self.status = match self.status {
+            OrderStatus::Filled => OrderStatus::Canceled,
+            status if self.is_terminal() => status,
+            _ if filled > FILL_EPSILON => OrderStatus::PartiallyFilled,
+            _ => OrderStatus::Open,
+        };
+    }
+}
+
+/// Whether a fill added or removed liquidity.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Liquidity {
+    Maker,
+    Taker,
+}
+
+/// A single execution against one of our orders.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Fill {
+    pub order_id: OrderId,
+    pub price: f64,
+    pub size: f64,
+    pub fee: f64,
+    pub liquidity: Liquidity,
+    pub ts: DateTime<Utc>,
 }
 
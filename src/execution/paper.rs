@@ -0,0 +1,665 @@
+//! Paper matching engine driven by the live L2 book.
+//!
+//! Orders never leave the process. Each one reaches the simulated book `latency_ms` after
+//! submission; at that point any marketable quantity takes the displayed opposite levels up to
+//! the limit price (taker), and the remainder of a GTC order rests at its limit price behind
+//! whatever size was already displayed there (the queue estimate). Resting orders fill as maker
+//! once trade prints at their price have worked through the queue ahead, or when opposite
+//! liquidity shows up at or through their price.
+//!
+//! Displayed liquidity is not depleted by our simulated fills, so the engine remembers how much
+//! of each level it already consumed for as long as the level is displayed.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::types::PaperConfig;
+
+use super::book::{key_price, price_key, BookSide, OrderBooks, PriceLevel, TokenBook};
+use super::executor::{ExecutionError, ExecutionResult};
+use super::order::{
+    Fill, Liquidity, Order, OrderId, OrderRequest, OrderSide, OrderStatus, TimeInForce,
+};
+
+/// Sizes below this are treated as zero.
+const SIZE_EPSILON: f64 = 1e-9;
+
+#[derive(Clone, Debug)]
+struct PaperOrder {
+    order: Order,
+    submitted_at: DateTime<Utc>,
+    /// When the order reaches the simulated book.
+    active_at: DateTime<Utc>,
+    /// Set once the order has been through its taker pass and rests at its limit price.
+    resting: bool,
+    /// Estimated displayed size ahead of us at our price.
+    queue_ahead: f64,
+    /// Displayed size at our price on the previous pass.
+    last_level_size: f64,
+    /// Trade prints on the token already accounted for (see `TokenBook::trade_seq`).
+    last_trade_seq: u64,
+}
+
+/// (token ID, side, price key) of a displayed level.
+type LevelKey = (String, BookSide, u64);
+
+/// Simulated venue holding paper orders and matching them against live books.
+#[derive(Debug)]
+pub(crate) struct PaperMatchingEngine {
+    config: PaperConfig,
+    orders: HashMap<OrderId, PaperOrder>,
+    /// Displayed liquidity already consumed by simulated fills, per level.
+    taken: HashMap<LevelKey, f64>,
+}
+
+impl PaperMatchingEngine {
+    pub fn new(config: PaperConfig) -> Self {
+        Self {
+            config,
+            orders: HashMap::new(),
+            taken: HashMap::new(),
+        }
+    }
+
+    /// Accept a new order. It is matched on the first pass after the configured latency.
+    pub fn submit(&mut self, req: &OrderRequest, now: DateTime<Utc>) -> Order {
+        let order = Order::new(OrderId::new_v4(), req.clone());
+        let latency = Duration::milliseconds(self.config.latency_ms as i64);
+        self.orders.insert(
+            order.id,
+            PaperOrder {
+                order: order.clone(),
+                submitted_at: now,
+                active_at: now + latency,
+                resting: false,
+                queue_ahead: 0.0,
+                last_level_size: 0.0,
+                last_trade_seq: 0,
+            },
+        );
+        order
+    }
+
+    /// Stop an order from filling any further. Like the venue, terminal orders cannot be
+    /// canceled.
+    pub fn cancel(&mut self, id: OrderId) -> ExecutionResult<()> {
+        let paper = self
+            .orders
+            .get_mut(&id)
+            .ok_or_else(|| ExecutionError::OrderNotFound(id.to_string()))?;
+        if paper.order.is_terminal() {
+            return Err(ExecutionError::NotCancelable {
+                id: id.to_string(),
+                status: paper.order.status,
+            });
+        }
+        paper.order.status = OrderStatus::Canceled;
+        Ok(())
+    }
+
+    /// Cancel every live order accepted by `filter`, returning the canceled IDs.
+    pub fn cancel_where(&mut self, filter: impl Fn(&Order) -> bool) -> Vec<OrderId> {
+        let mut canceled = Vec::new();
+        for (id, paper) in &mut self.orders {
+            if !paper.order.is_terminal() && filter(&paper.order) {
+                paper.order.status = OrderStatus::Canceled;
+                canceled.push(*id);
+            }
+        }
+        canceled
+    }
+
+    pub fn order(&self, id: &OrderId) -> Option<&Order> {
+        self.orders.get(id).map(|p| &p.order)
+    }
+
+    /// Drop orders the caller no longer asks about, along with the consumed liquidity of
+    /// tokens no remaining order trades.
+    pub fn forget(&mut self, ids: &[OrderId]) {
+        for id in ids {
+            self.orders.remove(id);
+        }
+        let traded: HashSet<&str> = self
+            .orders
+            .values()
+            .map(|p| p.order.request.token_id.as_str())
+            .collect();
+        self.taken
+            .retain(|(token_id, _, _), _| traded.contains(token_id.as_str()));
+    }
+
+    /// Orders that can still fill, oldest first.
+    pub fn open_orders(&self) -> Vec<Order> {
+        let mut open: Vec<&PaperOrder> = self
+            .orders
+            .values()
+            .filter(|p| !p.order.is_terminal())
+            .collect();
+        open.sort_by_key(|p| (p.submitted_at, p.order.id));
+        open.into_iter().map(|p| p.order.clone()).collect()
+    }
+
+    /// Estimated size ahead of a resting order at its price.
+    pub fn queue_ahead(&self, id: &OrderId) -> Option<f64> {
+        self.orders
+            .get(id)
+            .filter(|p| p.resting)
+            .map(|p| p.queue_ahead)
+    }
+
+    /// Match every live order against the current books, oldest first.
+    pub fn match_orders(&mut self, books: &OrderBooks, now: DateTime<Utc>) -> Vec<Fill> {
+        let mut due: Vec<(DateTime<Utc>, OrderId)> = self
+            .orders
+            .iter()
+            .filter(|(_, p)| !p.order.is_terminal() && p.active_at <= now)
+            .map(|(id, p)| (p.submitted_at, *id))
+            .collect();
+        due.sort();
+
+        let mut fills = Vec::new();
+        for (_, id) in due {
+            let Some(paper) = self.orders.get_mut(&id) else {
+                continue;
+            };
+            let Some(book) = books.token(&paper.order.request.token_id) else {
+                continue;
+            };
+            if !book.is_synced() {
+                continue;
+            }
+
+            if paper.resting {
+                match_resting(paper, book, &mut self.taken, &self.config, now, &mut fills);
+            } else {
+                activate(paper, book, &mut self.taken, &self.config, now, &mut fills);
+            }
+        }
+
+        // Forget consumption of levels that have left the book.
+        self.taken.retain(|(token_id, side, key), _| {
+            books
+                .token(token_id)
+                .is_some_and(|b| b.size_at(*side, key_price(*key)) > 0.0)
+        });
+
+        fills
+    }
+}
+
+/// (own side, opposite side) of the book for an order side.
+fn book_sides(side: OrderSide) -> (BookSide, BookSide) {
+    match side {
+        OrderSide::Buy => (BookSide::Bid, BookSide::Ask),
+        OrderSide::Sell => (BookSide::Ask, BookSide::Bid),
+    }
+}
+
+/// Whether an opposite level at `level_price` trades against a limit at `limit`.
+fn marketable(side: OrderSide, limit: f64, level_price: f64) -> bool {
+    match side {
+        OrderSide::Buy => price_key(level_price) <= price_key(limit),
+        OrderSide::Sell => price_key(level_price) >= price_key(limit),
+    }
+}
+
+/// Opposite levels that trade against `limit`, best first.
+fn crossing_levels(book: &TokenBook, side: OrderSide, limit: f64) -> Vec<PriceLevel> {
+    let (_, opposite) = book_sides(side);
+    book.levels(opposite, usize::MAX)
+        .into_iter()
+        .take_while(|l| marketable(side, limit, l.price))
+        .collect()
+}
+
+fn level_key(token_id: &str, side: BookSide, price: f64) -> LevelKey {
+    (token_id.to_string(), side, price_key(price))
+}
+
+/// Displayed size at a level that simulated fills have not consumed yet.
+fn available(
+    taken: &mut HashMap<LevelKey, f64>,
+    token_id: &str,
+    side: BookSide,
+    level: PriceLevel,
+) -> f64 {
+    match taken.get_mut(&level_key(token_id, side, level.price)) {
+        // If the level shrank below what we took, what is left counts as consumed too; size
+        // added on top of that later is fresh liquidity.
+        Some(used) => {
+            *used = used.min(level.size);
+            level.size - *used
+        }
+        None => level.size,
+    }
+}
+
+fn consume(
+    taken: &mut HashMap<LevelKey, f64>,
+    token_id: &str,
+    side: BookSide,
+    price: f64,
+    size: f64,
+) {
+    *taken.entry(level_key(token_id, side, price)).or_insert(0.0) += size;
+}
+
+fn push_fill(
+    paper: &mut PaperOrder,
+    price: f64,
+    size: f64,
+    fee_rate: f64,
+    liquidity: Liquidity,
+    now: DateTime<Utc>,
+    fills: &mut Vec<Fill>,
+) {
+    let fee = price * size * fee_rate;
+    paper.order.apply_fill(price, size, fee);
+    fills.push(Fill {
+        order_id: paper.order.id,
+        price,
+        size,
+        fee,
+        liquidity,
+        ts: now,
+    });
+}
+
+/// First pass once latency has elapsed: take marketable liquidity, then rest or expire.
+fn activate(
+    paper: &mut PaperOrder,
+    book: &TokenBook,
+    taken: &mut HashMap<LevelKey, f64>,
+    config: &PaperConfig,
+    now: DateTime<Utc>,
+    fills: &mut Vec<Fill>,
+) {
+    let req = paper.order.request.clone();
+    let (own, opposite) = book_sides(req.side);
+
+    let mut crossing: Vec<(PriceLevel, f64)> = crossing_levels(book, req.side, req.price)
+        .into_iter()
+        .map(|l| (l, 0.0))
+        .collect();
+    for (level, avail) in &mut crossing {
+        *avail = available(taken, &req.token_id, opposite, *level);
+    }
+
+    if req.time_in_force == TimeInForce::Fok {
+        let total: f64 = crossing.iter().map(|(_, a)| a).sum();
+        if total + SIZE_EPSILON < paper.order.remaining() {
+            paper.order.status = OrderStatus::Canceled;
+            return;
+        }
+    }
+
+    for (level, avail) in crossing {
+        let size = avail.min(paper.order.remaining());
+        if size <= SIZE_EPSILON {
+            continue;
+        }
+        consume(taken, &req.token_id, opposite, level.price, size);
+        push_fill(
+            paper,
+            level.price,
+            size,
+            config.taker_fee_rate,
+            Liquidity::Taker,
+            now,
+            fills,
+        );
+        if paper.order.remaining() <= SIZE_EPSILON {
+            break;
+        }
+    }
+
+    if paper.order.is_terminal() {
+        return;
+    }
+
+    match req.time_in_force {
+        TimeInForce::Gtc => {
+            if paper.order.status == OrderStatus::New {
+                paper.order.status = OrderStatus::Open;
+            }
+            paper.resting = true;
+            paper.queue_ahead = book.size_at(own, req.price);
+            paper.last_level_size = paper.queue_ahead;
+            paper.last_trade_seq = book.trade_seq();
+        }
+        TimeInForce::Ioc | TimeInForce::Fok => {
+            paper.order.status = OrderStatus::Canceled;
+        }
+    }
+}
+
+/// Subsequent passes for a resting order: advance the queue and fill as maker.
+fn match_resting(
+    paper: &mut PaperOrder,
+    book: &TokenBook,
+    taken: &mut HashMap<LevelKey, f64>,
+    config: &PaperConfig,
+    now: DateTime<Utc>,
+    fills: &mut Vec<Fill>,
+) {
+    let side = paper.order.request.side;
+    let price = paper.order.request.price;
+    let token_id = paper.order.request.token_id.clone();
+    let (own, opposite) = book_sides(side);
+
+    // Trade prints at our price work through the queue ahead of us before reaching us. Several
+    // prints may have arrived since the last pass; each one counts.
+    let traded: f64 = book
+        .trades_since(paper.last_trade_seq)
+        .filter(|trade| {
+            let hits_own_side = trade.side.is_none_or(|aggressor| aggressor == opposite);
+            hits_own_side && price_key(trade.price) == price_key(price)
+        })
+        .map(|trade| trade.size)
+        .sum();
+    paper.last_trade_seq = book.trade_seq();
+    let traded_ahead = traded.min(paper.queue_ahead);
+    paper.queue_ahead -= traded_ahead;
+    let mut volume_at_us = traded - traded_ahead;
+
+    // Any other size leaving the level is taken as cancels spread evenly through the queue.
+    let current = book.size_at(own, price);
+    let before = (paper.last_level_size - traded).max(0.0);
+    let canceled = (before - current).max(0.0);
+    if canceled > 0.0 && before > 0.0 {
+        paper.queue_ahead -= canceled * paper.queue_ahead / before;
+    }
+    paper.queue_ahead = paper.queue_ahead.clamp(0.0, current);
+    paper.last_level_size = current;
+
+    // Opposite liquidity at or through our price matches the queue ahead first, then us.
+    let mut wanted = paper.queue_ahead + (paper.order.remaining() - volume_at_us).max(0.0);
+    for level in crossing_levels(book, side, price) {
+        if wanted <= SIZE_EPSILON {
+            break;
+        }
+        let size = available(taken, &token_id, opposite, level).min(wanted);
+        if size <= SIZE_EPSILON {
+            continue;
+        }
+        consume(taken, &token_id, opposite, level.price, size);
+        wanted -= size;
+        let ahead = size.min(paper.queue_ahead);
+        paper.queue_ahead -= ahead;
+        volume_at_us += size - ahead;
+    }
+
+    let size = volume_at_us.min(paper.order.remaining());
+    if size > SIZE_EPSILON {
+        push_fill(
+            paper,
+            price,
+            size,
+            config.maker_fee_rate,
+            Liquidity::Maker,
+            now,
+            fills,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::gamma::ResolvedMarket;
+    use crate::execution::book::LastTrade;
+    use crate::execution::order::OrderType;
+
+    const UP: &str = "UP_TOKEN";
+
+    fn lvl(price: f64, size: f64) -> PriceLevel {
+        PriceLevel { price, size }
+    }
+
+    fn books(bids: Vec<PriceLevel>, asks: Vec<PriceLevel>) -> OrderBooks {
+        let mut books = OrderBooks::new(0);
+        books.insert_market(&ResolvedMarket {
+            slug: "BTC_15m".to_string(),
+            up_token_id: UP.to_string(),
+            down_token_id: "DOWN_TOKEN".to_string(),
+            neg_risk: false,
+        });
+        set_book(&mut books, bids, asks, 1);
+        books
+    }
+
+    fn set_book(books: &mut OrderBooks, bids: Vec<PriceLevel>, asks: Vec<PriceLevel>, ts_ms: i64) {
+        let (_, book) = books.token_mut(UP).unwrap();
+        assert!(book.apply_snapshot(bids, asks, ts_ms));
+    }
+
+    fn request(side: OrderSide, price: f64, size: f64, time_in_force: TimeInForce) -> OrderRequest {
+        OrderRequest {
+            market_slug: "BTC_15m".to_string(),
+            token_id: UP.to_string(),
+            side,
+            price,
+            size,
+            client_order_id: "test".to_string(),
+            order_type: OrderType::Limit,
+            time_in_force,
+            neg_risk: false,
+        }
+    }
+
+    fn engine() -> PaperMatchingEngine {
+        PaperMatchingEngine::new(PaperConfig {
+            latency_ms: 100,
+            maker_fee_rate: 0.0,
+            taker_fee_rate: 0.01,
+            ..PaperConfig::default()
+        })
+    }
+
+    fn at(ms: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(1_704_110_400_000 + ms).unwrap()
+    }
+
+    #[test]
+    fn waits_for_latency_then_takes_levels_and_rests_remainder() {
+        let books = books(
+            vec![lvl(0.40, 50.0)],
+            vec![lvl(0.45, 5.0), lvl(0.46, 3.0), lvl(0.50, 10.0)],
+        );
+        let mut engine = engine();
+        let order = engine.submit(
+            &request(OrderSide::Buy, 0.46, 10.0, TimeInForce::Gtc),
+            at(0),
+        );
+
+        assert!(engine.match_orders(&books, at(50)).is_empty());
+
+        let fills = engine.match_orders(&books, at(100));
+        assert_eq!(fills.len(), 2);
+        assert_eq!((fills[0].price, fills[0].size), (0.45, 5.0));
+        assert_eq!((fills[1].price, fills[1].size), (0.46, 3.0));
+        assert!(fills.iter().all(|f| f.liquidity == Liquidity::Taker));
+        assert!((fills[0].fee - 0.45 * 5.0 * 0.01).abs() < 1e-12);
+
+        let o = engine.order(&order.id).unwrap();
+        assert_eq!(o.status, OrderStatus::PartiallyFilled);
+        assert!((o.filled_size - 8.0).abs() < 1e-12);
+        assert_eq!(engine.queue_ahead(&order.id), Some(0.0));
+
+        // The same displayed asks are not filled twice.
+        assert!(engine.match_orders(&books, at(200)).is_empty());
+    }
+
+    #[test]
+    fn resting_order_fills_after_queue_ahead_trades_through() {
+        let mut books = books(vec![lvl(0.40, 30.0)], vec![lvl(0.45, 10.0)]);
+        let mut engine = engine();
+        let order = engine.submit(
+            &request(OrderSide::Buy, 0.40, 10.0, TimeInForce::Gtc),
+            at(0),
+        );
+        assert!(engine.match_orders(&books, at(100)).is_empty());
+        assert_eq!(engine.queue_ahead(&order.id), Some(30.0));
+
+        // 20 shares sold into the bid: all of it was ahead of us.
+        set_book(&mut books, vec![lvl(0.40, 10.0)], vec![lvl(0.45, 10.0)], 2);
+        let (_, book) = books.token_mut(UP).unwrap();
+        book.record_trade(LastTrade {
+            price: 0.40,
+            size: 20.0,
+            side: Some(BookSide::Ask),
+            ts: at(150),
+        });
+        assert!(engine.match_orders(&books, at(200)).is_empty());
+        assert_eq!(engine.queue_ahead(&order.id), Some(10.0));
+
+        // The next 14 shares clear the queue and fill 4 of ours as maker.
+        set_book(&mut books, vec![], vec![lvl(0.45, 10.0)], 3);
+        let (_, book) = books.token_mut(UP).unwrap();
+        book.record_trade(LastTrade {
+            price: 0.40,
+            size: 14.0,
+            side: Some(BookSide::Ask),
+            ts: at(250),
+        });
+        let fills = engine.match_orders(&books, at(300));
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].liquidity, Liquidity::Maker);
+        assert_eq!((fills[0].price, fills[0].size), (0.40, 4.0));
+        assert_eq!(fills[0].fee, 0.0);
+    }
+
+    #[test]
+    fn every_trade_since_the_last_pass_counts() {
+        let mut books = books(vec![lvl(0.40, 5.0)], vec![lvl(0.45, 10.0)]);
+        let mut engine = engine();
+        let order = engine.submit(
+            &request(OrderSide::Buy, 0.40, 10.0, TimeInForce::Gtc),
+            at(0),
+        );
+        assert!(engine.match_orders(&books, at(100)).is_empty());
+        assert_eq!(engine.queue_ahead(&order.id), Some(5.0));
+
+        // Three prints in one batch: 5 clear the queue, 6 more reach us.
+        set_book(&mut books, vec![], vec![lvl(0.45, 10.0)], 2);
+        let (_, book) = books.token_mut(UP).unwrap();
+        for (size, ms) in [(3.0, 150), (2.0, 150), (6.0, 160)] {
+            book.record_trade(LastTrade {
+                price: 0.40,
+                size,
+                side: Some(BookSide::Ask),
+                ts: at(ms),
+            });
+        }
+        let fills = engine.match_orders(&books, at(200));
+        assert_eq!(fills.len(), 1);
+        assert_eq!((fills[0].price, fills[0].size), (0.40, 6.0));
+        // Prints are not counted twice.
+        assert!(engine.match_orders(&books, at(300)).is_empty());
+    }
+
+    #[test]
+    fn crossing_liquidity_fills_resting_order_once() {
+        let mut books = books(vec![lvl(0.40, 5.0)], vec![lvl(0.45, 10.0)]);
+        let mut engine = engine();
+        let order = engine.submit(
+            &request(OrderSide::Buy, 0.42, 10.0, TimeInForce::Gtc),
+            at(0),
+        );
+        assert!(engine.match_orders(&books, at(100)).is_empty());
+
+        set_book(&mut books, vec![lvl(0.40, 5.0)], vec![lvl(0.41, 6.0)], 2);
+        let fills = engine.match_orders(&books, at(200));
+        assert_eq!(fills.len(), 1);
+        assert_eq!((fills[0].price, fills[0].size), (0.42, 6.0));
+        assert!(engine.match_orders(&books, at(300)).is_empty());
+
+        let o = engine.order(&order.id).unwrap();
+        assert_eq!(o.status, OrderStatus::PartiallyFilled);
+        assert!((o.avg_fill_price - 0.42).abs() < 1e-12);
+    }
+
+    #[test]
+    fn ioc_and_fok_never_rest() {
+        let books = books(vec![], vec![lvl(0.45, 4.0)]);
+        let mut engine = engine();
+        let ioc = engine.submit(
+            &request(OrderSide::Buy, 0.45, 10.0, TimeInForce::Ioc),
+            at(0),
+        );
+        let fok = engine.submit(
+            &request(OrderSide::Buy, 0.45, 10.0, TimeInForce::Fok),
+            at(0),
+        );
+
+        let fills = engine.match_orders(&books, at(100));
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].order_id, ioc.id);
+        assert_eq!(engine.order(&ioc.id).unwrap().status, OrderStatus::Canceled);
+        assert!((engine.order(&ioc.id).unwrap().filled_size - 4.0).abs() < 1e-12);
+        assert_eq!(engine.order(&fok.id).unwrap().status, OrderStatus::Canceled);
+        assert_eq!(engine.order(&fok.id).unwrap().filled_size, 0.0);
+    }
+
+    #[test]
+    fn canceled_orders_stop_matching() {
+        let mut books = books(vec![lvl(0.40, 5.0)], vec![lvl(0.45, 10.0)]);
+        let mut engine = engine();
+        let order = engine.submit(
+            &request(OrderSide::Sell, 0.44, 5.0, TimeInForce::Gtc),
+            at(0),
+        );
+        assert!(engine.match_orders(&books, at(100)).is_empty());
+        engine.cancel(order.id).unwrap();
+
+        set_book(&mut books, vec![lvl(0.44, 5.0)], vec![lvl(0.45, 10.0)], 2);
+        assert!(engine.match_orders(&books, at(200)).is_empty());
+        assert_eq!(
+            engine.order(&order.id).unwrap().status,
+            OrderStatus::Canceled
+        );
+    }
+
+    #[test]
+    fn tracks_open_orders_and_rejects_terminal_cancels() {
+        let books = books(vec![lvl(0.40, 5.0)], vec![lvl(0.45, 10.0)]);
+        let mut engine = engine();
+        let filled = engine.submit(
+            &request(OrderSide::Buy, 0.45, 2.0, TimeInForce::Gtc),
+            at(0),
+        );
+        let resting = engine.submit(
+            &request(OrderSide::Buy, 0.41, 2.0, TimeInForce::Gtc),
+            at(10),
+        );
+        assert_eq!(engine.open_orders().len(), 2);
+
+        engine.match_orders(&books, at(200));
+        let open = engine.open_orders();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].id, resting.id);
+        assert_eq!(open[0].status, OrderStatus::Open);
+
+        assert!(matches!(
+            engine.cancel(filled.id),
+            Err(ExecutionError::NotCancelable {
+                status: OrderStatus::Filled,
+                ..
+            })
+        ));
+        engine.cancel(resting.id).unwrap();
+        assert!(matches!(
+            engine.cancel(resting.id),
+            Err(ExecutionError::NotCancelable {
+                status: OrderStatus::Canceled,
+                ..
+            })
+        ));
+        assert!(engine.open_orders().is_empty());
+        assert!(matches!(
+            engine.cancel(OrderId::new_v4()),
+            Err(ExecutionError::OrderNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,293 @@
+//! Order submission stage between the strategies and the venue.
+//!
+//! Live placements are REST round-trips, so each one runs on its own task while the main loop
+//! keeps consuming market data. At most `max_parallel_orders` are in flight; intents beyond
+//! that wait in a queue of `max_queued_orders`, hedges and exits ahead of intents that open
+//! new positions. Orders of one strategy round go out one at a time, so a requote never
+//! overtakes the order it replaces. Cancels and order refreshes run on their own tasks too,
+//! outside those limits, and report back the same way.
+
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+use tokio::sync::mpsc;
+
+use crate::strategy::OrderIntent;
+
+use super::executor::{CallReply, PendingAck, PendingCall, SubmissionAck};
+use super::order::OrderId;
+
+/// Strategy, market and round an intent belongs to.
+type RoundKey = (String, String, DateTime<Utc>);
+
+fn round_key(intent: &OrderIntent) -> RoundKey {
+    (
+        intent.strategy.clone(),
+        intent.market_slug.clone(),
+        intent.round_start,
+    )
+}
+
+/// A venue answer coming back to the main loop.
+pub(crate) enum VenueAnswer {
+    /// Answer to the placement of an order.
+    Placed(OrderId, SubmissionAck),
+    /// Answer to a cancel or order refresh.
+    Call(CallReply),
+}
+
+/// Intents waiting to be submitted, and live submissions and other venue calls awaiting the
+/// venue.
+pub(crate) struct SubmissionQueue {
+    max_in_flight: usize,
+    max_waiting: usize,
+    waiting: VecDeque<OrderIntent>,
+    in_flight: HashMap<OrderId, RoundKey>,
+    answers_tx: mpsc::Sender<VenueAnswer>,
+    answers_rx: mpsc::Receiver<VenueAnswer>,
+}
+
+impl SubmissionQueue {
+    pub fn new(max_in_flight: usize, max_waiting: usize) -> Self {
+        let max_in_flight = max_in_flight.max(1);
+        // Every in-flight submission has a slot; only a burst of cancels can make one wait.
+        let (answers_tx, answers_rx) = mpsc::channel(max_in_flight);
+        Self {
+            max_in_flight,
+            max_waiting: max_waiting.max(1),
+            waiting: VecDeque::new(),
+            in_flight: HashMap::new(),
+            answers_tx,
+            answers_rx,
+        }
+    }
+
+    /// Queue an intent for submission, hedges and exits ahead of intents adding new risk.
+    ///
+    /// When the queue is full, a risk-reducing intent displaces the newest one that adds risk.
+    /// Returns the intent left out, if any.
+    pub fn push(&mut self, intent: OrderIntent) -> Option<OrderIntent> {
+        let displaced = if self.waiting.len() < self.max_waiting {
+            None
+        } else if !intent.reduces_risk {
+            return Some(intent);
+        } else {
+            match self.waiting.iter().rposition(|queued| !queued.reduces_risk) {
+                Some(pos) => self.waiting.remove(pos),
+                None => return Some(intent),
+            }
+        };
+        if intent.reduces_risk {
+            let pos = self
+                .waiting
+                .iter()
+                .position(|queued| !queued.reduces_risk)
+                .unwrap_or(self.waiting.len());
+            self.waiting.insert(pos, intent);
+        } else {
+            self.waiting.push_back(intent);
+        }
+        displaced
+    }
+
+    /// Oldest waiting intent that may be submitted now: there is a free slot and nothing of
+    /// its round is in flight.
+    pub fn next_ready(&mut self) -> Option<OrderIntent> {
+        if self.in_flight.len() >= self.max_in_flight {
+            return None;
+        }
+        let pos = self.waiting.iter().position(|intent| {
+            let key = round_key(intent);
+            !self.in_flight.values().any(|busy| *busy == key)
+        })?;
+        self.waiting.remove(pos)
+    }
+
+    /// Drop waiting intents of `market_slug` for rounds that started at or before `ended`;
+    /// returns how many were dropped.
+    pub fn drop_ended_round(&mut self, market_slug: &str, ended: DateTime<Utc>) -> usize {
+        let before = self.waiting.len();
+        self.waiting
+            .retain(|intent| intent.market_slug != market_slug || intent.round_start > ended);
+        before - self.waiting.len()
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Run the venue round-trip of `intent`'s order `id` on its own task.
+    pub fn start(&mut self, intent: &OrderIntent, id: OrderId, ack: PendingAck) {
+        self.in_flight.insert(id, round_key(intent));
+        let tx = self.answers_tx.clone();
+        tokio::spawn(async move {
+            let ack = ack.await;
+            // Only fails once the main loop is gone.
+            let _ = tx.send(VenueAnswer::Placed(id, ack)).await;
+        });
+    }
+
+    /// Run a cancel or order refresh on its own task; its answer comes from
+    /// [`Self::next_answer`] without taking a placement slot.
+    pub fn start_call(&self, call: PendingCall) {
+        let tx = self.answers_tx.clone();
+        tokio::spawn(async move {
+            let reply = call.await;
+            // Only fails once the main loop is gone.
+            let _ = tx.send(VenueAnswer::Call(reply)).await;
+        });
+    }
+
+    /// Next venue answer; waits forever while nothing is outstanding.
+    pub async fn next_answer(&mut self) -> Option<VenueAnswer> {
+        let answer = self.answers_rx.recv().await;
+        if let Some(VenueAnswer::Placed(id, _)) = &answer {
+            self.in_flight.remove(id);
+        }
+        answer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::order::{Order, OrderRequest, OrderSide, OrderType, TimeInForce};
+    use crate::strategy::{LegSide, TradeAction};
+
+    fn intent(market: &str, label: &str) -> OrderIntent {
+        OrderIntent {
+            strategy: "two_leg".to_string(),
+            market_slug: market.to_string(),
+            round_start: DateTime::from_timestamp(1_704_110_400, 0).unwrap(),
+            side: LegSide::Up,
+            action: TradeAction::Buy,
+            shares: 5.0,
+            limit_price: 0.40,
+            label: label.to_string(),
+            expected_locked_profit: None,
+            attempt: 0,
+            replaces: Vec::new(),
+            immediate_or_cancel: false,
+            reduces_risk: false,
+        }
+    }
+
+    fn placed(id: OrderId) -> PendingAck {
+        let order = Order::new(
+            id,
+            OrderRequest {
+                market_slug: "BTC".to_string(),
+                token_id: "UP".to_string(),
+                side: OrderSide::Buy,
+                price: 0.40,
+                size: 5.0,
+                client_order_id: "cid".to_string(),
+                order_type: OrderType::Limit,
+                time_in_force: TimeInForce::Gtc,
+                neg_risk: false,
+            },
+        );
+        Box::pin(async move {
+            SubmissionAck {
+                canceled: Vec::new(),
+                placed: Ok(order),
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn rounds_go_one_at_a_time_within_the_parallel_limit() {
+        let mut queue = SubmissionQueue::new(2, 2);
+        assert!(queue.push(intent("BTC", "leg1")).is_none());
+        let first = queue.next_ready().unwrap();
+        let btc = OrderId::new_v4();
+        queue.start(&first, btc, placed(btc));
+
+        assert!(queue.push(intent("BTC", "leg2")).is_none());
+        assert!(queue.push(intent("ETH", "leg1")).is_none());
+        assert!(queue.push(intent("SOL", "leg1")).is_some());
+        // BTC's next order waits behind the one in flight; ETH may go.
+        let second = queue.next_ready().unwrap();
+        assert_eq!(second.market_slug, "ETH");
+        let eth = OrderId::new_v4();
+        queue.start(&second, eth, placed(eth));
+        assert_eq!(queue.in_flight(), 2);
+        assert!(queue.next_ready().is_none());
+
+        let mut acked = Vec::new();
+        while acked.len() < 2 {
+            if let Some(VenueAnswer::Placed(id, _)) = queue.next_answer().await {
+                acked.push(id);
+            }
+        }
+        assert!(acked.contains(&btc) && acked.contains(&eth));
+        assert_eq!(queue.next_ready().unwrap().label, "leg2");
+    }
+
+    #[test]
+    fn hedges_and_exits_jump_ahead_of_new_risk() {
+        let mut queue = SubmissionQueue::new(1, 2);
+        let hedge = |market| OrderIntent {
+            reduces_risk: true,
+            ..intent(market, "leg2")
+        };
+        assert!(queue.push(intent("BTC", "leg1")).is_none());
+        assert!(queue.push(intent("ETH", "leg1")).is_none());
+
+        // Full: a hedge displaces the newest Leg 1, which is handed back.
+        let displaced = queue.push(hedge("SOL")).unwrap();
+        assert_eq!(displaced.market_slug, "ETH");
+        assert_eq!(queue.next_ready().unwrap().market_slug, "SOL");
+
+        assert!(queue.push(hedge("ETH")).is_none());
+        assert_eq!(queue.push(hedge("XRP")).unwrap().market_slug, "BTC");
+        // Nothing but hedges waiting: whatever comes next is handed back itself.
+        assert_eq!(queue.push(hedge("DOGE")).unwrap().market_slug, "DOGE");
+        assert_eq!(queue.push(intent("SOL", "leg1")).unwrap().market_slug, "SOL");
+    }
+
+    #[tokio::test]
+    async fn venue_calls_report_back_outside_the_placement_slots() {
+        let mut queue = SubmissionQueue::new(1, 1);
+        let first = intent("BTC", "leg1");
+        let id = OrderId::new_v4();
+        queue.start(&first, id, placed(id));
+        queue.start_call(Box::pin(async {
+            CallReply::Canceled {
+                scope: "ETH".to_string(),
+                result: Ok(vec!["0xabc".to_string()]),
+            }
+        }));
+
+        let mut placed = false;
+        while !placed {
+            match queue.next_answer().await.unwrap() {
+                VenueAnswer::Placed(acked, _) => placed = acked == id,
+                VenueAnswer::Call(CallReply::Canceled { scope, result }) => {
+                    assert_eq!(scope, "ETH");
+                    assert_eq!(result.unwrap(), vec!["0xabc".to_string()]);
+                    // The cancel never held the placement's slot.
+                    assert_eq!(queue.in_flight(), 1);
+                }
+                VenueAnswer::Call(_) => panic!("unexpected reply"),
+            }
+        }
+        assert_eq!(queue.in_flight(), 0);
+    }
+
+    #[test]
+    fn rollover_drops_waiting_intents_of_the_ended_round() {
+        let mut queue = SubmissionQueue::new(3, 3);
+        let ended = intent("BTC", "leg1");
+        let mut next_round = intent("BTC", "leg1");
+        next_round.round_start += chrono::Duration::minutes(15);
+        assert!(queue.push(ended.clone()).is_none());
+        assert!(queue.push(intent("ETH", "leg1")).is_none());
+        assert!(queue.push(next_round).is_none());
+
+        assert_eq!(queue.drop_ended_round("BTC", ended.round_start), 1);
+        assert_eq!(queue.next_ready().unwrap().market_slug, "ETH");
+        assert!(queue.next_ready().unwrap().round_start > ended.round_start);
+        assert!(queue.next_ready().is_none());
+    }
+}
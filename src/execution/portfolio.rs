@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+
+use super::order::{Fill, Order, OrderId, OrderSide};
+
+/// Account cash and positions that new orders are sized against.
+///
+/// Working buy orders reserve their unfilled notional at the limit price, so the same cash is
+/// never offered to strategies twice. Fills move cash (fees included) into shares per token.
+/// What each strategy has reserved and paid for the shares it holds is tracked too, so every
+/// strategy is sized against its own share of capital.
+#[derive(Clone, Debug, Default)]
+pub struct Portfolio {
+    /// USDC held, in dollars.
+    cash: f64,
+    /// Cash reserved by each working buy order, with the strategy that placed it.
+    reserved: HashMap<OrderId, (String, f64)>,
+    /// Shares held per token ID.
+    positions: HashMap<String, f64>,
+    /// Shares bought by each strategy per token, keyed by (strategy, token ID).
+    holdings: HashMap<(String, String), Holding>,
+}
+
+/// Shares a strategy holds of a token and what it paid for them, fees included.
+#[derive(Clone, Copy, Debug, Default)]
+struct Holding {
+    shares: f64,
+    cost: f64,
+}
+
+impl Portfolio {
+    pub fn new(cash: f64) -> Self {
+        Self {
+            cash,
+            ..Self::default()
+        }
+    }
+
+    /// Start out holding `shares` of a token, e.g. as reported by the venue.
+    pub fn with_position(mut self, token_id: &str, shares: f64) -> Self {
+        if shares > 0.0 {
+            self.positions.insert(token_id.to_string(), shares);
+        }
+        self
+    }
+
+    pub fn cash(&self) -> f64 {
+        self.cash
+    }
+
+    /// Cash committed to working buy orders.
+    pub fn reserved(&self) -> f64 {
+        self.reserved.values().map(|(_, amount)| amount).sum()
+    }
+
+    /// Cash free for new orders: cash less what working buys have reserved.
+    pub fn available_capital(&self) -> f64 {
+        (self.cash - self.reserved()).max(0.0)
+    }
+
+    /// Shares held of a token.
+    pub fn shares(&self, token_id: &str) -> f64 {
+        self.positions.get(token_id).copied().unwrap_or(0.0)
+    }
+
+    /// Cash a strategy has tied up: its working buys' reservations and the cost of the shares
+    /// it holds.
+    pub fn committed(&self, strategy: &str) -> f64 {
+        let reserved: f64 = self
+            .reserved
+            .values()
+            .filter(|(owner, _)| owner == strategy)
+            .map(|(_, amount)| amount)
+            .sum();
+        let held: f64 = self
+            .holdings
+            .iter()
+            .filter(|((owner, _), _)| owner == strategy)
+            .map(|(_, holding)| holding.cost)
+            .sum();
+        reserved + held
+    }
+
+    /// Capital a strategy with `allocation` of the account may size new orders against: its
+    /// share of cash plus what the strategies have paid for shares they hold, less what it
+    /// has committed itself, and never more than is free.
+    pub fn strategy_capital(&self, strategy: &str, allocation: f64) -> f64 {
+        let invested: f64 = self.holdings.values().map(|holding| holding.cost).sum();
+        let share = (self.cash + invested) * allocation - self.committed(strategy);
+        share.clamp(0.0, self.available_capital())
+    }
+
+    /// Account for a newly placed order of `strategy`, including anything it filled on arrival.
+    pub fn on_order_accepted(&mut self, strategy: &str, order: &Order) {
+        if order.filled_size > 0.0 {
+            let (price, size, fee) = (order.avg_fill_price, order.filled_size, order.fees_paid);
+            self.settle(strategy, order, price, size, fee);
+        }
+        self.sync_reservation(strategy, order);
+    }
+
+    /// Apply a fill of an order of `strategy`; `order` must already include it.
+    pub fn on_fill(&mut self, strategy: &str, order: &Order, fill: &Fill) {
+        self.settle(strategy, order, fill.price, fill.size, fill.fee);
+        self.sync_reservation(strategy, order);
+    }
+
+    /// Release whatever an order that can no longer fill still reserves.
+    pub fn on_order_finished(&mut self, order: &Order) {
+        self.reserved.remove(&order.id);
+    }
+
+    /// Token IDs a position is held in.
+    pub fn held_tokens(&self) -> Vec<String> {
+        self.positions.keys().cloned().collect()
+    }
+
+    /// Pay out a resolved round: each winning share becomes a dollar of cash and the losing
+    /// shares expire worthless. Returns the cash credited.
+    pub fn on_round_resolved(&mut self, winning_token: &str, losing_token: &str) -> f64 {
+        self.forget_tokens(&[losing_token]);
+        let payout = self.positions.get(winning_token).copied().unwrap_or(0.0);
+        self.forget_tokens(&[winning_token]);
+        self.cash += payout;
+        payout
+    }
+
+    /// Stop carrying positions in tokens the account no longer trades, e.g. those of a resolved
+    /// round that the venue pays out on redemption.
+    pub fn forget_tokens(&mut self, token_ids: &[&str]) {
+        for token_id in token_ids {
+            self.positions.remove(*token_id);
+        }
+        self.holdings
+            .retain(|(_, token_id), _| !token_ids.contains(&token_id.as_str()));
+    }
+
+    /// Take cash and positions from the venue's view of the account. Reservations of working
+    /// orders are kept, since the venue reports balances without them.
+    pub fn sync_balances(&mut self, venue: Portfolio) {
+        self.cash = venue.cash;
+        self.positions = venue.positions;
+        let positions = &self.positions;
+        self.holdings
+            .retain(|(_, token_id), _| positions.contains_key(token_id));
+    }
+
+    fn settle(&mut self, strategy: &str, order: &Order, price: f64, size: f64, fee: f64) {
+        let token_id = &order.request.token_id;
+        let position = self.positions.entry(token_id.clone()).or_insert(0.0);
+        let key = (strategy.to_string(), token_id.clone());
+        let holding = self.holdings.entry(key.clone()).or_default();
+        match order.request.side {
+            OrderSide::Buy => {
+                self.cash -= price * size + fee;
+                *position += size;
+                holding.shares += size;
+                holding.cost += price * size + fee;
+            }
+            OrderSide::Sell => {
+                self.cash += price * size - fee;
+                *position = (*position - size).max(0.0);
+                // Shares sold give back their average cost.
+                if holding.shares > 0.0 {
+                    let sold = size.min(holding.shares);
+                    holding.cost -= holding.cost * sold / holding.shares;
+                    holding.shares -= sold;
+                }
+            }
+        }
+        if holding.shares <= 0.0 {
+            self.holdings.remove(&key);
+        }
+    }
+
+    fn sync_reservation(&mut self, strategy: &str, order: &Order) {
+        let working = order.request.side == OrderSide::Buy && !order.is_terminal();
+        if working && order.remaining() > 0.0 {
+            let amount = order.remaining() * order.request.price;
+            self.reserved.insert(order.id, (strategy.to_string(), amount));
+        } else {
+            self.reserved.remove(&order.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::order::{Liquidity, OrderRequest, OrderStatus, OrderType, TimeInForce};
+    use chrono::Utc;
+
+    fn order(side: OrderSide, price: f64, size: f64) -> Order {
+        let mut order = Order::new(
+            OrderId::new_v4(),
+            OrderRequest {
+                market_slug: "BTC-USD-15MIN".to_string(),
+                token_id: "UP".to_string(),
+                side,
+                price,
+                size,
+                client_order_id: "cid".to_string(),
+                order_type: OrderType::Limit,
+                time_in_force: TimeInForce::Gtc,
+                neg_risk: false,
+            },
+        );
+        order.status = OrderStatus::Open;
+        order
+    }
+
+    fn fill(order: &mut Order, price: f64, size: f64, fee: f64) -> Fill {
+        order.apply_fill(price, size, fee);
+        Fill {
+            order_id: order.id,
+            price,
+            size,
+            fee,
+            liquidity: Liquidity::Maker,
+            ts: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn working_buys_reserve_cash_until_filled_or_finished() {
+        let mut portfolio = Portfolio::new(100.0);
+        let mut buy = order(OrderSide::Buy, 0.40, 50.0);
+        portfolio.on_order_accepted("a", &buy);
+        assert!((portfolio.reserved() - 20.0).abs() < 1e-9);
+        assert!((portfolio.available_capital() - 80.0).abs() < 1e-9);
+
+        // A fill below the limit spends less than it had reserved.
+        let f = fill(&mut buy, 0.38, 20.0, 0.1);
+        portfolio.on_fill("a", &buy, &f);
+        assert!((portfolio.cash() - (100.0 - 7.6 - 0.1)).abs() < 1e-9);
+        assert!((portfolio.reserved() - 12.0).abs() < 1e-9);
+        assert_eq!(portfolio.shares("UP"), 20.0);
+
+        buy.status = OrderStatus::Canceled;
+        portfolio.on_order_finished(&buy);
+        assert_eq!(portfolio.reserved(), 0.0);
+        assert!((portfolio.available_capital() - 92.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sells_turn_shares_back_into_cash() {
+        let mut portfolio = Portfolio::new(10.0).with_position("UP", 5.0);
+        let mut sell = order(OrderSide::Sell, 0.30, 5.0);
+        portfolio.on_order_accepted("a", &sell);
+        assert_eq!(portfolio.reserved(), 0.0);
+
+        let f = fill(&mut sell, 0.30, 5.0, 0.0);
+        portfolio.on_fill("a", &sell, &f);
+        assert!((portfolio.cash() - 11.5).abs() < 1e-9);
+        assert_eq!(portfolio.shares("UP"), 0.0);
+    }
+
+    #[test]
+    fn resolved_rounds_pay_out_winning_shares() {
+        let mut portfolio = Portfolio::new(50.0)
+            .with_position("up", 10.0)
+            .with_position("down", 4.0);
+
+        assert_eq!(portfolio.on_round_resolved("up", "down"), 10.0);
+        assert_eq!(portfolio.cash(), 60.0);
+        assert!(portfolio.held_tokens().is_empty());
+        assert_eq!(portfolio.on_round_resolved("up", "down"), 0.0);
+    }
+
+    #[test]
+    fn venue_balances_replace_cash_and_positions_but_keep_reservations() {
+        let mut portfolio = Portfolio::new(100.0).with_position("old", 3.0);
+        portfolio.on_order_accepted("a", &order(OrderSide::Buy, 0.5, 20.0));
+
+        portfolio.sync_balances(Portfolio::new(80.0).with_position("up", 5.0));
+        assert_eq!(portfolio.cash(), 80.0);
+        assert_eq!(portfolio.reserved(), 10.0);
+        assert_eq!(portfolio.held_tokens(), vec!["up".to_string()]);
+    }
+
+    #[test]
+    fn strategies_are_sized_against_their_own_share() {
+        let mut portfolio = Portfolio::new(100.0);
+        let mut buy = order(OrderSide::Buy, 0.50, 60.0);
+        portfolio.on_order_accepted("a", &buy);
+        assert_eq!(portfolio.committed("a"), 30.0);
+        assert_eq!(portfolio.strategy_capital("a", 0.5), 20.0);
+        // Another strategy's working order does not eat into this one's share.
+        assert_eq!(portfolio.strategy_capital("b", 0.5), 50.0);
+
+        // Filled shares stay committed at their cost until sold or paid out.
+        let f = fill(&mut buy, 0.50, 60.0, 0.0);
+        portfolio.on_fill("a", &buy, &f);
+        assert_eq!(portfolio.committed("a"), 30.0);
+        assert_eq!(portfolio.strategy_capital("b", 0.5), 50.0);
+
+        let mut sell = order(OrderSide::Sell, 0.60, 30.0);
+        portfolio.on_order_accepted("a", &sell);
+        let f = fill(&mut sell, 0.60, 30.0, 0.0);
+        portfolio.on_fill("a", &sell, &f);
+        assert!((portfolio.committed("a") - 15.0).abs() < 1e-9);
+
+        portfolio.forget_tokens(&["UP"]);
+        assert_eq!(portfolio.committed("a"), 0.0);
+        assert!((portfolio.strategy_capital("a", 0.5) - 44.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn orders_filled_on_arrival_settle_immediately() {
+        let mut portfolio = Portfolio::new(10.0);
+        let mut buy = order(OrderSide::Buy, 0.50, 4.0);
+        buy.apply_fill(0.50, 4.0, 0.04);
+        portfolio.on_order_accepted("a", &buy);
+        assert!((portfolio.cash() - 7.96).abs() < 1e-9);
+        assert_eq!(portfolio.reserved(), 0.0);
+        assert_eq!(portfolio.shares("UP"), 4.0);
+    }
+}
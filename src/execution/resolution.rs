@@ -0,0 +1,177 @@
+//! Round resolution watcher for dynamic 15m markets.
+//!
+//! A round's shares pay out once its market resolves, some time after the round has ended.
+//! This task polls Gamma for every ended round of the `coin` markets until the result is final
+//! and hands the winning side to the main loop, which settles what the round still holds.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use crate::client::gamma::{current_15m_round_ts, fetch_15m_result, ResolvedMarket, ROUND_SECS};
+use crate::strategy::LegSide;
+use crate::types::MarketConfig;
+
+/// Delay between polls for the results of ended rounds.
+const POLL_INTERVAL_SECS: u64 = 30;
+/// How long after a round ends we keep asking for its result before giving up on it.
+const RESOLVE_TIMEOUT_SECS: i64 = 3600;
+
+/// Final result of a market round.
+#[derive(Clone, Debug)]
+pub(crate) struct RoundResolution {
+    /// The round's market, under its logical slug, with the tokens it paid out on.
+    pub market: ResolvedMarket,
+    pub round_start: DateTime<Utc>,
+    pub winner: LegSide,
+}
+
+impl RoundResolution {
+    /// Token IDs of the winning and the losing side.
+    pub fn tokens(&self) -> (&str, &str) {
+        let (up, down) = (&self.market.up_token_id, &self.market.down_token_id);
+        match self.winner {
+            LegSide::Up => (up, down),
+            LegSide::Down => (down, up),
+        }
+    }
+}
+
+/// A round of a dynamic market whose result has not been published yet.
+struct PendingRound {
+    market_slug: String,
+    coin: String,
+    round_ts: i64,
+}
+
+/// Rounds that ended between `*next_round` and `now`, one per dynamic market; advances
+/// `next_round` past them.
+fn ended_rounds(markets: &[MarketConfig], next_round: &mut i64, now: i64) -> Vec<PendingRound> {
+    let mut ended = Vec::new();
+    while *next_round + ROUND_SECS <= now {
+        for m in markets {
+            ended.push(PendingRound {
+                market_slug: m.slug.clone(),
+                coin: m.coin.clone().unwrap_or_default(),
+                round_ts: *next_round,
+            });
+        }
+        *next_round += ROUND_SECS;
+    }
+    ended
+}
+
+/// Spawn the resolution watcher for all markets that have a `coin` configured.
+///
+/// Rounds that ended before the bot started are not watched, and static markets have no Gamma
+/// slug to poll. If no market is dynamic, no task is spawned and the receiver yields nothing.
+pub(crate) fn spawn_resolution_task(
+    http: reqwest::Client,
+    markets: &[MarketConfig],
+) -> mpsc::Receiver<RoundResolution> {
+    let (tx, rx) = mpsc::channel(16);
+    let dynamic: Vec<MarketConfig> = markets.iter().filter(|m| m.coin.is_some()).cloned().collect();
+    if dynamic.is_empty() {
+        return rx;
+    }
+
+    tokio::spawn(async move {
+        let mut next_round = current_15m_round_ts();
+        let mut pending: Vec<PendingRound> = Vec::new();
+        let mut interval = tokio::time::interval(Duration::from_secs(POLL_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let now = Utc::now().timestamp();
+            pending.extend(ended_rounds(&dynamic, &mut next_round, now));
+
+            let mut still_pending = Vec::new();
+            for round in pending {
+                match fetch_15m_result(&http, &round.coin, &round.market_slug, round.round_ts)
+                    .await
+                {
+                    Ok(Some((market, index))) => {
+                        let winner = if index == 0 { LegSide::Up } else { LegSide::Down };
+                        info!(
+                            target: "bot",
+                            slug = %round.market_slug,
+                            round_ts = round.round_ts,
+                            ?winner,
+                            "round resolved"
+                        );
+                        let resolution = RoundResolution {
+                            market,
+                            round_start: DateTime::from_timestamp(round.round_ts, 0)
+                                .unwrap_or_default(),
+                            winner,
+                        };
+                        if tx.send(resolution).await.is_err() {
+                            debug!(target: "bot", "resolution receiver dropped; stopping watcher");
+                            return;
+                        }
+                        continue;
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        debug!(
+                            target: "bot",
+                            slug = %round.market_slug,
+                            round_ts = round.round_ts,
+                            error = %e,
+                            "failed to fetch round result; will retry"
+                        );
+                    }
+                }
+                if now >= round.round_ts + ROUND_SECS + RESOLVE_TIMEOUT_SECS {
+                    warn!(
+                        target: "bot",
+                        slug = %round.market_slug,
+                        round_ts = round.round_ts,
+                        "round never resolved on Gamma; giving up on it"
+                    );
+                } else {
+                    still_pending.push(round);
+                }
+            }
+            pending = still_pending;
+        }
+    });
+
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market(slug: &str, coin: &str) -> MarketConfig {
+        MarketConfig {
+            slug: slug.to_string(),
+            coin: Some(coin.to_string()),
+            up_token_id: None,
+            down_token_id: None,
+            neg_risk: false,
+        }
+    }
+
+    #[test]
+    fn every_market_is_watched_once_per_ended_round() {
+        let markets = [market("BTC", "btc"), market("ETH", "eth")];
+        let mut next_round = 1_704_110_400;
+
+        assert!(ended_rounds(&markets, &mut next_round, 1_704_111_299).is_empty());
+        let ended = ended_rounds(&markets, &mut next_round, 1_704_112_200);
+        let rounds: Vec<_> = ended.iter().map(|r| (r.coin.as_str(), r.round_ts)).collect();
+        assert_eq!(
+            rounds,
+            vec![
+                ("btc", 1_704_110_400),
+                ("eth", 1_704_110_400),
+                ("btc", 1_704_111_300),
+                ("eth", 1_704_111_300),
+            ]
+        );
+        assert_eq!(next_round, 1_704_112_200);
+    }
+}
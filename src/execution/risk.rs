@@ -0,0 +1,728 @@
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use tracing::warn;
+
+use crate::monitoring::metrics::METRICS;
+use crate::strategy::{LegSide, OrderIntent, TradeAction};
+use crate::types::RiskConfig;
+use crate::utils::time::round_end;
+
+use super::kill_switch::TradingMode;
+use super::order::{Fill, Order, OrderId};
+
+/// Shares below this are treated as flat.
+const SHARE_EPSILON: f64 = 1e-9;
+
+/// How long an ended round waits for its result before its last mark is taken as final.
+const RESOLUTION_WAIT_HOURS: i64 = 24;
+
+/// Why a risk limit refused an order intent.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum RiskViolation {
+    #[error("kill switch: trading is {}", mode.as_str())]
+    KillSwitch { mode: TradingMode },
+
+    #[error("market {market} notional {current:.2} leaves no room under limit {limit:.2}")]
+    MarketNotional {
+        market: String,
+        current: f64,
+        limit: f64,
+    },
+
+    #[error("total exposure {current:.2} leaves no room under limit {limit:.2}")]
+    TotalExposure { current: f64, limit: f64 },
+
+    #[error("realized loss today {loss:.2} reached limit {limit:.2}")]
+    DailyLoss { loss: f64, limit: f64 },
+
+    #[error("order rate limit of {limit} per minute reached")]
+    OrderRate { limit: u32 },
+
+    #[error("unhedged leg in {market} open for {age_secs}s, limit {limit}s")]
+    NakedLegAge {
+        market: String,
+        age_secs: i64,
+        limit: i64,
+    },
+}
+
+impl RiskViolation {
+    /// Short name of the limit, used to tag metrics.
+    pub fn limit(&self) -> &'static str {
+        match self {
+            RiskViolation::KillSwitch { .. } => "kill_switch",
+            RiskViolation::MarketNotional { .. } => "market_notional",
+            RiskViolation::TotalExposure { .. } => "total_exposure",
+            RiskViolation::DailyLoss { .. } => "daily_loss",
+            RiskViolation::OrderRate { .. } => "order_rate",
+            RiskViolation::NakedLegAge { .. } => "naked_leg_age",
+        }
+    }
+}
+
+/// Position of one market round as seen by the risk layer.
+///
+/// Matched UP/DOWN shares pay exactly 1 at resolution, so they are booked as realized PnL
+/// as soon as they pair off; only the unpaired remainder counts as exposure.
+#[derive(Debug, Default)]
+struct RoundBook {
+    /// Unpaired shares held per side (UP, DOWN).
+    shares: [f64; 2],
+    /// What those shares cost, fees included.
+    cost: [f64; 2],
+    /// Unfilled notional of each working buy.
+    working: HashMap<OrderId, f64>,
+    /// When the round last started holding an unpaired leg.
+    naked_since: Option<DateTime<Utc>>,
+}
+
+impl RoundBook {
+    fn notional(&self) -> f64 {
+        self.cost.iter().sum::<f64>() + self.working.values().sum::<f64>()
+    }
+
+    fn avg_cost(&self, i: usize) -> f64 {
+        if self.shares[i] > SHARE_EPSILON {
+            self.cost[i] / self.shares[i]
+        } else {
+            0.0
+        }
+    }
+
+    /// Remove `shares` from side `i` at average cost; returns the cost removed.
+    fn take(&mut self, i: usize, shares: f64) -> f64 {
+        let cost = self.avg_cost(i) * shares;
+        self.shares[i] -= shares;
+        self.cost[i] -= cost;
+        if self.shares[i] <= SHARE_EPSILON {
+            self.shares[i] = 0.0;
+            self.cost[i] = 0.0;
+        }
+        cost
+    }
+
+    fn is_flat(&self) -> bool {
+        self.shares.iter().all(|s| *s <= SHARE_EPSILON)
+    }
+}
+
+/// Unpaired shares of a round that has ended, waiting for its result.
+#[derive(Debug)]
+struct EndedRound {
+    shares: [f64; 2],
+    /// What has been booked as realized for the shares so far: their value at the last mids
+    /// seen for the round, or what they cost while no mid is known.
+    booked: f64,
+    ended_at: DateTime<Utc>,
+}
+
+fn side_index(side: LegSide) -> usize {
+    match side {
+        LegSide::Up => 0,
+        LegSide::Down => 1,
+    }
+}
+
+/// Pre-trade risk checks between the strategies and the executor.
+///
+/// Orders that add risk are held to notional, exposure, daily-loss and naked-leg-age limits,
+/// and shrunk when only part of them fits. Sells and buys that hedge an unpaired leg reduce
+/// risk and only count toward the order rate, so a position can always be closed. The kill
+/// switch sits in front of all of it: hedge-only mode refuses new risk, a halt refuses everything.
+///
+/// A round stops counting against the limits once it ends; what it still holds is marked at
+/// its last mids and settled when its result comes in.
+#[derive(Debug)]
+pub struct RiskManager {
+    config: RiskConfig,
+    trading_mode: TradingMode,
+    rounds: HashMap<(String, DateTime<Utc>), RoundBook>,
+    /// Rounds that ended with unpaired shares, until their result is known.
+    ended: HashMap<(String, DateTime<Utc>), EndedRound>,
+    /// Day `realized_today` covers.
+    day: NaiveDate,
+    realized_today: f64,
+    /// Submission times within the last minute.
+    recent_orders: VecDeque<DateTime<Utc>>,
+}
+
+impl RiskManager {
+    pub fn new(config: RiskConfig) -> Self {
+        Self {
+            config,
+            trading_mode: TradingMode::Active,
+            rounds: HashMap::new(),
+            ended: HashMap::new(),
+            day: NaiveDate::MIN,
+            realized_today: 0.0,
+            recent_orders: VecDeque::new(),
+        }
+    }
+
+    /// Apply the kill switch's effective mode to every following check.
+    pub fn set_trading_mode(&mut self, mode: TradingMode) {
+        self.trading_mode = mode;
+    }
+
+    /// Realized PnL since UTC midnight of the last check or fill.
+    pub fn realized_today(&self) -> f64 {
+        self.realized_today
+    }
+
+    /// Check an intent against every limit; returns it, possibly shrunk, or the violation.
+    ///
+    /// Rejections and reductions are logged and counted in `METRICS`.
+    pub fn check(
+        &mut self,
+        mut intent: OrderIntent,
+        now: DateTime<Utc>,
+    ) -> Result<OrderIntent, RiskViolation> {
+        match self.evaluate(&mut intent, now) {
+            Ok(None) => Ok(intent),
+            Ok(Some(reduced_by)) => {
+                warn!(
+                    target: "execution",
+                    strategy = %intent.strategy,
+                    market = %intent.market_slug,
+                    leg = %intent.label,
+                    shares = intent.shares,
+                    reason = %reduced_by,
+                    "order reduced by risk limit"
+                );
+                METRICS.record_risk_reduced(&intent.market_slug, reduced_by.limit());
+                Ok(intent)
+            }
+            Err(violation) => {
+                warn!(
+                    target: "execution",
+                    strategy = %intent.strategy,
+                    market = %intent.market_slug,
+                    leg = %intent.label,
+                    shares = intent.shares,
+                    reason = %violation,
+                    "order rejected by risk limit"
+                );
+                METRICS.record_risk_rejected(&intent.market_slug, violation.limit());
+                Err(violation)
+            }
+        }
+    }
+
+    /// Apply the limits to `intent`, shrinking it in place; returns the limit that shrank it.
+    fn evaluate(
+        &mut self,
+        intent: &mut OrderIntent,
+        now: DateTime<Utc>,
+    ) -> Result<Option<RiskViolation>, RiskViolation> {
+        self.roll_day(now);
+        self.expire_rounds(now);
+
+        let blocked = match self.trading_mode {
+            TradingMode::Active => false,
+            TradingMode::HedgeOnly => self.adds_risk(intent),
+            TradingMode::Halted => true,
+        };
+        if blocked {
+            return Err(RiskViolation::KillSwitch {
+                mode: self.trading_mode,
+            });
+        }
+
+        if let Some(limit) = self.config.max_orders_per_minute {
+            while self
+                .recent_orders
+                .front()
+                .is_some_and(|ts| *ts <= now - Duration::minutes(1))
+            {
+                self.recent_orders.pop_front();
+            }
+            if self.recent_orders.len() >= limit as usize {
+                return Err(RiskViolation::OrderRate { limit });
+            }
+        }
+
+        let mut reduced_by = None;
+        if self.adds_risk(intent) {
+            if let Some(violation) = self.blocking_violation(now) {
+                return Err(violation);
+            }
+
+            for (headroom, violation) in self.notional_headroom(&intent.market_slug) {
+                let notional = intent.shares * intent.limit_price;
+                if notional <= headroom {
+                    continue;
+                }
+                // Shares, in hundredths, that still fit.
+                let shares = (headroom / intent.limit_price * 100.0).floor() / 100.0;
+                if shares <= 0.0 {
+                    return Err(violation);
+                }
+                intent.shares = shares;
+                reduced_by = Some(violation);
+            }
+        }
+
+        if self.config.max_orders_per_minute.is_some() {
+            self.recent_orders.push_back(now);
+        }
+        Ok(reduced_by)
+    }
+
+    /// Whether an intent can increase exposure. Sells never do; a buy does unless it only
+    /// pairs off shares of the opposite side held in the same round.
+    fn adds_risk(&self, intent: &OrderIntent) -> bool {
+        if intent.action == TradeAction::Sell {
+            return false;
+        }
+        let Some(book) = self
+            .rounds
+            .get(&(intent.market_slug.clone(), intent.round_start))
+        else {
+            return true;
+        };
+        let side = side_index(intent.side);
+        let unpaired_opposite = book.shares[1 - side] - book.shares[side];
+        intent.shares > unpaired_opposite + SHARE_EPSILON
+    }
+
+    /// A limit that refuses any new risk right now: daily loss or a stale naked leg.
+    fn blocking_violation(&self, now: DateTime<Utc>) -> Option<RiskViolation> {
+        if let Some(limit) = self.config.max_daily_loss_usd {
+            if -self.realized_today >= limit {
+                return Some(RiskViolation::DailyLoss {
+                    loss: -self.realized_today,
+                    limit,
+                });
+            }
+        }
+        if let Some(limit) = self.config.max_naked_leg_secs {
+            let stale = self.rounds.iter().find_map(|((market, _), book)| {
+                let age_secs = (now - book.naked_since?).num_seconds();
+                (age_secs > limit).then(|| (market.clone(), age_secs))
+            });
+            if let Some((market, age_secs)) = stale {
+                return Some(RiskViolation::NakedLegAge {
+                    market,
+                    age_secs,
+                    limit,
+                });
+            }
+        }
+        None
+    }
+
+    /// Room left under the market and total notional limits, with the violation for each.
+    fn notional_headroom(&self, market_slug: &str) -> Vec<(f64, RiskViolation)> {
+        let mut headroom = Vec::new();
+        if let Some(limit) = self.config.max_market_notional_usd {
+            let current: f64 = self
+                .rounds
+                .iter()
+                .filter(|((market, _), _)| market == market_slug)
+                .map(|(_, book)| book.notional())
+                .sum();
+            headroom.push((
+                limit - current,
+                RiskViolation::MarketNotional {
+                    market: market_slug.to_string(),
+                    current,
+                    limit,
+                },
+            ));
+        }
+        if let Some(limit) = self.config.max_total_exposure_usd {
+            let current: f64 = self.rounds.values().map(RoundBook::notional).sum();
+            headroom.push((limit - current, RiskViolation::TotalExposure { current, limit }));
+        }
+        headroom
+    }
+
+    /// Start counting working buy notional for an accepted order, and any fill it came with.
+    pub fn on_order_accepted(&mut self, intent: &OrderIntent, order: &Order, ts: DateTime<Utc>) {
+        if order.filled_size > 0.0 {
+            self.settle(intent, order.avg_fill_price, order.filled_size, order.fees_paid, ts);
+        }
+        self.sync_working(intent, order);
+    }
+
+    /// Apply a fill; `order` must already include it.
+    pub fn on_fill(&mut self, intent: &OrderIntent, order: &Order, fill: &Fill) {
+        self.settle(intent, fill.price, fill.size, fill.fee, fill.ts);
+        self.sync_working(intent, order);
+    }
+
+    /// Stop counting an order that can no longer fill.
+    pub fn on_order_finished(&mut self, intent: &OrderIntent, order: &Order) {
+        self.sync_working(intent, order);
+    }
+
+    /// Mark what an ended round still holds at its last mids (UP, DOWN), when known.
+    ///
+    /// The difference to what was booked for the shares before is realized now; the rest
+    /// follows from [`Self::on_round_resolved`].
+    pub fn on_round_end(
+        &mut self,
+        market_slug: &str,
+        round_start: DateTime<Utc>,
+        mids: Option<[f64; 2]>,
+        ts: DateTime<Utc>,
+    ) {
+        self.roll_day(ts);
+        let key = (market_slug.to_string(), round_start);
+        self.end_round(&key, ts);
+        let (Some(ended), Some(mids)) = (self.ended.get_mut(&key), mids) else {
+            return;
+        };
+        let marked = ended.shares[0] * mids[0] + ended.shares[1] * mids[1];
+        self.realized_today += marked - ended.booked;
+        ended.booked = marked;
+    }
+
+    /// Settle an ended round: its winning shares pay 1 each, its losing ones nothing.
+    pub fn on_round_resolved(
+        &mut self,
+        market_slug: &str,
+        round_start: DateTime<Utc>,
+        winner: LegSide,
+        ts: DateTime<Utc>,
+    ) {
+        self.roll_day(ts);
+        let key = (market_slug.to_string(), round_start);
+        self.end_round(&key, ts);
+        if let Some(ended) = self.ended.remove(&key) {
+            self.realized_today += ended.shares[side_index(winner)] - ended.booked;
+        }
+    }
+
+    /// End every round past its scheduled end, so nothing a finished round holds blocks new
+    /// risk, and give up on results that never came.
+    fn expire_rounds(&mut self, now: DateTime<Utc>) {
+        let expired: Vec<_> = self
+            .rounds
+            .keys()
+            .filter(|(_, round_start)| round_end(*round_start) <= now)
+            .cloned()
+            .collect();
+        for key in expired {
+            self.end_round(&key, now);
+        }
+        self.ended
+            .retain(|_, ended| now - ended.ended_at < Duration::hours(RESOLUTION_WAIT_HOURS));
+    }
+
+    /// Move a round's unpaired shares out of the limits, carried at cost until marked.
+    fn end_round(&mut self, key: &(String, DateTime<Utc>), now: DateTime<Utc>) {
+        let Some(book) = self.rounds.remove(key) else {
+            return;
+        };
+        if book.is_flat() {
+            return;
+        }
+        let ended = self.ended.entry(key.clone()).or_insert(EndedRound {
+            shares: [0.0; 2],
+            booked: 0.0,
+            ended_at: now,
+        });
+        for (held, shares) in ended.shares.iter_mut().zip(book.shares) {
+            *held += shares;
+        }
+        ended.booked += book.cost.iter().sum::<f64>();
+    }
+
+    fn sync_working(&mut self, intent: &OrderIntent, order: &Order) {
+        let book = self
+            .rounds
+            .entry((intent.market_slug.clone(), intent.round_start))
+            .or_default();
+        if intent.action == TradeAction::Buy && !order.is_terminal() && order.remaining() > 0.0 {
+            book.working
+                .insert(order.id, order.remaining() * order.request.price);
+        } else {
+            book.working.remove(&order.id);
+        }
+    }
+
+    fn settle(&mut self, intent: &OrderIntent, price: f64, size: f64, fee: f64, ts: DateTime<Utc>) {
+        self.roll_day(ts);
+        let book = self
+            .rounds
+            .entry((intent.market_slug.clone(), intent.round_start))
+            .or_default();
+        let side = side_index(intent.side);
+        let mut realized = 0.0;
+
+        match intent.action {
+            TradeAction::Buy => {
+                book.shares[side] += size;
+                book.cost[side] += price * size + fee;
+                let pairs = book.shares[0].min(book.shares[1]);
+                if pairs > SHARE_EPSILON {
+                    let cost = book.take(0, pairs) + book.take(1, pairs);
+                    realized += pairs - cost;
+                }
+            }
+            TradeAction::Sell => {
+                let sold = size.min(book.shares[side]);
+                let cost = book.take(side, sold);
+                realized += price * sold - fee - cost;
+            }
+        }
+
+        if book.is_flat() {
+            book.naked_since = None;
+        } else if book.naked_since.is_none() {
+            book.naked_since = Some(ts);
+        }
+        self.realized_today += realized;
+    }
+
+    fn roll_day(&mut self, now: DateTime<Utc>) {
+        let today = now.date_naive();
+        if today > self.day {
+            self.day = today;
+            self.realized_today = 0.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::order::{
+        Liquidity, OrderRequest, OrderSide, OrderStatus, OrderType, TimeInForce,
+    };
+
+    fn ts(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(&format!("{s}Z")).unwrap().with_timezone(&Utc)
+    }
+
+    fn intent(side: LegSide, action: TradeAction, shares: f64, price: f64) -> OrderIntent {
+        OrderIntent {
+            strategy: "two_leg".to_string(),
+            market_slug: "BTC_15m".to_string(),
+            round_start: ts("2024-01-01T12:00:00"),
+            side,
+            action,
+            shares,
+            limit_price: price,
+            label: "leg1".to_string(),
+            expected_locked_profit: None,
+            attempt: 1,
+            replaces: Vec::new(),
+            immediate_or_cancel: false,
+            reduces_risk: false,
+        }
+    }
+
+    fn order_for(intent: &OrderIntent) -> Order {
+        let mut order = Order::new(
+            OrderId::new_v4(),
+            OrderRequest {
+                market_slug: intent.market_slug.clone(),
+                token_id: format!("{:?}", intent.side),
+                side: match intent.action {
+                    TradeAction::Buy => OrderSide::Buy,
+                    TradeAction::Sell => OrderSide::Sell,
+                },
+                price: intent.limit_price,
+                size: intent.shares,
+                client_order_id: "cid".to_string(),
+                order_type: OrderType::Limit,
+                time_in_force: TimeInForce::Gtc,
+                neg_risk: false,
+            },
+        );
+        order.status = OrderStatus::Open;
+        order
+    }
+
+    /// Submit `intent` and fill it completely at its limit price.
+    fn fill(risk: &mut RiskManager, intent: &OrderIntent, at: &str) {
+        let mut order = order_for(intent);
+        risk.on_order_accepted(intent, &order, ts(at));
+        order.apply_fill(intent.limit_price, intent.shares, 0.0);
+        let f = Fill {
+            order_id: order.id,
+            price: intent.limit_price,
+            size: intent.shares,
+            fee: 0.0,
+            liquidity: Liquidity::Taker,
+            ts: ts(at),
+        };
+        risk.on_fill(intent, &order, &f);
+    }
+
+    #[test]
+    fn shrinks_buys_to_the_notional_headroom() {
+        let mut risk = RiskManager::new(RiskConfig {
+            max_market_notional_usd: Some(10.0),
+            max_total_exposure_usd: Some(100.0),
+            ..RiskConfig::default()
+        });
+        let now = ts("2024-01-01T12:01:00");
+
+        let leg1 = intent(LegSide::Up, TradeAction::Buy, 20.0, 0.4);
+        let working = order_for(&leg1);
+        risk.on_order_accepted(&leg1, &working, now);
+
+        // 8 of the 10 USD are committed to the working order: 5 shares at 0.4 still fit.
+        let more = risk.check(intent(LegSide::Up, TradeAction::Buy, 20.0, 0.4), now).unwrap();
+        assert!((more.shares - 5.0).abs() < 1e-9);
+
+        let mut order = working.clone();
+        order.status = OrderStatus::Canceled;
+        risk.on_order_finished(&leg1, &order);
+        let full = risk.check(intent(LegSide::Up, TradeAction::Buy, 20.0, 0.4), now).unwrap();
+        assert_eq!(full.shares, 20.0);
+
+        risk.on_order_accepted(&leg1, &working, now);
+        // Nothing is held yet, so a DOWN buy is new risk rather than a hedge.
+        let down = risk.check(intent(LegSide::Down, TradeAction::Buy, 30.0, 0.5), now).unwrap();
+        assert!((down.shares - 4.0).abs() < 1e-9);
+
+        let rest = intent(LegSide::Up, TradeAction::Buy, 5.0, 0.4);
+        risk.on_order_accepted(&rest, &order_for(&rest), now);
+        let violation = risk
+            .check(intent(LegSide::Up, TradeAction::Buy, 1.0, 0.4), now)
+            .unwrap_err();
+        assert_eq!(violation.limit(), "market_notional");
+    }
+
+    #[test]
+    fn hedges_and_sells_pass_while_new_risk_is_blocked() {
+        let mut risk = RiskManager::new(RiskConfig {
+            max_market_notional_usd: Some(5.0),
+            max_daily_loss_usd: Some(1.0),
+            ..RiskConfig::default()
+        });
+        let leg1 = intent(LegSide::Up, TradeAction::Buy, 10.0, 0.45);
+        fill(&mut risk, &leg1, "2024-01-01T12:01:00");
+        let now = ts("2024-01-01T12:02:00");
+
+        // The market is nearly full, but hedging the 10 UP shares reduces risk.
+        let hedge = intent(LegSide::Down, TradeAction::Buy, 10.0, 0.5);
+        assert_eq!(risk.check(hedge, now).unwrap().shares, 10.0);
+
+        // Selling 10 UP at 0.30 realizes a 1.50 loss, which blocks new risk for the day...
+        let exit = intent(LegSide::Up, TradeAction::Sell, 10.0, 0.30);
+        assert!(risk.check(exit.clone(), now).is_ok());
+        fill(&mut risk, &exit, "2024-01-01T12:02:00");
+        assert!((risk.realized_today() + 1.5).abs() < 1e-9);
+        let violation = risk.check(leg1.clone(), now).unwrap_err();
+        assert_eq!(violation.limit(), "daily_loss");
+
+        // ...until the next UTC day.
+        assert!(risk.check(leg1, ts("2024-01-02T00:00:01")).is_ok());
+        assert_eq!(risk.realized_today(), 0.0);
+    }
+
+    #[test]
+    fn pairs_book_locked_profit_and_clear_exposure() {
+        let mut risk = RiskManager::new(RiskConfig {
+            max_naked_leg_secs: Some(60),
+            ..RiskConfig::default()
+        });
+        let leg1 = intent(LegSide::Up, TradeAction::Buy, 10.0, 0.4);
+        fill(&mut risk, &leg1, "2024-01-01T12:01:00");
+
+        // An unhedged leg older than a minute blocks new risk in every market.
+        let mut other = intent(LegSide::Up, TradeAction::Buy, 10.0, 0.4);
+        other.market_slug = "ETH_15m".to_string();
+        let violation = risk.check(other.clone(), ts("2024-01-01T12:02:30")).unwrap_err();
+        assert_eq!(violation.limit(), "naked_leg_age");
+
+        let hedge = intent(LegSide::Down, TradeAction::Buy, 10.0, 0.55);
+        fill(&mut risk, &hedge, "2024-01-01T12:02:30");
+        assert!((risk.realized_today() - 0.5).abs() < 1e-9);
+        assert!(risk.check(other, ts("2024-01-01T12:02:31")).is_ok());
+    }
+
+    #[test]
+    fn caps_orders_per_rolling_minute() {
+        let mut risk = RiskManager::new(RiskConfig {
+            max_orders_per_minute: Some(2),
+            ..RiskConfig::default()
+        });
+        let sell = intent(LegSide::Up, TradeAction::Sell, 1.0, 0.4);
+        assert!(risk.check(sell.clone(), ts("2024-01-01T12:00:00")).is_ok());
+        assert!(risk.check(sell.clone(), ts("2024-01-01T12:00:30")).is_ok());
+        let violation = risk.check(sell.clone(), ts("2024-01-01T12:00:59")).unwrap_err();
+        assert_eq!(violation, RiskViolation::OrderRate { limit: 2 });
+        assert!(risk.check(sell, ts("2024-01-01T12:01:01")).is_ok());
+    }
+
+    #[test]
+    fn kill_switch_blocks_new_risk_then_everything() {
+        let mut risk = RiskManager::new(RiskConfig::default());
+        let leg1 = intent(LegSide::Up, TradeAction::Buy, 10.0, 0.4);
+        fill(&mut risk, &leg1, "2024-01-01T12:01:00");
+        let now = ts("2024-01-01T12:02:00");
+        let hedge = intent(LegSide::Down, TradeAction::Buy, 10.0, 0.55);
+
+        risk.set_trading_mode(TradingMode::HedgeOnly);
+        let violation = risk.check(leg1.clone(), now).unwrap_err();
+        assert_eq!(violation.limit(), "kill_switch");
+        assert!(risk.check(hedge.clone(), now).is_ok());
+
+        risk.set_trading_mode(TradingMode::Halted);
+        assert!(risk.check(hedge, now).is_err());
+
+        risk.set_trading_mode(TradingMode::Active);
+        assert!(risk.check(leg1, now).is_ok());
+    }
+
+    #[test]
+    fn ended_rounds_are_marked_then_settled_at_their_result() {
+        let mut risk = RiskManager::new(RiskConfig::default());
+        let start = ts("2024-01-01T12:00:00");
+        fill(&mut risk, &intent(LegSide::Up, TradeAction::Buy, 10.0, 0.4), "2024-01-01T12:01:00");
+
+        // Selling more than is held only realizes the shares that were there.
+        let exit = intent(LegSide::Up, TradeAction::Sell, 15.0, 0.3);
+        fill(&mut risk, &exit, "2024-01-01T12:02:00");
+        assert!((risk.realized_today() + 1.0).abs() < 1e-9);
+
+        // An unhedged leg is carried at its last mid once the round ends...
+        let leg1 = intent(LegSide::Down, TradeAction::Buy, 10.0, 0.4);
+        fill(&mut risk, &leg1, "2024-01-01T12:03:00");
+        risk.on_round_end("BTC_15m", start, Some([0.9, 0.1]), ts("2024-01-01T12:15:00"));
+        assert!((risk.realized_today() + 4.0).abs() < 1e-9);
+
+        // ...and pays 1 a share when it turns out to have won.
+        risk.on_round_resolved("BTC_15m", start, LegSide::Down, ts("2024-01-01T12:20:00"));
+        assert!((risk.realized_today() - 5.0).abs() < 1e-9);
+
+        // Without mids the leg stays at cost until its result; a losing leg is lost in full.
+        let mut next = intent(LegSide::Up, TradeAction::Buy, 10.0, 0.4);
+        next.round_start = ts("2024-01-01T12:15:00");
+        fill(&mut risk, &next, "2024-01-01T12:16:00");
+        risk.on_round_end("BTC_15m", next.round_start, None, ts("2024-01-01T12:30:00"));
+        assert!((risk.realized_today() - 5.0).abs() < 1e-9);
+        let resolved = ts("2024-01-01T12:35:00");
+        risk.on_round_resolved("BTC_15m", next.round_start, LegSide::Down, resolved);
+        risk.on_round_resolved("BTC_15m", next.round_start, LegSide::Down, resolved);
+        assert!((risk.realized_today() - 1.0).abs() < 1e-9);
+        assert_eq!(risk.check(next, resolved).unwrap().shares, 10.0);
+    }
+
+    #[test]
+    fn naked_legs_stop_blocking_once_their_round_is_over() {
+        let mut risk = RiskManager::new(RiskConfig {
+            max_naked_leg_secs: Some(60),
+            max_total_exposure_usd: Some(5.0),
+            ..RiskConfig::default()
+        });
+        fill(&mut risk, &intent(LegSide::Up, TradeAction::Buy, 10.0, 0.4), "2024-01-01T12:01:00");
+        let mut other = intent(LegSide::Up, TradeAction::Buy, 10.0, 0.4);
+        other.market_slug = "ETH_15m".to_string();
+        other.round_start = ts("2024-01-01T12:15:00");
+        let violation = risk.check(other.clone(), ts("2024-01-01T12:14:00")).unwrap_err();
+        assert_eq!(violation.limit(), "naked_leg_age");
+
+        // No rollover has come in for the round, but it is over: it no longer blocks or counts
+        // as exposure, and nothing is booked for it yet.
+        assert_eq!(risk.check(other, ts("2024-01-01T12:15:00")).unwrap().shares, 10.0);
+        assert_eq!(risk.realized_today(), 0.0);
+    }
+}
@@ -0,0 +1,168 @@
+//! Round rollover scheduler for dynamic 15m markets.
+//!
+//! Token IDs for `{coin}-updown-15m-{ts}` markets change every round. This task pre-resolves
+//! the next round's slug shortly before each 900-second boundary and hands the new token IDs
+//! to the main loop once the boundary has passed, so the bot never listens to an expired market.
+
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use crate::client::gamma::{next_15m_round_ts, resolve_15m_market_at, ResolvedMarket};
+use crate::types::MarketConfig;
+
+/// How long before the round boundary we start resolving the next round.
+const PRE_RESOLVE_LEAD_SECS: i64 = 60;
+/// Delay between resolution attempts while Gamma has not listed the next round yet.
+const RETRY_INTERVAL_SECS: i64 = 5;
+/// How far into the new round we keep retrying before giving up on a market for that round.
+const RESOLVE_GRACE_SECS: i64 = 120;
+
+/// Markets resolved for a new round, to be swapped in by the main loop.
+#[derive(Clone, Debug)]
+pub(crate) struct MarketRollover {
+    pub round_ts: i64,
+    pub markets: Vec<ResolvedMarket>,
+}
+
+fn duration_until(unix_secs: i64) -> Duration {
+    let remaining_ms = unix_secs.saturating_mul(1000) - Utc::now().timestamp_millis();
+    Duration::from_millis(remaining_ms.max(0) as u64)
+}
+
+/// Delay before the next resolution attempt for a round starting at `round_ts`.
+///
+/// Retries never sleep past the boundary so already-resolved markets are handed over on time.
+fn next_attempt_delay(now_ms: i64, round_ts: i64) -> Duration {
+    let retry_ms = RETRY_INTERVAL_SECS * 1000;
+    let until_boundary_ms = round_ts * 1000 - now_ms;
+    if until_boundary_ms > 0 {
+        Duration::from_millis(retry_ms.min(until_boundary_ms) as u64)
+    } else {
+        Duration::from_millis(retry_ms as u64)
+    }
+}
+
+/// Spawn the rollover scheduler for all markets that have a `coin` configured.
+///
+/// Static markets (explicit token IDs) never roll over and are ignored. If no market is
+/// dynamic, no task is spawned and the returned receiver yields nothing.
+pub(crate) fn spawn_rollover_task(
+    http: reqwest::Client,
+    markets: &[MarketConfig],
+) -> mpsc::Receiver<MarketRollover> {
+    let (tx, rx) = mpsc::channel(4);
+    let dynamic: Vec<MarketConfig> = markets.iter().filter(|m| m.coin.is_some()).cloned().collect();
+    if dynamic.is_empty() {
+        return rx;
+    }
+
+    tokio::spawn(async move {
+        loop {
+            let round_ts = next_15m_round_ts(Utc::now().timestamp());
+            tokio::time::sleep(duration_until(round_ts - PRE_RESOLVE_LEAD_SECS)).await;
+
+            if !roll_round(&http, &dynamic, round_ts, &tx).await {
+                debug!(target: "bot", "rollover receiver dropped; stopping scheduler");
+                break;
+            }
+        }
+    });
+
+    rx
+}
+
+/// Resolve every dynamic market for `round_ts` and deliver them once the round has started.
+///
+/// Returns `false` if the receiving side has been dropped.
+async fn roll_round(
+    http: &reqwest::Client,
+    markets: &[MarketConfig],
+    round_ts: i64,
+    tx: &mpsc::Sender<MarketRollover>,
+) -> bool {
+    let deadline = round_ts + RESOLVE_GRACE_SECS;
+    let mut pending: Vec<&MarketConfig> = markets.iter().collect();
+    let mut ready: Vec<ResolvedMarket> = Vec::new();
+
+    loop {
+        let mut still_pending = Vec::new();
+        for m in pending {
+            let coin = m.coin.as_deref().unwrap_or_default();
+            match resolve_15m_market_at(http, coin, &m.slug, round_ts).await {
+                Ok(Some(r)) => {
+                    debug!(target: "bot", slug = %r.slug, round_ts, "pre-resolved next 15m market");
+                    ready.push(r);
+                }
+                Ok(None) => {
+                    debug!(target: "bot", slug = %m.slug, round_ts, "next 15m market not listed yet");
+                    still_pending.push(m);
+                }
+                Err(e) => {
+                    warn!(
+                        target: "bot",
+                        slug = %m.slug,
+                        round_ts,
+                        error = %e,
+                        "failed to resolve next 15m market; will retry"
+                    );
+                    still_pending.push(m);
+                }
+            }
+        }
+        pending = still_pending;
+
+        if pending.is_empty() {
+            tokio::time::sleep(duration_until(round_ts)).await;
+        }
+
+        let now_ms = Utc::now().timestamp_millis();
+        if now_ms >= round_ts * 1000 && !ready.is_empty() {
+            info!(target: "bot", round_ts, count = ready.len(), "rolling markets over to new round");
+            let rollover = MarketRollover {
+                round_ts,
+                markets: std::mem::take(&mut ready),
+            };
+            if tx.send(rollover).await.is_err() {
+                return false;
+            }
+        }
+
+        if pending.is_empty() {
+            return true;
+        }
+
+        if now_ms >= deadline * 1000 {
+            for m in &pending {
+                warn!(
+                    target: "bot",
+                    slug = %m.slug,
+                    round_ts,
+                    "Gamma API never listed market for round; skipping round"
+                );
+            }
+            return true;
+        }
+
+        tokio::time::sleep(next_attempt_delay(now_ms, round_ts)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_are_capped_by_boundary() {
+        let round_ms = 1_704_111_300 * 1000;
+        let retry = Duration::from_secs(RETRY_INTERVAL_SECS as u64);
+        assert_eq!(next_attempt_delay(round_ms - 60_000, 1_704_111_300), retry);
+        assert_eq!(
+            next_attempt_delay(round_ms - 1_500, 1_704_111_300),
+            Duration::from_millis(1_500)
+        );
+        assert_eq!(next_attempt_delay(round_ms + 10_000, 1_704_111_300), retry);
+    }
+}
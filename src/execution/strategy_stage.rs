@@ -0,0 +1,249 @@
+//! Strategy stage between market data and order submission.
+//!
+//! The strategies run on a task of their own. The main loop hands them every snapshot, with
+//! the capital each strategy may size against at that moment, and every execution report
+//! (fills, order outcomes, round ends) in the order it applied them; the stage answers with
+//! the intents the strategies emitted. Both directions are bounded channels. When the stage
+//! falls behind, queued snapshots of a market are folded into the newest one, while reports
+//! are never dropped.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use tracing::debug;
+
+use crate::monitoring::metrics::METRICS;
+use crate::strategy::{MarketSnapshot, OrderIntent, OrderUpdate, StrategyFill, StrategySet};
+
+/// Events queued for the strategies; snapshots beyond this are dropped instead of holding up
+/// the main loop.
+const EVENT_CAPACITY: usize = 1024;
+/// Intent batches waiting for the main loop.
+const INTENT_CAPACITY: usize = 64;
+/// Most queued events the stage takes in one go, folding stale snapshots among them.
+const MAX_EVENT_BATCH: usize = 256;
+
+/// Input to the strategies.
+pub(crate) enum StrategyEvent {
+    /// A market snapshot, with the capital of each strategy by instance name.
+    Snapshot {
+        snapshot: MarketSnapshot,
+        capital: HashMap<String, f64>,
+    },
+    Fill(StrategyFill),
+    Update(OrderUpdate),
+    RoundEnd {
+        market_slug: String,
+        round_start: DateTime<Utc>,
+    },
+}
+
+/// Main loop side of the strategy stage.
+pub(crate) struct StrategyStage {
+    events_tx: mpsc::Sender<StrategyEvent>,
+    /// Instance name and capital allocation of every strategy.
+    allocations: Vec<(String, f64)>,
+}
+
+impl StrategyStage {
+    /// Run `strategies` on their own task. Returns the stage and the receiver of the intents
+    /// they emit; the task ends once the stage is dropped.
+    pub fn spawn(strategies: StrategySet) -> (Self, mpsc::Receiver<Vec<OrderIntent>>) {
+        let (events_tx, events_rx) = mpsc::channel(EVENT_CAPACITY);
+        let (intents_tx, intents_rx) = mpsc::channel(INTENT_CAPACITY);
+        let allocations = strategies
+            .allocations()
+            .map(|(name, allocation)| (name.to_string(), allocation))
+            .collect();
+        tokio::spawn(run_strategies(strategies, events_rx, intents_tx));
+        (
+            Self {
+                events_tx,
+                allocations,
+            },
+            intents_rx,
+        )
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.allocations.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Hand a snapshot to the strategies, each sized by `capital(name, allocation)`.
+    ///
+    /// If the stage is too far behind to take it, the snapshot is dropped (and counted); the
+    /// market's next one supersedes it anyway.
+    pub fn on_snapshot(&self, snapshot: MarketSnapshot, capital: impl Fn(&str, f64) -> f64) {
+        let capital = self
+            .allocations
+            .iter()
+            .map(|(name, allocation)| (name.clone(), capital(name, *allocation)))
+            .collect();
+        if let Err(TrySendError::Full(_)) =
+            self.events_tx.try_send(StrategyEvent::Snapshot { snapshot, capital })
+        {
+            METRICS.record_snapshots_coalesced(1);
+        }
+    }
+
+    /// Route a fill to the strategy that emitted the order.
+    pub async fn on_fill(&self, fill: StrategyFill) {
+        self.send(StrategyEvent::Fill(fill)).await;
+    }
+
+    /// Route an order lifecycle update to the strategy that emitted the order.
+    pub async fn on_order_update(&self, update: OrderUpdate) {
+        self.send(StrategyEvent::Update(update)).await;
+    }
+
+    pub async fn on_round_end(&self, market_slug: &str, round_start: DateTime<Utc>) {
+        self.send(StrategyEvent::RoundEnd {
+            market_slug: market_slug.to_string(),
+            round_start,
+        })
+        .await;
+    }
+
+    async fn send(&self, event: StrategyEvent) {
+        // Only fails once the task is gone, i.e. during shutdown.
+        let _ = self.events_tx.send(event).await;
+    }
+}
+
+/// Drop every snapshot followed by a newer one of the same market. Returns the events left, in
+/// their original order, and how many snapshots were dropped.
+fn coalesce(events: Vec<StrategyEvent>) -> (Vec<StrategyEvent>, usize) {
+    let total = events.len();
+    let mut newest = HashSet::new();
+    let mut kept: Vec<StrategyEvent> = events
+        .into_iter()
+        .rev()
+        .filter(|event| match event {
+            StrategyEvent::Snapshot { snapshot, .. } => newest.insert(snapshot.market_slug.clone()),
+            _ => true,
+        })
+        .collect();
+    kept.reverse();
+    let dropped = total - kept.len();
+    (kept, dropped)
+}
+
+fn apply(strategies: &mut StrategySet, event: StrategyEvent, intents: &mut Vec<OrderIntent>) {
+    match event {
+        StrategyEvent::Snapshot { snapshot, capital } => {
+            intents.extend(strategies.on_snapshot(&snapshot, |name, _| {
+                capital.get(name).copied().unwrap_or(0.0)
+            }));
+        }
+        StrategyEvent::Fill(fill) => strategies.on_fill(&fill),
+        StrategyEvent::Update(update) => strategies.on_order_update(&update),
+        StrategyEvent::RoundEnd {
+            market_slug,
+            round_start,
+        } => strategies.on_round_end(&market_slug, round_start),
+    }
+}
+
+/// Strategy task: apply events as they come and pass intents on whenever the main loop has
+/// room. Events keep being taken in while intents wait, so the main loop never blocks on a
+/// stage that is blocked on it.
+async fn run_strategies(
+    mut strategies: StrategySet,
+    mut events_rx: mpsc::Receiver<StrategyEvent>,
+    intents_tx: mpsc::Sender<Vec<OrderIntent>>,
+) {
+    let mut pending = Vec::new();
+    loop {
+        tokio::select! {
+            permit = intents_tx.reserve(), if !pending.is_empty() => match permit {
+                Ok(permit) => permit.send(std::mem::take(&mut pending)),
+                Err(_) => break,
+            },
+            event = events_rx.recv() => {
+                let Some(event) = event else {
+                    break;
+                };
+                let mut events = vec![event];
+                while events.len() < MAX_EVENT_BATCH {
+                    match events_rx.try_recv() {
+                        Ok(event) => events.push(event),
+                        Err(_) => break,
+                    }
+                }
+                let (events, dropped) = coalesce(events);
+                if dropped > 0 {
+                    METRICS.record_snapshots_coalesced(dropped as u64);
+                }
+                for event in events {
+                    apply(&mut strategies, event, &mut pending);
+                }
+            }
+        }
+    }
+    debug!(target: "bot", "strategy stage stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(market_slug: &str, up_ask: f64) -> StrategyEvent {
+        StrategyEvent::Snapshot {
+            snapshot: MarketSnapshot {
+                market_slug: market_slug.to_string(),
+                ts: Utc::now(),
+                up_bid: 0.48,
+                up_ask,
+                down_bid: 0.49,
+                down_ask: 0.51,
+                up_bid_size: None,
+                up_ask_size: None,
+                down_bid_size: None,
+                down_ask_size: None,
+                depth: None,
+            },
+            capital: HashMap::new(),
+        }
+    }
+
+    fn round_end(market_slug: &str) -> StrategyEvent {
+        StrategyEvent::RoundEnd {
+            market_slug: market_slug.to_string(),
+            round_start: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn only_the_newest_snapshot_of_a_market_is_kept() {
+        let events = vec![
+            snapshot("BTC", 0.50),
+            snapshot("ETH", 0.50),
+            round_end("BTC"),
+            snapshot("BTC", 0.52),
+            snapshot("BTC", 0.53),
+        ];
+        let (kept, dropped) = coalesce(events);
+        assert_eq!(dropped, 2);
+
+        let kept: Vec<(String, Option<f64>)> = kept
+            .into_iter()
+            .map(|event| match event {
+                StrategyEvent::Snapshot { snapshot, .. } => {
+                    (snapshot.market_slug, Some(snapshot.up_ask))
+                }
+                StrategyEvent::RoundEnd { market_slug, .. } => (market_slug, None),
+                _ => panic!("unexpected event"),
+            })
+            .collect();
+        assert_eq!(
+            kept,
+            vec![
+                ("ETH".to_string(), Some(0.50)),
+                ("BTC".to_string(), None),
+                ("BTC".to_string(), Some(0.53)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Deserialize;
+use tracing::debug;
+
+use super::order::{Liquidity, OrderStatus};
+
+/// Lifecycle change of an order reported on the user channel.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct OrderUpdate {
+    pub venue_order_id: String,
+    /// New status, if the event implies one; fills are taken from trade events instead.
+    pub status: Option<OrderStatus>,
+}
+
+/// One order's share of a trade reported on the user channel.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct TradeFill {
+    pub trade_id: String,
+    pub venue_order_id: String,
+    pub price: f64,
+    pub size: f64,
+    pub fee_rate_bps: f64,
+    pub liquidity: Liquidity,
+    pub ts: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum UserEvent {
+    Order(OrderUpdate),
+    Fill(TradeFill),
+    /// An order's share of a trade that failed to settle on-chain; a fill applied from an
+    /// earlier report of the trade must be taken back.
+    TradeFailed(TradeFill),
+}
+
+#[derive(Debug, Deserialize)]
+struct OrderEvent {
+    id: String,
+    #[serde(rename = "type", default)]
+    kind: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MakerOrder {
+    order_id: String,
+    matched_amount: String,
+    price: String,
+    #[serde(default)]
+    fee_rate_bps: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TradeEvent {
+    id: String,
+    #[serde(default)]
+    status: String,
+    price: String,
+    size: String,
+    #[serde(default)]
+    taker_order_id: Option<String>,
+    #[serde(default)]
+    maker_orders: Vec<MakerOrder>,
+    #[serde(default)]
+    fee_rate_bps: Option<String>,
+    #[serde(default)]
+    timestamp: Option<String>,
+}
+
+/// User channel timestamps come in seconds or milliseconds depending on the event.
+fn parse_timestamp(ts: Option<&str>) -> DateTime<Utc> {
+    ts.and_then(|ts| ts.parse::<i64>().ok())
+        .and_then(|n| {
+            if n < 100_000_000_000 {
+                Utc.timestamp_opt(n, 0).single()
+            } else {
+                Utc.timestamp_millis_opt(n).single()
+            }
+        })
+        .unwrap_or_else(Utc::now)
+}
+
+fn parse_f64(s: &str) -> Option<f64> {
+    s.parse::<f64>().ok()
+}
+
+/// Parse a user channel message into order updates and per-order trade fills.
+///
+/// A trade lists the taker order and every maker order it matched; all of them are returned,
+/// and the executor keeps the ones it owns. Failed trades are returned per order the same way,
+/// as [`UserEvent::TradeFailed`].
+pub(crate) fn parse_user_events(text: &str) -> Vec<UserEvent> {
+    let v: serde_json::Value = match serde_json::from_str(text) {
+        Ok(v) => v,
+        Err(_) => return Vec::new(),
+    };
+    let items = match v {
+        serde_json::Value::Array(items) => items,
+        other => vec![other],
+    };
+
+    let mut events = Vec::new();
+    for item in items {
+        let event_type = item
+            .get("event_type")
+            .and_then(|t| t.as_str())
+            .unwrap_or_default()
+            .to_string();
+        match event_type.as_str() {
+            "order" => {
+                let Ok(ev) = serde_json::from_value::<OrderEvent>(item) else {
+                    continue;
+                };
+                let status = match ev.kind.to_ascii_uppercase().as_str() {
+                    "PLACEMENT" => Some(OrderStatus::Open),
+                    "CANCELLATION" => Some(OrderStatus::Canceled),
+                    _ => None,
+                };
+                events.push(UserEvent::Order(OrderUpdate {
+                    venue_order_id: ev.id,
+                    status,
+                }));
+            }
+            "trade" => {
+                let Ok(ev) = serde_json::from_value::<TradeEvent>(item) else {
+                    continue;
+                };
+                let event = if ev.status.eq_ignore_ascii_case("FAILED") {
+                    debug!(target: "execution", trade_id = %ev.id, "trade failed");
+                    UserEvent::TradeFailed
+                } else {
+                    UserEvent::Fill
+                };
+                let ts = parse_timestamp(ev.timestamp.as_deref());
+                let fee_rate_bps = |bps: Option<&str>| bps.and_then(parse_f64).unwrap_or(0.0);
+
+                if let (Some(order_id), Some(price), Some(size)) =
+                    (ev.taker_order_id, parse_f64(&ev.price), parse_f64(&ev.size))
+                {
+                    events.push(event(TradeFill {
+                        trade_id: ev.id.clone(),
+                        venue_order_id: order_id,
+                        price,
+                        size,
+                        fee_rate_bps: fee_rate_bps(ev.fee_rate_bps.as_deref()),
+                        liquidity: Liquidity::Taker,
+                        ts,
+                    }));
+                }
+                for maker in ev.maker_orders {
+                    let (Some(price), Some(size)) =
+                        (parse_f64(&maker.price), parse_f64(&maker.matched_amount))
+                    else {
+                        continue;
+                    };
+                    events.push(event(TradeFill {
+                        trade_id: ev.id.clone(),
+                        venue_order_id: maker.order_id,
+                        price,
+                        size,
+                        fee_rate_bps: fee_rate_bps(maker.fee_rate_bps.as_deref()),
+                        liquidity: Liquidity::Maker,
+                        ts,
+                    }));
+                }
+            }
+            _ => {}
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_order_lifecycle_events() {
+        let text = r#"[
+            {"event_type": "order", "id": "0xaaa", "type": "PLACEMENT", "size_matched": "0"},
+            {"event_type": "order", "id": "0xaaa", "type": "UPDATE", "size_matched": "2"},
+            {"event_type": "order", "id": "0xaaa", "type": "CANCELLATION", "size_matched": "2"}
+        ]"#;
+        let statuses: Vec<_> = parse_user_events(text)
+            .into_iter()
+            .map(|ev| match ev {
+                UserEvent::Order(update) => update.status,
+                other => panic!("unexpected event {other:?}"),
+            })
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![Some(OrderStatus::Open), None, Some(OrderStatus::Canceled)]
+        );
+    }
+
+    #[test]
+    fn splits_trades_into_taker_and_maker_fills() {
+        let text = r#"{
+            "event_type": "trade",
+            "id": "t1",
+            "status": "MATCHED",
+            "price": "0.57",
+            "size": "10",
+            "fee_rate_bps": "100",
+            "taker_order_id": "0xtaker",
+            "maker_orders": [
+                {"order_id": "0xm1", "matched_amount": "4", "price": "0.56"},
+                {"order_id": "0xm2", "matched_amount": "6", "price": "0.57"}
+            ],
+            "timestamp": "1672290701"
+        }"#;
+        let fills: Vec<TradeFill> = parse_user_events(text)
+            .into_iter()
+            .map(|ev| match ev {
+                UserEvent::Fill(fill) => fill,
+                other => panic!("unexpected event {other:?}"),
+            })
+            .collect();
+
+        assert_eq!(fills.len(), 3);
+        assert_eq!(fills[0].venue_order_id, "0xtaker");
+        assert_eq!(fills[0].liquidity, Liquidity::Taker);
+        assert_eq!(fills[0].size, 10.0);
+        assert_eq!(fills[1].venue_order_id, "0xm1");
+        assert_eq!(fills[1].liquidity, Liquidity::Maker);
+        assert_eq!(fills[1].price, 0.56);
+        assert_eq!(fills[2].size, 6.0);
+        assert_eq!(fills[0].ts.timestamp(), 1_672_290_701);
+        assert_eq!(fills[0].fee_rate_bps, 100.0);
+        assert_eq!(fills[1].fee_rate_bps, 0.0);
+
+        let failed = text.replace("MATCHED", "FAILED");
+        let failed: Vec<UserEvent> = parse_user_events(&failed);
+        assert_eq!(failed.len(), 3);
+        assert!(matches!(&failed[1], UserEvent::TradeFailed(f) if f.venue_order_id == "0xm1"));
+    }
+}
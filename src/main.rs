@@ -12,11 +12,71 @@ fn redact_host(url: &str) -> String {
 
 use polymarket_hft_bot::{
     backtest,
+    client::{
+        auth::build_signer_from_config,
+        clob::{ApiCredentials, ClobClient},
+    },
     execution,
     monitoring,
-    types::{AppConfig, ExecutionMode},
+    types::{ApiConfig, AppConfig, ExecutionMode},
 };
 
+/// Environment variables `config/config.toml` reads the L2 credentials from.
+const CREDENTIAL_VARS: [&str; 3] = [
+    "POLYMARKET_API_KEY",
+    "POLYMARKET_API_SECRET",
+    "POLYMARKET_API_PASSPHRASE",
+];
+
+fn credential_values(creds: &ApiCredentials) -> [&str; 3] {
+    [&creds.api_key, &creds.secret, &creds.passphrase]
+}
+
+/// Set the credential variables in the dotenv file at `path`, keeping its other lines.
+fn store_credentials(path: &str, creds: &ApiCredentials) -> anyhow::Result<()> {
+    let existing = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err.into()),
+    };
+    let mut lines: Vec<String> = existing
+        .lines()
+        .filter(|line| {
+            !CREDENTIAL_VARS
+                .iter()
+                .any(|var| line.trim_start().starts_with(&format!("{var}=")))
+        })
+        .map(str::to_string)
+        .collect();
+    for (var, value) in CREDENTIAL_VARS.iter().zip(credential_values(creds)) {
+        lines.push(format!("{var}={value}"));
+    }
+    std::fs::write(path, lines.join("\n") + "\n")?;
+    Ok(())
+}
+
+/// Create or derive L2 API credentials for the configured wallet using L1 auth.
+async fn api_key(api: &ApiConfig, nonce: u64, derive: bool) -> anyhow::Result<ApiCredentials> {
+    let signer = build_signer_from_config(api, api.chain_id)?;
+    let clob = ClobClient::unauthenticated(api)?;
+    if let Err(err) = clob.sync_time().await {
+        tracing::warn!(target: "bot", error = %err, "server time sync failed; using local clock");
+    }
+    let creds = if derive {
+        clob.derive_api_key(&signer, nonce).await?
+    } else {
+        clob.create_or_derive_api_key(&signer, nonce).await?
+    };
+    tracing::info!(
+        target: "bot",
+        address = %signer.address(),
+        api_key = %creds.api_key,
+        nonce,
+        "obtained CLOB API credentials"
+    );
+    Ok(creds)
+}
+
 #[derive(Parser, Debug)]
 #[command(name = "polymarket-hft-bot")]
 #[command(about = "Polymarket 15m BTC/crypto two-leg arbitrage bot", long_about = None)]
@@ -43,6 +103,18 @@ enum Commands {
         #[arg(short, long)]
         config: Option<String>,
     },
+    /// Create (or derive the existing) CLOB API credentials for the configured wallet
+    ApiKey {
+        /// Nonce the credentials are bound to; the same nonce derives the same key
+        #[arg(long, default_value_t = 0)]
+        nonce: u64,
+        /// Only derive existing credentials, never create new ones
+        #[arg(long)]
+        derive: bool,
+        /// Write the credentials into this dotenv file instead of printing them
+        #[arg(long)]
+        env_file: Option<String>,
+    },
 }
 
 #[tokio::main]
@@ -86,6 +158,24 @@ async fn main() -> anyhow::Result<()> {
             let backtest_cfg = backtest::config::BacktestConfig::from_file(&backtest_config_path)?;
             backtest::runner::run_backtest(backtest_cfg).await?;
         }
+        Commands::ApiKey {
+            nonce,
+            derive,
+            env_file,
+        } => {
+            let creds = api_key(&settings.api, nonce, derive).await?;
+            match env_file {
+                Some(path) => {
+                    store_credentials(&path, &creds)?;
+                    tracing::info!(target: "bot", path = %path, "credentials written");
+                }
+                None => {
+                    for (var, value) in CREDENTIAL_VARS.iter().zip(credential_values(&creds)) {
+                        println!("{var}={value}");
+                    }
+                }
+            }
+        }
     }
 
     Ok(())
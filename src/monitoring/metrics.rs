@@ -21,6 +21,13 @@ struct MetricsInner {
     snapshots_recorded: AtomicU64,
     orders_submitted: AtomicU64,
     orders_failed: AtomicU64,
+    risk_rejections: AtomicU64,
+    risk_reductions: AtomicU64,
+    breakers_open: AtomicU64,
+    snapshots_coalesced: AtomicU64,
+    storage_dropped: AtomicU64,
+    requests_throttled: AtomicU64,
+    rate_limited: AtomicU64,
     last_event_ts: AtomicU64,
 }
 
@@ -78,6 +85,116 @@ impl Metrics {
         );
     }
 
+    /// An order intent refused by a pre-trade risk limit.
+    pub fn record_risk_rejected(&self, market_slug: &str, limit: &str) {
+        self.inner.risk_rejections.fetch_add(1, Ordering::Relaxed);
+
+        info!(
+            target: "metrics",
+            event = "risk_rejected",
+            market = %market_slug,
+            limit = %limit,
+            total_rejections = self.inner.risk_rejections.load(Ordering::Relaxed),
+            "order rejected by risk limit"
+        );
+    }
+
+    /// An order intent shrunk to fit a pre-trade risk limit.
+    pub fn record_risk_reduced(&self, market_slug: &str, limit: &str) {
+        self.inner.risk_reductions.fetch_add(1, Ordering::Relaxed);
+
+        info!(
+            target: "metrics",
+            event = "risk_reduced",
+            market = %market_slug,
+            limit = %limit,
+            total_reductions = self.inner.risk_reductions.load(Ordering::Relaxed),
+            "order reduced by risk limit"
+        );
+    }
+
+    /// A circuit breaker changed state; open and half-open breakers count as open.
+    pub fn record_breaker_state(&self, scope: &str, class: &str, from: &str, to: &str) {
+        match (from == "closed", to == "closed") {
+            (true, false) => {
+                self.inner.breakers_open.fetch_add(1, Ordering::Relaxed);
+            }
+            (false, true) => {
+                self.inner.breakers_open.fetch_sub(1, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+
+        info!(
+            target: "metrics",
+            event = "breaker_state",
+            scope = %scope,
+            class = %class,
+            from = %from,
+            to = %to,
+            breakers_open = self.inner.breakers_open.load(Ordering::Relaxed),
+            "circuit breaker state changed"
+        );
+    }
+
+    /// Queued market messages folded into a later snapshot instead of getting their own.
+    pub fn record_snapshots_coalesced(&self, messages: u64) {
+        let total = self
+            .inner
+            .snapshots_coalesced
+            .fetch_add(messages, Ordering::Relaxed)
+            + messages;
+
+        info!(
+            target: "metrics",
+            event = "snapshots_coalesced",
+            messages,
+            total_coalesced = total,
+            "market messages coalesced under backpressure"
+        );
+    }
+
+    /// A snapshot write dropped because the storage task fell behind.
+    pub fn record_storage_dropped(&self, market_slug: &str) {
+        self.inner.storage_dropped.fetch_add(1, Ordering::Relaxed);
+
+        info!(
+            target: "metrics",
+            event = "storage_dropped",
+            market = %market_slug,
+            total_dropped = self.inner.storage_dropped.load(Ordering::Relaxed),
+            "snapshot dropped by storage backpressure"
+        );
+    }
+
+    /// A REST request held back by the client-side rate limiter.
+    pub fn record_request_throttled(&self, endpoint: &str, wait: Duration) {
+        self.inner.requests_throttled.fetch_add(1, Ordering::Relaxed);
+
+        info!(
+            target: "metrics",
+            event = "request_throttled",
+            endpoint = %endpoint,
+            wait_ms = wait.as_millis() as u64,
+            total_throttled = self.inner.requests_throttled.load(Ordering::Relaxed),
+            "request throttled by client rate limit"
+        );
+    }
+
+    /// A 429 from the venue; `retry_after` is how long the endpoint class is paused.
+    pub fn record_rate_limited(&self, endpoint: &str, retry_after: Duration) {
+        self.inner.rate_limited.fetch_add(1, Ordering::Relaxed);
+
+        info!(
+            target: "metrics",
+            event = "rate_limited",
+            endpoint = %endpoint,
+            retry_after_ms = retry_after.as_millis() as u64,
+            total_rate_limited = self.inner.rate_limited.load(Ordering::Relaxed),
+            "request rate limited by venue"
+        );
+    }
+
     pub fn heartbeat(&self) {
         self.inner
             .last_event_ts
@@ -102,6 +219,13 @@ impl Metrics {
                 .load(Ordering::Relaxed),
             orders_submitted: self.inner.orders_submitted.load(Ordering::Relaxed),
             orders_failed: self.inner.orders_failed.load(Ordering::Relaxed),
+            risk_rejections: self.inner.risk_rejections.load(Ordering::Relaxed),
+            risk_reductions: self.inner.risk_reductions.load(Ordering::Relaxed),
+            breakers_open: self.inner.breakers_open.load(Ordering::Relaxed),
+            snapshots_coalesced: self.inner.snapshots_coalesced.load(Ordering::Relaxed),
+            storage_dropped: self.inner.storage_dropped.load(Ordering::Relaxed),
+            requests_throttled: self.inner.requests_throttled.load(Ordering::Relaxed),
+            rate_limited: self.inner.rate_limited.load(Ordering::Relaxed),
             last_event_ts: self.inner.last_event_ts.load(Ordering::Relaxed),
         }
     }
@@ -113,6 +237,13 @@ pub struct MetricsSnapshot {
     pub snapshots_recorded: u64,
     pub orders_submitted: u64,
     pub orders_failed: u64,
+    pub risk_rejections: u64,
+    pub risk_reductions: u64,
+    pub breakers_open: u64,
+    pub snapshots_coalesced: u64,
+    pub storage_dropped: u64,
+    pub requests_throttled: u64,
+    pub rate_limited: u64,
     pub last_event_ts: u64,
 }
 
@@ -123,6 +254,13 @@ pub fn log_metrics_snapshot(snapshot: &MetricsSnapshot) {
         snapshots_recorded = snapshot.snapshots_recorded,
         orders_submitted = snapshot.orders_submitted,
         orders_failed = snapshot.orders_failed,
+        risk_rejections = snapshot.risk_rejections,
+        risk_reductions = snapshot.risk_reductions,
+        breakers_open = snapshot.breakers_open,
+        snapshots_coalesced = snapshot.snapshots_coalesced,
+        storage_dropped = snapshot.storage_dropped,
+        requests_throttled = snapshot.requests_throttled,
+        rate_limited = snapshot.rate_limited,
         last_event_ts = snapshot.last_event_ts,
         "metrics snapshot"
     );
@@ -6,6 +6,7 @@ use crate::types::{PostgresConfig, RedisConfig};
 pub mod models;
 pub mod recorder;
 pub mod state;
+pub mod writer;
 
 pub type PgPool = Pool<Postgres>;
 
@@ -1,8 +1,9 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
 use sqlx::FromRow;
 
-use crate::strategy::MarketSnapshot;
+use crate::strategy::{BookDepth, MarketSnapshot};
 
 /// Row model for time-series market snapshots stored in TimescaleDB.
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -13,6 +14,11 @@ pub struct MarketSnapshotRow {
     pub up_ask: f64,
     pub down_bid: f64,
     pub down_ask: f64,
+    pub up_bid_size: Option<f64>,
+    pub up_ask_size: Option<f64>,
+    pub down_bid_size: Option<f64>,
+    pub down_ask_size: Option<f64>,
+    pub depth: Option<Json<BookDepth>>,
 }
 
 impl From<&MarketSnapshot> for MarketSnapshotRow {
@@ -24,6 +30,11 @@ impl From<&MarketSnapshot> for MarketSnapshotRow {
             up_ask: s.up_ask,
             down_bid: s.down_bid,
             down_ask: s.down_ask,
+            up_bid_size: s.up_bid_size,
+            up_ask_size: s.up_ask_size,
+            down_bid_size: s.down_bid_size,
+            down_ask_size: s.down_ask_size,
+            depth: s.depth.clone().map(Json),
         }
     }
 }
@@ -37,10 +48,23 @@ impl From<MarketSnapshotRow> for MarketSnapshot {
             up_ask: row.up_ask,
             down_bid: row.down_bid,
             down_ask: row.down_ask,
+            up_bid_size: row.up_bid_size,
+            up_ask_size: row.up_ask_size,
+            down_bid_size: row.down_bid_size,
+            down_ask_size: row.down_ask_size,
+            depth: row.depth.map(|d| d.0),
         }
     }
 }
 
+/// Recorded resolution of a market round; `winner` is "up" or "down".
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct RoundOutcomeRow {
+    pub market_slug: String,
+    pub round_start: DateTime<Utc>,
+    pub winner: String,
+}
+
 /// Simplified trade event model capturing execution outcomes.
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct TradeEventRow {
@@ -14,7 +14,12 @@ use crate::strategy::MarketSnapshot;
 ///   up_bid       DOUBLE PRECISION NOT NULL,
 ///   up_ask       DOUBLE PRECISION NOT NULL,
 ///   down_bid     DOUBLE PRECISION NOT NULL,
-///   down_ask     DOUBLE PRECISION NOT NULL
+///   down_ask     DOUBLE PRECISION NOT NULL,
+///   up_bid_size  DOUBLE PRECISION,
+///   up_ask_size  DOUBLE PRECISION,
+///   down_bid_size DOUBLE PRECISION,
+///   down_ask_size DOUBLE PRECISION,
+///   depth        JSONB
 /// );
 /// ```
 pub struct SnapshotRecorder {
@@ -34,8 +39,9 @@ impl SnapshotRecorder {
 
         query(
             "INSERT INTO market_snapshots \
-             (ts, market_slug, up_bid, up_ask, down_bid, down_ask) \
-             VALUES ($1, $2, $3, $4, $5, $6)",
+             (ts, market_slug, up_bid, up_ask, down_bid, down_ask, \
+              up_bid_size, up_ask_size, down_bid_size, down_ask_size, depth) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
         )
         .bind(row.ts)
         .bind(row.market_slug)
@@ -43,6 +49,11 @@ impl SnapshotRecorder {
         .bind(row.up_ask)
         .bind(row.down_bid)
         .bind(row.down_ask)
+        .bind(row.up_bid_size)
+        .bind(row.up_ask_size)
+        .bind(row.down_bid_size)
+        .bind(row.down_ask_size)
+        .bind(row.depth)
         .execute(&self.pool)
         .await?;
 
@@ -102,7 +113,11 @@ impl TradeRecorder {
             status: status.to_string(),
             expected_locked_profit,
         };
+        self.record_trade_row(row).await
+    }
 
+    /// Insert an already built `trade_events` row.
+    pub async fn record_trade_row(&self, row: TradeEventRow) -> anyhow::Result<()> {
         query(
             "INSERT INTO trade_events \
              (ts, market_slug, round_start, leg, client_order_id, side, price, size, status, expected_locked_profit) \
@@ -123,5 +138,30 @@ impl TradeRecorder {
 
         Ok(())
     }
+
+    /// Record an operational event, such as a kill switch transition, in `audit_events`.
+    ///
+    /// ```sql
+    /// CREATE TABLE IF NOT EXISTS audit_events (
+    ///   ts      TIMESTAMPTZ NOT NULL,
+    ///   event   TEXT        NOT NULL,
+    ///   detail  JSONB       NOT NULL
+    /// );
+    /// ```
+    pub async fn record_audit_event(
+        &self,
+        ts: DateTime<Utc>,
+        event: &str,
+        detail: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        query("INSERT INTO audit_events (ts, event, detail) VALUES ($1, $2, $3)")
+            .bind(ts)
+            .bind(event)
+            .bind(detail)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
 }
 
@@ -61,5 +61,10 @@ impl RedisStateManager {
         let _: () = self.conn.del(key).await?;
         Ok(())
     }
-}
 
+    /// Raw value of an operator-controlled flag such as the kill switch key.
+    pub async fn load_flag(&mut self, key: &str) -> anyhow::Result<Option<String>> {
+        let v: Option<String> = self.conn.get(key).await?;
+        Ok(v)
+    }
+}
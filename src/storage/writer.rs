@@ -0,0 +1,116 @@
+use chrono::{DateTime, Utc};
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+use crate::monitoring::metrics::METRICS;
+use crate::storage::models::TradeEventRow;
+use crate::storage::recorder::{SnapshotRecorder, TradeRecorder};
+use crate::storage::PgPool;
+use crate::strategy::MarketSnapshot;
+
+/// Writes waiting for the storage task before snapshots start being dropped.
+const WRITER_CAPACITY: usize = 1024;
+
+/// A write handed to the storage task.
+#[derive(Debug)]
+enum StorageEvent {
+    Snapshot(MarketSnapshot),
+    Trade(TradeEventRow),
+    Audit {
+        ts: DateTime<Utc>,
+        event: String,
+        detail: serde_json::Value,
+    },
+}
+
+/// Handle to the task that persists snapshots and trade events, so Postgres round-trips stay
+/// off the trading path.
+///
+/// Snapshots are best-effort: when the task falls behind they are dropped and counted. Trade
+/// and audit events are the record of what the bot did, so they wait for room instead.
+#[derive(Clone)]
+pub struct StorageWriter {
+    tx: mpsc::Sender<StorageEvent>,
+}
+
+impl StorageWriter {
+    /// Spawn the storage task writing to `pool`.
+    pub fn spawn(pool: PgPool) -> Self {
+        let (tx, rx) = mpsc::channel(WRITER_CAPACITY);
+        let snapshots = SnapshotRecorder::new(pool.clone());
+        let trades = TradeRecorder::new(pool);
+        tokio::spawn(run_writer(rx, snapshots, trades));
+        Self { tx }
+    }
+
+    pub fn record_snapshot(&self, snapshot: &MarketSnapshot) {
+        if self
+            .tx
+            .try_send(StorageEvent::Snapshot(snapshot.clone()))
+            .is_err()
+        {
+            METRICS.record_storage_dropped(&snapshot.market_slug);
+        }
+    }
+
+    pub async fn record_trade(&self, row: TradeEventRow) {
+        let market = row.market_slug.clone();
+        if self.tx.send(StorageEvent::Trade(row)).await.is_err() {
+            warn!(target: "storage", market = %market, "storage task stopped; trade not recorded");
+        }
+    }
+
+    pub async fn record_audit_event(
+        &self,
+        ts: DateTime<Utc>,
+        event: &str,
+        detail: serde_json::Value,
+    ) {
+        let event = StorageEvent::Audit {
+            ts,
+            event: event.to_string(),
+            detail,
+        };
+        if self.tx.send(event).await.is_err() {
+            warn!(target: "storage", "storage task stopped; audit event not recorded");
+        }
+    }
+}
+
+async fn run_writer(
+    mut rx: mpsc::Receiver<StorageEvent>,
+    snapshots: SnapshotRecorder,
+    trades: TradeRecorder,
+) {
+    while let Some(event) = rx.recv().await {
+        match event {
+            StorageEvent::Snapshot(snapshot) => {
+                if let Err(err) = snapshots.record_snapshot(&snapshot).await {
+                    warn!(
+                        target: "storage",
+                        error = %err,
+                        market = %snapshot.market_slug,
+                        "failed to record snapshot"
+                    );
+                }
+            }
+            StorageEvent::Trade(row) => {
+                let market = row.market_slug.clone();
+                if let Err(err) = trades.record_trade_row(row).await {
+                    warn!(
+                        target: "storage",
+                        error = %err,
+                        market = %market,
+                        "failed to record trade"
+                    );
+                }
+            }
+            StorageEvent::Audit { ts, event, detail } => {
+                if let Err(err) = trades.record_audit_event(ts, &event, detail).await {
+                    warn!(target: "storage", error = %err, "failed to record audit event");
+                }
+            }
+        }
+    }
+    debug!(target: "storage", "storage writer stopped");
+}
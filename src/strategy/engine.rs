@@ -0,0 +1,159 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::strategy::{LegSide, MarketSnapshot};
+
+/// Direction of an order intent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TradeAction {
+    Buy,
+    Sell,
+}
+
+/// Strategy-agnostic order intent consumed by the execution layer and the backtester.
+#[derive(Clone, Debug)]
+pub struct OrderIntent {
+    /// Name of the strategy instance that produced the intent; fills are routed back by it.
+    pub strategy: String,
+    pub market_slug: String,
+    pub round_start: DateTime<Utc>,
+    /// Token of the binary market to trade.
+    pub side: LegSide,
+    pub action: TradeAction,
+    pub shares: f64,
+    pub limit_price: f64,
+    /// Short label recorded with the trade (e.g. "leg1", "leg2").
+    pub label: String,
+    /// Profit the intent is expected to lock in, if it completes a hedged position.
+    pub expected_locked_profit: Option<f64>,
+    /// Sequence number of the order within its leg, so reports for a requoted leg can be told
+    /// apart from those of the order it replaced.
+    pub attempt: u32,
+    /// Labels of the strategy's working orders in the same market and round to cancel before
+    /// placing this one (cancel/replace).
+    pub replaces: Vec<String>,
+    /// Take what is available at the limit right away and cancel the rest instead of resting.
+    pub immediate_or_cancel: bool,
+    /// Hedges or unwinds a position already held; queued ahead of intents that open new ones.
+    pub reduces_risk: bool,
+}
+
+/// Execution report handed back to the strategy that emitted the intent.
+#[derive(Clone, Debug)]
+pub struct StrategyFill {
+    pub strategy: String,
+    pub market_slug: String,
+    pub round_start: DateTime<Utc>,
+    pub side: LegSide,
+    pub action: TradeAction,
+    pub label: String,
+    pub attempt: u32,
+    pub price: f64,
+    pub shares: f64,
+    pub ts: DateTime<Utc>,
+}
+
+impl StrategyFill {
+    /// Fill of `shares` at `price` against an order placed for `intent`.
+    pub fn from_intent(intent: &OrderIntent, price: f64, shares: f64, ts: DateTime<Utc>) -> Self {
+        Self {
+            strategy: intent.strategy.clone(),
+            market_slug: intent.market_slug.clone(),
+            round_start: intent.round_start,
+            side: intent.side,
+            action: intent.action,
+            label: intent.label.clone(),
+            attempt: intent.attempt,
+            price,
+            shares,
+            ts,
+        }
+    }
+}
+
+/// How an order emitted for an intent ended up, as far as the venue is concerned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderOutcome {
+    /// The order was accepted and may rest; fills follow through [`Strategy::on_fill`].
+    Accepted,
+    /// The order was refused at submission.
+    Rejected,
+    /// The order was held back before reaching the venue by the kill switch, a risk limit, an
+    /// open circuit breaker or a full submission queue. Nothing was placed, so it is not a
+    /// failed attempt.
+    Blocked,
+    /// The order left the book before filling completely (canceled, killed or expired).
+    Canceled,
+    /// The order filled completely.
+    Filled,
+}
+
+impl OrderOutcome {
+    /// Whether the order can no longer fill.
+    pub fn is_final(self) -> bool {
+        !matches!(self, OrderOutcome::Accepted)
+    }
+}
+
+/// Lifecycle notification for an order emitted by a strategy.
+#[derive(Clone, Debug)]
+pub struct OrderUpdate {
+    pub strategy: String,
+    pub market_slug: String,
+    pub round_start: DateTime<Utc>,
+    pub side: LegSide,
+    pub action: TradeAction,
+    pub label: String,
+    pub attempt: u32,
+    pub outcome: OrderOutcome,
+    /// Shares filled over the order's lifetime at the time of the update.
+    pub filled_shares: f64,
+}
+
+impl OrderUpdate {
+    pub fn from_intent(intent: &OrderIntent, outcome: OrderOutcome, filled_shares: f64) -> Self {
+        Self {
+            strategy: intent.strategy.clone(),
+            market_slug: intent.market_slug.clone(),
+            round_start: intent.round_start,
+            side: intent.side,
+            action: intent.action,
+            label: intent.label.clone(),
+            attempt: intent.attempt,
+            outcome,
+            filled_shares,
+        }
+    }
+}
+
+/// A trading strategy driven by market snapshots.
+///
+/// Implementations keep their own per-market state; the execution loop and the backtester only
+/// see [`OrderIntent`]s going out and [`StrategyFill`]s coming back.
+pub trait Strategy: Send {
+    /// Unique instance name, used to tag intents and route fills.
+    fn name(&self) -> &str;
+
+    /// Process a snapshot and return any order intents.
+    ///
+    /// `available_capital` is the capital allocated to this instance, not the whole account.
+    fn on_snapshot(
+        &mut self,
+        snapshot: &MarketSnapshot,
+        available_capital: f64,
+    ) -> Vec<OrderIntent>;
+
+    /// Notification that an order emitted by this strategy (partially) filled.
+    fn on_fill(&mut self, fill: &StrategyFill);
+
+    /// Notification that an order emitted by this strategy was accepted, or will not fill
+    /// (any further). Fills themselves arrive through [`Strategy::on_fill`], before the final
+    /// update of the same order.
+    fn on_order_update(&mut self, _update: &OrderUpdate) {}
+
+    /// Notification that a market's round has ended; per-round state may be released.
+    fn on_round_end(&mut self, market_slug: &str, round_start: DateTime<Utc>);
+
+    /// Serializable view of internal state for monitoring and persistence.
+    fn export_state(&self) -> serde_json::Value;
+}
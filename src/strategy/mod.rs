@@ -1,10 +1,31 @@
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
+pub mod engine;
 pub mod params;
+pub mod registry;
 pub mod two_leg;
 
+pub use engine::{OrderIntent, OrderOutcome, OrderUpdate, Strategy, StrategyFill, TradeAction};
 pub use params::TwoLegParams;
-pub use two_leg::{LegSide, TwoLegDecision, TwoLegEngine, TwoLegState};
+pub use registry::StrategySet;
+pub use two_leg::{ExitReason, LegSide, LegStatus, TwoLegDecision, TwoLegEngine, TwoLegState};
+
+/// Aggregate resting size at a single price.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PriceLevel {
+    pub price: f64,
+    pub size: f64,
+}
+
+/// Top-N levels per token and side, best first.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BookDepth {
+    pub up_bids: Vec<PriceLevel>,
+    pub up_asks: Vec<PriceLevel>,
+    pub down_bids: Vec<PriceLevel>,
+    pub down_asks: Vec<PriceLevel>,
+}
 
 /// Normalized snapshot of a Polymarket 15-minute UP/DOWN market.
 #[derive(Clone, Debug)]
@@ -17,6 +38,13 @@ pub struct MarketSnapshot {
     /// Best bid/ask for the DOWN token (0-1 price).
     pub down_bid: f64,
     pub down_ask: f64,
+    /// Displayed size (shares) at the touch; `None` when unknown (e.g. legacy recordings).
+    pub up_bid_size: Option<f64>,
+    pub up_ask_size: Option<f64>,
+    pub down_bid_size: Option<f64>,
+    pub down_ask_size: Option<f64>,
+    /// Deeper book levels, if captured.
+    pub depth: Option<BookDepth>,
 }
 
 impl MarketSnapshot {
@@ -29,5 +57,45 @@ impl MarketSnapshot {
     pub fn mid_down(&self) -> f64 {
         0.5 * (self.down_bid + self.down_ask)
     }
+
+    /// Mid price for a leg side.
+    pub fn mid(&self, side: LegSide) -> f64 {
+        match side {
+            LegSide::Up => self.mid_up(),
+            LegSide::Down => self.mid_down(),
+        }
+    }
+
+    /// Best bid for a leg side.
+    pub fn bid(&self, side: LegSide) -> f64 {
+        match side {
+            LegSide::Up => self.up_bid,
+            LegSide::Down => self.down_bid,
+        }
+    }
+
+    /// Best ask for a leg side.
+    pub fn ask(&self, side: LegSide) -> f64 {
+        match side {
+            LegSide::Up => self.up_ask,
+            LegSide::Down => self.down_ask,
+        }
+    }
+
+    /// Displayed size at the best ask for a leg side, if known.
+    pub fn ask_size(&self, side: LegSide) -> Option<f64> {
+        match side {
+            LegSide::Up => self.up_ask_size,
+            LegSide::Down => self.down_ask_size,
+        }
+    }
+
+    /// Displayed size at the best bid for a leg side, if known.
+    pub fn bid_size(&self, side: LegSide) -> Option<f64> {
+        match side {
+            LegSide::Up => self.up_bid_size,
+            LegSide::Down => self.down_bid_size,
+        }
+    }
 }
 
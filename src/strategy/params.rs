@@ -19,6 +19,26 @@ pub struct TwoLegParams {
     pub fee_rate: f64,
     /// Minimum locked-in profit (USD) required before opening the hedge leg.
     pub min_profit_usd: f64,
+    /// Whether a crash in the UP token may open Leg 1.
+    pub leg1_up_enabled: bool,
+    /// Whether a crash in the DOWN token may open Leg 1.
+    pub leg1_down_enabled: bool,
+    /// Failed orders allowed per leg and round before the leg is abandoned.
+    pub max_order_retries: u32,
+    /// Move in the desired hedge price that triggers a cancel/replace of the working hedge.
+    pub hedge_requote_threshold: f64,
+    /// Seconds before round end at which hedge escalation starts.
+    pub hedge_escalation_secs: i64,
+    /// Seconds before round end at which hedge escalation is complete.
+    pub hedge_taker_secs: i64,
+    /// Maximum combined price of both legs accepted at full escalation.
+    pub hedge_max_sum: f64,
+    /// Adverse move of Leg 1's bid from its entry, as a fraction, that triggers an exit.
+    pub stop_loss_pct: Option<f64>,
+    /// Seconds before round end at which unhedged Leg 1 shares are exited.
+    pub exit_before_end_secs: Option<i64>,
+    /// Largest locked-in loss (USD) accepted to force the hedge instead of selling Leg 1.
+    pub emergency_hedge_max_loss_usd: Option<f64>,
 }
 
 impl From<&BotConfig> for TwoLegParams {
@@ -32,6 +52,16 @@ impl From<&BotConfig> for TwoLegParams {
             risk_per_trade_pct: cfg.risk_per_trade_pct,
             fee_rate: cfg.fee_rate,
             min_profit_usd: cfg.min_profit_usd,
+            leg1_up_enabled: cfg.leg1_up_enabled,
+            leg1_down_enabled: cfg.leg1_down_enabled,
+            max_order_retries: cfg.max_order_retries,
+            hedge_requote_threshold: cfg.hedge_requote_threshold,
+            hedge_escalation_secs: cfg.hedge_escalation_secs,
+            hedge_taker_secs: cfg.hedge_taker_secs,
+            hedge_max_sum: cfg.hedge_max_sum,
+            stop_loss_pct: cfg.stop_loss_pct,
+            exit_before_end_secs: cfg.exit_before_end_secs,
+            emergency_hedge_max_loss_usd: cfg.emergency_hedge_max_loss_usd,
         }
     }
 }
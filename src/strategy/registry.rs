@@ -0,0 +1,310 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    strategy::{
+        engine::{OrderIntent, OrderUpdate, Strategy, StrategyFill},
+        MarketSnapshot, TwoLegEngine, TwoLegParams,
+    },
+    types::{BotConfig, StrategyConfig, StrategyKind},
+};
+
+/// Instance name used when no `[[strategies]]` are configured.
+pub const DEFAULT_STRATEGY_NAME: &str = "two_leg";
+
+/// Instantiate a strategy of the given kind.
+pub fn build_strategy(kind: StrategyKind, name: &str, bot: &BotConfig) -> Box<dyn Strategy> {
+    match kind {
+        StrategyKind::TwoLeg => {
+            Box::new(TwoLegEngine::new(TwoLegParams::from(bot)).with_name(name))
+        }
+    }
+}
+
+struct Slot {
+    strategy: Box<dyn Strategy>,
+    capital_allocation: f64,
+}
+
+/// Strategies running side by side on the same feed, each sized against its own share of capital.
+pub struct StrategySet {
+    slots: Vec<Slot>,
+}
+
+impl StrategySet {
+    /// Build the configured strategies.
+    ///
+    /// With no `[[strategies]]` entries, a single two-leg engine on `[bot]` receives all capital.
+    pub fn from_config(bot: &BotConfig, strategies: &[StrategyConfig]) -> anyhow::Result<Self> {
+        if strategies.is_empty() {
+            return Ok(Self::single(build_strategy(
+                StrategyKind::TwoLeg,
+                DEFAULT_STRATEGY_NAME,
+                bot,
+            )));
+        }
+
+        let mut names = HashSet::new();
+        let mut total_allocation = 0.0;
+        let mut set = Self { slots: Vec::new() };
+        for s in strategies {
+            if s.name.is_empty() {
+                anyhow::bail!("strategy name must not be empty");
+            }
+            if !names.insert(s.name.as_str()) {
+                anyhow::bail!("duplicate strategy name: {}", s.name);
+            }
+            if !(s.capital_allocation > 0.0 && s.capital_allocation <= 1.0) {
+                anyhow::bail!(
+                    "strategy {}: capital_allocation must be in (0, 1], got {}",
+                    s.name,
+                    s.capital_allocation
+                );
+            }
+            total_allocation += s.capital_allocation;
+
+            let params = s.bot.as_ref().unwrap_or(bot);
+            set.push(build_strategy(s.kind, &s.name, params), s.capital_allocation);
+        }
+
+        if total_allocation > 1.0 + 1e-9 {
+            anyhow::bail!("strategy capital allocations sum to {total_allocation}, must not exceed 1");
+        }
+
+        Ok(set)
+    }
+
+    /// A set holding one strategy with the full capital allocation.
+    pub fn single(strategy: Box<dyn Strategy>) -> Self {
+        let mut set = Self { slots: Vec::new() };
+        set.push(strategy, 1.0);
+        set
+    }
+
+    pub fn push(&mut self, strategy: Box<dyn Strategy>, capital_allocation: f64) {
+        self.slots.push(Slot {
+            strategy,
+            capital_allocation,
+        });
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.slots.iter().map(|s| s.strategy.name())
+    }
+
+    /// Instance name and capital allocation of every strategy.
+    pub fn allocations(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.slots
+            .iter()
+            .map(|s| (s.strategy.name(), s.capital_allocation))
+    }
+
+    /// Feed a snapshot to every strategy and collect their intents in registration order.
+    ///
+    /// `capital` gives the capital a strategy may size against from its name and allocation,
+    /// e.g. its share of the account less what it already has committed.
+    pub fn on_snapshot(
+        &mut self,
+        snapshot: &MarketSnapshot,
+        capital: impl Fn(&str, f64) -> f64,
+    ) -> Vec<OrderIntent> {
+        self.slots
+            .iter_mut()
+            .flat_map(|slot| {
+                let capital = capital(slot.strategy.name(), slot.capital_allocation);
+                slot.strategy.on_snapshot(snapshot, capital)
+            })
+            .collect()
+    }
+
+    /// Route a fill to the strategy that emitted the order.
+    pub fn on_fill(&mut self, fill: &StrategyFill) {
+        if let Some(slot) = self
+            .slots
+            .iter_mut()
+            .find(|s| s.strategy.name() == fill.strategy)
+        {
+            slot.strategy.on_fill(fill);
+        }
+    }
+
+    /// Route an order lifecycle update to the strategy that emitted the order.
+    pub fn on_order_update(&mut self, update: &OrderUpdate) {
+        if let Some(slot) = self
+            .slots
+            .iter_mut()
+            .find(|s| s.strategy.name() == update.strategy)
+        {
+            slot.strategy.on_order_update(update);
+        }
+    }
+
+    pub fn on_round_end(&mut self, market_slug: &str, round_start: DateTime<Utc>) {
+        for slot in &mut self.slots {
+            slot.strategy.on_round_end(market_slug, round_start);
+        }
+    }
+
+    /// State of every strategy, keyed by instance name.
+    pub fn export_state(&self) -> serde_json::Value {
+        let states = self
+            .slots
+            .iter()
+            .map(|s| (s.strategy.name().to_string(), s.strategy.export_state()))
+            .collect::<serde_json::Map<_, _>>();
+        serde_json::Value::Object(states)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::{LegSide, TradeAction};
+
+    fn bot_cfg() -> BotConfig {
+        BotConfig {
+            shares: 10.0,
+            sum_target: 0.95,
+            move_pct: 0.1,
+            window_min: 3,
+            max_concurrent_trades: 1,
+            risk_per_trade_pct: 2.0,
+            fee_rate: 0.02,
+            min_profit_usd: 0.0,
+            leg1_up_enabled: true,
+            leg1_down_enabled: true,
+            max_order_retries: 2,
+            hedge_requote_threshold: 0.01,
+            hedge_escalation_secs: 300,
+            hedge_taker_secs: 60,
+            hedge_max_sum: 1.0,
+            stop_loss_pct: None,
+            exit_before_end_secs: None,
+            emergency_hedge_max_loss_usd: None,
+        }
+    }
+
+    fn strategy_cfg(name: &str, capital_allocation: f64) -> StrategyConfig {
+        StrategyConfig {
+            name: name.to_string(),
+            kind: StrategyKind::TwoLeg,
+            capital_allocation,
+            bot: None,
+        }
+    }
+
+    /// Records the capital it was offered and every fill routed to it.
+    struct Probe {
+        name: String,
+        capital_seen: Vec<f64>,
+        fills: usize,
+    }
+
+    impl Strategy for Probe {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn on_snapshot(&mut self, snapshot: &MarketSnapshot, available_capital: f64) -> Vec<OrderIntent> {
+            self.capital_seen.push(available_capital);
+            vec![OrderIntent {
+                strategy: self.name.clone(),
+                market_slug: snapshot.market_slug.clone(),
+                round_start: snapshot.ts,
+                side: LegSide::Up,
+                action: TradeAction::Buy,
+                shares: 1.0,
+                limit_price: snapshot.up_ask,
+                label: "probe".to_string(),
+                expected_locked_profit: None,
+                attempt: 0,
+                replaces: Vec::new(),
+                immediate_or_cancel: false,
+                reduces_risk: false,
+            }]
+        }
+
+        fn on_fill(&mut self, _fill: &StrategyFill) {
+            self.fills += 1;
+        }
+
+        fn on_round_end(&mut self, _market_slug: &str, _round_start: DateTime<Utc>) {}
+
+        fn export_state(&self) -> serde_json::Value {
+            serde_json::json!({ "capital_seen": self.capital_seen, "fills": self.fills })
+        }
+    }
+
+    fn probe(name: &str) -> Box<dyn Strategy> {
+        Box::new(Probe {
+            name: name.to_string(),
+            capital_seen: Vec::new(),
+            fills: 0,
+        })
+    }
+
+    fn snapshot() -> MarketSnapshot {
+        MarketSnapshot {
+            ts: Utc::now(),
+            market_slug: "BTC_15m".to_string(),
+            up_bid: 0.49,
+            up_ask: 0.51,
+            down_bid: 0.49,
+            down_ask: 0.51,
+            up_bid_size: None,
+            up_ask_size: None,
+            down_bid_size: None,
+            down_ask_size: None,
+            depth: None,
+        }
+    }
+
+    #[test]
+    fn empty_config_runs_default_two_leg() {
+        let set = StrategySet::from_config(&bot_cfg(), &[]).unwrap();
+        assert_eq!(set.names().collect::<Vec<_>>(), vec![DEFAULT_STRATEGY_NAME]);
+    }
+
+    #[test]
+    fn rejects_invalid_allocations() {
+        let cfg = bot_cfg();
+        assert!(StrategySet::from_config(&cfg, &[strategy_cfg("a", 0.6), strategy_cfg("b", 0.6)]).is_err());
+        assert!(StrategySet::from_config(&cfg, &[strategy_cfg("a", 0.5), strategy_cfg("a", 0.5)]).is_err());
+        assert!(StrategySet::from_config(&cfg, &[strategy_cfg("a", 0.0)]).is_err());
+        assert!(StrategySet::from_config(&cfg, &[strategy_cfg("a", 0.7), strategy_cfg("b", 0.3)]).is_ok());
+    }
+
+    #[test]
+    fn splits_capital_and_routes_fills_by_name() {
+        let mut set = StrategySet::single(probe("a"));
+        set.push(probe("b"), 0.25);
+        // `single` gives full allocation; rebalance for the test.
+        set.slots[0].capital_allocation = 0.75;
+
+        let intents = set.on_snapshot(&snapshot(), |_, allocation| 1_000.0 * allocation);
+        assert_eq!(intents.len(), 2);
+        assert_eq!(intents[0].strategy, "a");
+        assert_eq!(intents[1].strategy, "b");
+
+        let fill = StrategyFill {
+            strategy: "b".to_string(),
+            market_slug: intents[1].market_slug.clone(),
+            round_start: intents[1].round_start,
+            side: intents[1].side,
+            action: intents[1].action,
+            label: intents[1].label.clone(),
+            attempt: intents[1].attempt,
+            price: intents[1].limit_price,
+            shares: intents[1].shares,
+            ts: Utc::now(),
+        };
+        set.on_fill(&fill);
+
+        let state = set.export_state();
+        assert_eq!(state["a"]["capital_seen"][0], 750.0);
+        assert_eq!(state["b"]["capital_seen"][0], 250.0);
+        assert_eq!(state["a"]["fills"], 0);
+        assert_eq!(state["b"]["fills"], 1);
+    }
+}
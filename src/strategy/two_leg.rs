@@ -2,11 +2,17 @@ use std::collections::HashMap;
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use tracing::warn;
 
 use crate::{
-    strategy::{params::TwoLegParams, MarketSnapshot},
+    strategy::{
+        engine::{OrderIntent, OrderOutcome, OrderUpdate, Strategy, StrategyFill, TradeAction},
+        params::TwoLegParams,
+        registry::DEFAULT_STRATEGY_NAME,
+        MarketSnapshot,
+    },
     utils::{
-        math::{locked_profit, position_size_kelly},
+        math::{locked_profit, max_pair_cost, position_size_kelly},
         time::{round_end, round_start, seconds_remaining, within_leg1_window},
     },
 };
@@ -25,10 +31,41 @@ pub struct LegPosition {
     pub shares: f64,
 }
 
+/// Execution state of the order working a leg.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LegStatus {
+    /// Emitted, not yet acknowledged by the venue.
+    Pending,
+    /// Accepted and working; may be partially filled.
+    Open,
+    /// Done; the filled shares are the leg's position.
+    Filled,
+    /// Ended without any fill.
+    Failed,
+}
+
+/// Why an unhedged Leg 1 is being unwound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExitReason {
+    /// Leg 1's bid fell `stop_loss_pct` below its entry price.
+    StopLoss,
+    /// The round is about to end with Leg 1 still unhedged.
+    RoundEnd,
+}
+
 /// Public summary of per-round state for monitoring/backtesting.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum TwoLegState {
     Idle,
+    /// Leg 1 order working with nothing filled yet.
+    Leg1Pending {
+        round_start: DateTime<Utc>,
+        side: LegSide,
+        limit_price: f64,
+        shares: f64,
+        status: LegStatus,
+    },
+    /// Leg 1 (partially) filled and not yet fully hedged.
     Leg1Open {
         round_start: DateTime<Utc>,
         leg1: LegPosition,
@@ -40,6 +77,18 @@ pub enum TwoLegState {
         leg2_price: f64,
         locked_profit: f64,
     },
+    /// An exit rule fired; `open_shares` of Leg 1 are still neither sold nor hedged.
+    Exited {
+        round_start: DateTime<Utc>,
+        leg1: LegPosition,
+        reason: ExitReason,
+        open_shares: f64,
+    },
+    /// A leg ran out of retries; any Leg 1 position is left to settle at round end.
+    Abandoned {
+        round_start: DateTime<Utc>,
+        leg1: Option<LegPosition>,
+    },
 }
 
 /// Decision emitted by the strategy engine to be consumed by the execution layer.
@@ -52,8 +101,10 @@ pub enum TwoLegDecision {
         side: LegSide,
         shares: f64,
         limit_price: f64,
+        attempt: u32,
     },
-    /// Open the hedge leg that locks in profit for the round.
+    /// Open the hedge leg that locks in profit for the round, or requote the working hedge
+    /// when `replace` is set.
     OpenLeg2 {
         market_slug: String,
         round_start: DateTime<Utc>,
@@ -61,7 +112,112 @@ pub enum TwoLegDecision {
         shares: f64,
         limit_price: f64,
         expected_locked_profit: f64,
+        attempt: u32,
+        replace: bool,
     },
+    /// Unwind an unhedged Leg 1 after an exit rule fired: sell it back at `side`'s bid, or with
+    /// `force_hedge` buy the opposite `side` at its ask whatever the profit. Exits cancel the
+    /// round's working orders first and never rest on the book.
+    ExitLeg1 {
+        market_slug: String,
+        round_start: DateTime<Utc>,
+        side: LegSide,
+        shares: f64,
+        limit_price: f64,
+        force_hedge: bool,
+        reason: ExitReason,
+        attempt: u32,
+    },
+}
+
+impl TwoLegDecision {
+    /// Generic intent for the execution layer, tagged with the emitting strategy instance.
+    pub fn into_intent(self, strategy: &str) -> OrderIntent {
+        match self {
+            TwoLegDecision::OpenLeg1 {
+                market_slug,
+                round_start,
+                side,
+                shares,
+                limit_price,
+                attempt,
+            } => OrderIntent {
+                strategy: strategy.to_string(),
+                market_slug,
+                round_start,
+                side,
+                action: TradeAction::Buy,
+                shares,
+                limit_price,
+                label: "leg1".to_string(),
+                expected_locked_profit: None,
+                attempt,
+                replaces: Vec::new(),
+                immediate_or_cancel: false,
+                reduces_risk: false,
+            },
+            TwoLegDecision::OpenLeg2 {
+                market_slug,
+                round_start,
+                side,
+                shares,
+                limit_price,
+                expected_locked_profit,
+                attempt,
+                replace,
+            } => OrderIntent {
+                strategy: strategy.to_string(),
+                market_slug,
+                round_start,
+                side,
+                action: TradeAction::Buy,
+                shares,
+                limit_price,
+                label: "leg2".to_string(),
+                expected_locked_profit: Some(expected_locked_profit),
+                attempt,
+                replaces: if replace {
+                    vec!["leg2".to_string()]
+                } else {
+                    Vec::new()
+                },
+                immediate_or_cancel: false,
+                reduces_risk: true,
+            },
+            TwoLegDecision::ExitLeg1 {
+                market_slug,
+                round_start,
+                side,
+                shares,
+                limit_price,
+                force_hedge,
+                reason: _,
+                attempt,
+            } => {
+                // Forced hedges go through the hedge leg so their fills cover Leg 1.
+                let (action, label) = if force_hedge {
+                    (TradeAction::Buy, "leg2")
+                } else {
+                    (TradeAction::Sell, "exit")
+                };
+                OrderIntent {
+                    strategy: strategy.to_string(),
+                    market_slug,
+                    round_start,
+                    side,
+                    action,
+                    shares,
+                    limit_price,
+                    label: label.to_string(),
+                    expected_locked_profit: None,
+                    attempt,
+                    replaces: ["leg1", "leg2", "exit"].map(String::from).to_vec(),
+                    immediate_or_cancel: true,
+                    reduces_risk: true,
+                }
+            }
+        }
+    }
 }
 
 #[derive(Hash, Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
@@ -70,21 +226,174 @@ struct RoundKey {
     round_start: DateTime<Utc>,
 }
 
+/// Share quantities below this are treated as zero.
+const SHARE_EPSILON: f64 = 1e-9;
+
+/// Coarsest price increment of the markets traded. Resting hedge quotes are floored to it, so
+/// the venue's rounding of the limit price can never push the pair over its budget.
+const PRICE_TICK: f64 = 0.01;
+
+/// Largest multiple of [`PRICE_TICK`] not above `price`.
+fn floor_to_tick(price: f64) -> f64 {
+    let ticks = (price / PRICE_TICK + SHARE_EPSILON).floor();
+    ticks / PRICE_TICK.recip()
+}
+
+/// Order working a leg, tracked from emission until it stops filling.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct LegOrder {
+    attempt: u32,
+    side: LegSide,
+    limit_price: f64,
+    requested_shares: f64,
+    filled_shares: f64,
+    avg_price: f64,
+    status: LegStatus,
+}
+
+impl LegOrder {
+    fn new(side: LegSide, limit_price: f64, shares: f64, attempt: u32) -> Self {
+        Self {
+            attempt,
+            side,
+            limit_price,
+            requested_shares: shares,
+            filled_shares: 0.0,
+            avg_price: 0.0,
+            status: LegStatus::Pending,
+        }
+    }
+
+    fn apply_fill(&mut self, price: f64, shares: f64) {
+        let filled = self.filled_shares + shares;
+        if filled > 0.0 {
+            self.avg_price = (self.avg_price * self.filled_shares + price * shares) / filled;
+        }
+        self.filled_shares = filled;
+        self.status = if self.requested_shares - filled <= SHARE_EPSILON {
+            LegStatus::Filled
+        } else {
+            LegStatus::Open
+        };
+    }
+
+    /// The order will not fill any further.
+    fn finish(&mut self) {
+        self.status = if self.filled_shares > SHARE_EPSILON {
+            LegStatus::Filled
+        } else {
+            LegStatus::Failed
+        };
+    }
+
+    fn position(&self) -> LegPosition {
+        LegPosition {
+            side: self.side,
+            entry_price: self.avg_price,
+            shares: self.filled_shares,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct RoundInternal {
     round_start: DateTime<Utc>,
-    baseline_mid: f64,
-    leg1: Option<LegPosition>,
-    hedged: bool,
+    /// Mids at the start of the round, used as the crash reference for each side.
+    baseline_up_mid: f64,
+    baseline_down_mid: f64,
+    /// Leg 1 order; cleared after a failure that may still be retried.
+    leg1: Option<LegOrder>,
+    /// Leg 1 orders emitted so far; numbers each one so client order IDs never repeat.
+    leg1_attempts: u32,
+    leg1_failures: u32,
+    /// Latest hedge order. Hedges may be split by displayed liquidity or requoted, so several
+    /// can run one after another.
+    hedge: Option<LegOrder>,
+    hedge_attempts: u32,
+    hedge_failures: u32,
+    /// Leg 1 shares covered by filled hedge orders, and their average price.
+    hedged_shares: f64,
+    hedge_avg_price: f64,
+    /// Order selling Leg 1 back after an exit rule fired, and the shares sold so far.
+    exit: Option<LegOrder>,
+    exit_attempts: u32,
+    exited_shares: f64,
+    /// Set once an exit rule fires; from then on the round only unwinds.
+    exit_reason: Option<ExitReason>,
+    /// A leg ran out of retries; the round takes no further action.
+    abandoned: bool,
 }
 
 impl RoundInternal {
-    fn new(round_start: DateTime<Utc>, baseline_mid: f64) -> Self {
+    fn new(round_start: DateTime<Utc>, baseline_up_mid: f64, baseline_down_mid: f64) -> Self {
         Self {
             round_start,
-            baseline_mid,
+            baseline_up_mid,
+            baseline_down_mid,
             leg1: None,
-            hedged: false,
+            leg1_attempts: 0,
+            leg1_failures: 0,
+            hedge: None,
+            hedge_attempts: 0,
+            hedge_failures: 0,
+            hedged_shares: 0.0,
+            hedge_avg_price: 0.0,
+            exit: None,
+            exit_attempts: 0,
+            exited_shares: 0.0,
+            exit_reason: None,
+            abandoned: false,
+        }
+    }
+
+    fn leg1_filled_shares(&self) -> f64 {
+        self.leg1.as_ref().map_or(0.0, |l| l.filled_shares)
+    }
+
+    /// Leg 1 fully filled and every filled share covered by a filled hedge.
+    fn is_hedged(&self) -> bool {
+        self.leg1
+            .as_ref()
+            .is_some_and(|l| l.status == LegStatus::Filled)
+            && self.leg1_filled_shares() - self.hedged_shares <= SHARE_EPSILON
+    }
+
+    /// Filled Leg 1 shares neither hedged nor sold back.
+    fn open_exposure(&self) -> f64 {
+        self.leg1_filled_shares() - self.hedged_shares - self.exited_shares
+    }
+
+    /// Leg 1 is working, or held without being completely hedged or sold back.
+    fn is_unhedged(&self) -> bool {
+        match &self.leg1 {
+            Some(leg1) if leg1.status == LegStatus::Filled => {
+                self.open_exposure() > SHARE_EPSILON
+            }
+            Some(leg1) => leg1.status != LegStatus::Failed,
+            None => false,
+        }
+    }
+
+    fn record_hedge_fill(&mut self, price: f64, shares: f64) {
+        let covered = self.hedged_shares + shares;
+        if covered > 0.0 {
+            self.hedge_avg_price =
+                (self.hedge_avg_price * self.hedged_shares + price * shares) / covered;
+        }
+        self.hedged_shares = covered;
+    }
+
+    fn baseline(&self, side: LegSide) -> f64 {
+        match side {
+            LegSide::Up => self.baseline_up_mid,
+            LegSide::Down => self.baseline_down_mid,
+        }
+    }
+
+    fn set_baseline(&mut self, side: LegSide, mid: f64) {
+        match side {
+            LegSide::Up => self.baseline_up_mid = mid,
+            LegSide::Down => self.baseline_down_mid = mid,
         }
     }
 }
@@ -92,6 +401,7 @@ impl RoundInternal {
 /// Two-leg crash+hedge strategy engine maintaining per-market, per-round state.
 #[derive(Debug)]
 pub struct TwoLegEngine {
+    name: String,
     params: TwoLegParams,
     rounds: HashMap<RoundKey, RoundInternal>,
 }
@@ -99,11 +409,18 @@ pub struct TwoLegEngine {
 impl TwoLegEngine {
     pub fn new(params: TwoLegParams) -> Self {
         Self {
+            name: DEFAULT_STRATEGY_NAME.to_string(),
             params,
             rounds: HashMap::new(),
         }
     }
 
+    /// Set the instance name used to tag intents when several engines run side by side.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
     /// Process a new market snapshot and return any trading decisions.
     ///
     /// `available_capital` is the current account capital in quote currency and is
@@ -126,16 +443,25 @@ impl TwoLegEngine {
 
         let active_unhedged = self.active_unhedged_trades();
 
-        let baseline_mid = snapshot.mid_up();
+        let (baseline_up, baseline_down) = (snapshot.mid_up(), snapshot.mid_down());
         let round = self
             .rounds
             .entry(key.clone())
-            .or_insert_with(|| RoundInternal::new(current_round_start, baseline_mid));
+            .or_insert_with(|| RoundInternal::new(current_round_start, baseline_up, baseline_down));
 
         let mut decisions = Vec::new();
+        // An abandoned round opens nothing new, but shares it still holds keep being hedged or
+        // exited.
+        let holds_shares = round
+            .leg1
+            .as_ref()
+            .is_some_and(|leg1| leg1.position().shares > SHARE_EPSILON);
+        if round.abandoned && !holds_shares {
+            return decisions;
+        }
 
-        match (&round.leg1, round.hedged) {
-            (None, false) => {
+        match &round.leg1 {
+            None => {
                 // Potential Leg 1 entry.
                 if let Some(decision) =
                     maybe_open_leg1(&self.params, active_unhedged, round, &snapshot, available_capital)
@@ -143,21 +469,119 @@ impl TwoLegEngine {
                     decisions.push(decision);
                 }
             }
-            (Some(leg1), false) => {
-                // Leg 1 is open, consider hedge.
-                if let Some(decision) = maybe_open_leg2(&self.params, leg1, round, &snapshot) {
-                    round.hedged = true;
-                    decisions.push(decision);
+            Some(leg1) => {
+                // Hedge what Leg 1 has filled so far, one hedge order at a time, unless an exit
+                // rule says to unwind it instead.
+                let position = leg1.position();
+                if position.shares > SHARE_EPSILON {
+                    let decision = match exit_reason(&self.params, &position, round, &snapshot) {
+                        Some(reason) => {
+                            manage_exit(&self.params, &position, round, &snapshot, reason)
+                        }
+                        None => manage_hedge(&self.params, &position, round, &snapshot),
+                    };
+                    decisions.extend(decision);
                 }
             }
-            _ => {
-                // Already hedged or otherwise idle; nothing to do.
-            }
         }
 
         decisions
     }
 
+    /// Apply a fill to the round that emitted the order.
+    pub fn on_fill(&mut self, fill: &StrategyFill) {
+        let Some(round) = self.rounds.get_mut(&RoundKey {
+            market_slug: fill.market_slug.clone(),
+            round_start: fill.round_start,
+        }) else {
+            return;
+        };
+
+        match fill.label.as_str() {
+            "leg1" => round
+                .leg1
+                .get_or_insert_with(|| LegOrder::new(fill.side, fill.price, 0.0, fill.attempt))
+                .apply_fill(fill.price, fill.shares),
+            "leg2" => {
+                // Fills of a replaced hedge still cover Leg 1 but no longer drive the hedge order.
+                if let Some(hedge) = round.hedge.as_mut().filter(|h| h.attempt == fill.attempt) {
+                    hedge.apply_fill(fill.price, fill.shares);
+                }
+                round.record_hedge_fill(fill.price, fill.shares);
+                round.hedge_failures = 0;
+            }
+            "exit" => {
+                if let Some(exit) = round.exit.as_mut().filter(|e| e.attempt == fill.attempt) {
+                    exit.apply_fill(fill.price, fill.shares);
+                }
+                round.exited_shares += fill.shares;
+            }
+            _ => {}
+        }
+    }
+
+    /// Advance a leg on an execution report: acknowledge it, or close it out and decide
+    /// whether a failed leg may be retried or the round is abandoned.
+    pub fn on_order_update(&mut self, update: &OrderUpdate) {
+        let max_failures = self.params.max_order_retries;
+        let Some(round) = self.rounds.get_mut(&RoundKey {
+            market_slug: update.market_slug.clone(),
+            round_start: update.round_start,
+        }) else {
+            return;
+        };
+
+        // Unwinding retries on every snapshot until the exposure is gone, so its failures
+        // never abandon the round.
+        let exiting = round.exit_reason.is_some();
+        let (order, failures) = match update.label.as_str() {
+            "leg1" => (&mut round.leg1, Some(&mut round.leg1_failures)),
+            "leg2" => (&mut round.hedge, Some(&mut round.hedge_failures)),
+            "exit" => (&mut round.exit, None),
+            _ => return,
+        };
+        // Reports for replaced orders concern an order the round no longer tracks.
+        let Some(leg) = order.as_mut().filter(|l| l.attempt == update.attempt) else {
+            return;
+        };
+
+        if !update.outcome.is_final() {
+            if leg.status == LegStatus::Pending {
+                leg.status = LegStatus::Open;
+            }
+            return;
+        }
+        if update.outcome == OrderOutcome::Blocked {
+            // Never placed: free the slot for a later snapshot without counting a failure.
+            *order = None;
+            return;
+        }
+
+        leg.finish();
+        if leg.status != LegStatus::Failed {
+            return;
+        }
+        let Some(failures) = failures.filter(|_| !exiting) else {
+            *order = None;
+            return;
+        };
+
+        *failures += 1;
+        if *failures > max_failures {
+            round.abandoned = true;
+            warn!(
+                target: "bot",
+                market = %update.market_slug,
+                leg = %update.label,
+                failures = *failures,
+                "leg failed too often; abandoning round"
+            );
+        } else {
+            // Free the slot so a later snapshot can try again.
+            *order = None;
+        }
+    }
+
     /// Snapshot of state for a given market/round, if it exists.
     pub fn state_for(&self, market_slug: &str, round_start_ts: DateTime<Utc>) -> Option<TwoLegState> {
         let key = RoundKey {
@@ -165,18 +589,45 @@ impl TwoLegEngine {
             round_start: round_start_ts,
         };
         self.rounds.get(&key).map(|r| {
-            match (&r.leg1, r.hedged) {
-                (None, _) => TwoLegState::Idle,
-                (Some(leg1), false) => TwoLegState::Leg1Open {
+            let position = r
+                .leg1
+                .as_ref()
+                .map(LegOrder::position)
+                .filter(|p| p.shares > SHARE_EPSILON);
+            match (&r.leg1, position, r.exit_reason) {
+                (_, leg1, _) if r.abandoned => TwoLegState::Abandoned {
                     round_start: r.round_start,
-                    leg1: leg1.clone(),
-                    baseline_mid: r.baseline_mid,
+                    leg1,
                 },
-                (Some(leg1), true) => TwoLegState::Hedged {
+                (None, _, _) => TwoLegState::Idle,
+                (Some(order), None, _) => TwoLegState::Leg1Pending {
                     round_start: r.round_start,
-                    leg1: leg1.clone(),
-                    leg2_price: 0.0,
-                    locked_profit: 0.0,
+                    side: order.side,
+                    limit_price: order.limit_price,
+                    shares: order.requested_shares,
+                    status: order.status,
+                },
+                (Some(_), Some(leg1), Some(reason)) => TwoLegState::Exited {
+                    round_start: r.round_start,
+                    reason,
+                    open_shares: r.open_exposure().max(0.0),
+                    leg1,
+                },
+                (Some(_), Some(leg1), None) if r.is_hedged() => TwoLegState::Hedged {
+                    round_start: r.round_start,
+                    locked_profit: locked_profit(
+                        leg1.entry_price,
+                        r.hedge_avg_price,
+                        r.hedged_shares,
+                        self.params.fee_rate,
+                    ),
+                    leg2_price: r.hedge_avg_price,
+                    leg1,
+                },
+                (Some(_), Some(leg1), None) => TwoLegState::Leg1Open {
+                    round_start: r.round_start,
+                    baseline_mid: r.baseline(leg1.side),
+                    leg1,
                 },
             }
         })
@@ -184,17 +635,59 @@ impl TwoLegEngine {
 
     /// Number of active (unhedged) trades across markets.
     pub fn active_unhedged_trades(&self) -> usize {
-        self.rounds
-            .values()
-            .filter(|r| r.leg1.is_some() && !r.hedged)
-            .count()
+        self.rounds.values().filter(|r| r.is_unhedged()).count()
     }
 
     fn drop_expired_rounds(&mut self, now: DateTime<Utc>) {
         self.rounds
             .retain(|_, r| round_end(r.round_start) >= now);
     }
+}
+
+impl Strategy for TwoLegEngine {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn on_snapshot(&mut self, snapshot: &MarketSnapshot, available_capital: f64) -> Vec<OrderIntent> {
+        TwoLegEngine::on_snapshot(self, snapshot.clone(), available_capital)
+            .into_iter()
+            .map(|d| d.into_intent(&self.name))
+            .collect()
+    }
+
+    fn on_fill(&mut self, fill: &StrategyFill) {
+        TwoLegEngine::on_fill(self, fill);
+    }
 
+    fn on_order_update(&mut self, update: &OrderUpdate) {
+        TwoLegEngine::on_order_update(self, update);
+    }
+
+    fn on_round_end(&mut self, market_slug: &str, round_start: DateTime<Utc>) {
+        self.rounds.remove(&RoundKey {
+            market_slug: market_slug.to_string(),
+            round_start,
+        });
+    }
+
+    fn export_state(&self) -> serde_json::Value {
+        let mut keys: Vec<&RoundKey> = self.rounds.keys().collect();
+        keys.sort_by(|a, b| {
+            (a.round_start, &a.market_slug).cmp(&(b.round_start, &b.market_slug))
+        });
+        let rounds = keys
+            .into_iter()
+            .map(|k| {
+                serde_json::json!({
+                    "market_slug": k.market_slug,
+                    "round_start": k.round_start,
+                    "state": self.state_for(&k.market_slug, k.round_start),
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::Value::Array(rounds)
+    }
 }
 
 fn maybe_open_leg1(
@@ -218,22 +711,36 @@ fn maybe_open_leg1(
             return None;
         }
 
-        let baseline_mid = if round.baseline_mid > 0.0 {
-            round.baseline_mid
-        } else {
-            snapshot.mid_up()
-        };
+        // Crash detection: require a drop from the round baseline of at least move_pct on an
+        // enabled side; if both sides qualify, take the larger crash.
+        let mut crashed: Option<(LegSide, f64, f64)> = None;
+        for side in [LegSide::Up, LegSide::Down] {
+            let enabled = match side {
+                LegSide::Up => params.leg1_up_enabled,
+                LegSide::Down => params.leg1_down_enabled,
+            };
+            if !enabled {
+                continue;
+            }
 
-        let current_mid = snapshot.mid_up();
-        if baseline_mid <= 0.0 || current_mid <= 0.0 {
-            return None;
-        }
+            let current_mid = snapshot.mid(side);
+            let baseline_mid = if round.baseline(side) > 0.0 {
+                round.baseline(side)
+            } else {
+                current_mid
+            };
+            if baseline_mid <= 0.0 || current_mid <= 0.0 {
+                continue;
+            }
 
-        // Crash detection: require price drop from baseline of at least move_pct.
-        let drop = (baseline_mid - current_mid) / baseline_mid;
-        if drop < params.move_pct {
-            return None;
+            let drop = (baseline_mid - current_mid) / baseline_mid;
+            if drop >= params.move_pct && crashed.is_none_or(|(_, best, _)| drop > best) {
+                crashed = Some((side, drop, baseline_mid));
+            }
         }
+        let (side, _, baseline_mid) = crashed?;
+        let current_mid = snapshot.mid(side);
+        let entry_price = snapshot.ask(side);
 
         // Estimate win probability from crash severity and current price.
         let mut p = (1.0 - current_mid) * (1.0 + params.move_pct);
@@ -242,7 +749,7 @@ fn maybe_open_leg1(
         }
         p = p.clamp(0.01, 0.99);
 
-        let price_for_sizing = snapshot.up_ask.max(1e-6);
+        let price_for_sizing = entry_price.max(1e-6);
         let kelly_shares = position_size_kelly(
             available_capital,
             price_for_sizing,
@@ -259,65 +766,284 @@ fn maybe_open_leg1(
         // Ensure we do not request negative or absurdly large size.
         shares = shares.clamp(0.0, params.base_shares);
 
-        let leg1 = LegPosition {
-            side: LegSide::Up,
-            entry_price: snapshot.up_ask,
-            shares,
-        };
-        round.leg1 = Some(leg1.clone());
-        round.baseline_mid = baseline_mid;
+        // Never ask for more than is displayed at the touch.
+        if let Some(available) = snapshot.ask_size(side) {
+            shares = shares.min(available);
+        }
+        if shares <= SHARE_EPSILON {
+            return None;
+        }
+
+        // Nothing is held until fills arrive; the order only reserves the round's Leg 1 slot.
+        round.leg1_attempts += 1;
+        let attempt = round.leg1_attempts;
+        round.leg1 = Some(LegOrder::new(side, entry_price, shares, attempt));
+        round.set_baseline(side, baseline_mid);
 
         Some(TwoLegDecision::OpenLeg1 {
             market_slug: snapshot.market_slug.clone(),
             round_start: round.round_start,
-            side: leg1.side,
-            shares: leg1.shares,
-            limit_price: snapshot.up_ask,
+            side,
+            shares,
+            limit_price: entry_price,
+            attempt,
         })
     }
 
-fn maybe_open_leg2(
+/// Price and size the hedge should be working at.
+struct HedgeQuote {
+    side: LegSide,
+    limit_price: f64,
+    shares: f64,
+}
+
+/// How far hedge escalation has progressed: 0 until `hedge_escalation_secs` before round end,
+/// rising linearly to 1 at `hedge_taker_secs`.
+fn hedge_urgency(params: &TwoLegParams, seconds_left: i64) -> f64 {
+    if seconds_left <= params.hedge_taker_secs {
+        1.0
+    } else if seconds_left >= params.hedge_escalation_secs {
+        0.0
+    } else {
+        (params.hedge_escalation_secs - seconds_left) as f64
+            / (params.hedge_escalation_secs - params.hedge_taker_secs) as f64
+    }
+}
+
+/// Where the hedge for the uncovered part of Leg 1 should be quoted right now.
+///
+/// The most the pair may cost is set by `sum_target` and `min_profit_usd`, relaxed toward
+/// `hedge_max_sum` as the round runs out. An ask within that budget is taken (up to the
+/// displayed size); otherwise the hedge rests as a maker, from the bid early in the round
+/// walking up toward the ask, never above the budget and always on the tick.
+fn quote_hedge(
     params: &TwoLegParams,
     leg1: &LegPosition,
     round: &RoundInternal,
     snapshot: &MarketSnapshot,
-) -> Option<TwoLegDecision> {
-        // Avoid hedging in the last seconds of the round.
-        if seconds_remaining(snapshot.ts) <= 3 {
-            return None;
-        }
+) -> Option<HedgeQuote> {
+    // Avoid hedging in the last seconds of the round.
+    let seconds_left = seconds_remaining(snapshot.ts);
+    if seconds_left <= 3 {
+        return None;
+    }
+
+    let side = match leg1.side {
+        LegSide::Up => LegSide::Down,
+        LegSide::Down => LegSide::Up,
+    };
+    let uncovered = leg1.shares - round.hedged_shares;
+    if uncovered <= SHARE_EPSILON {
+        return None;
+    }
 
-        let (hedge_side, hedge_price) = match leg1.side {
-            LegSide::Up => (LegSide::Down, snapshot.down_ask),
-            LegSide::Down => (LegSide::Up, snapshot.up_ask),
+    let urgency = hedge_urgency(params, seconds_left);
+    let price_cap = |shares: f64| {
+        let profitable = max_pair_cost(shares, params.fee_rate, params.min_profit_usd)
+            .min(params.sum_target);
+        let max_sum = profitable + urgency * (params.hedge_max_sum - profitable).max(0.0);
+        max_sum - leg1.entry_price
+    };
+
+    let (bid, ask) = (snapshot.bid(side), snapshot.ask(side));
+    let take_shares = snapshot
+        .ask_size(side)
+        .map_or(uncovered, |available| uncovered.min(available));
+    let (limit_price, shares) = if ask > 0.0
+        && take_shares > SHARE_EPSILON
+        && ask <= price_cap(take_shares)
+    {
+        (ask, take_shares)
+    } else {
+        let passive = if bid > 0.0 && ask > bid {
+            bid + urgency * (ask - bid)
+        } else {
+            // One-sided book: quote the budget itself.
+            f64::INFINITY
         };
+        (floor_to_tick(passive.min(price_cap(uncovered))), uncovered)
+    };
+    if limit_price <= 0.0 {
+        return None;
+    }
 
-        if hedge_price <= 0.0 {
-            return None;
-        }
+    Some(HedgeQuote {
+        side,
+        limit_price,
+        shares,
+    })
+}
 
-        let expected_profit =
-            locked_profit(leg1.entry_price, hedge_price, leg1.shares, params.fee_rate);
+/// Exit rule that applies to the round's unhedged Leg 1, if any. Once one fires it sticks.
+fn exit_reason(
+    params: &TwoLegParams,
+    leg1: &LegPosition,
+    round: &RoundInternal,
+    snapshot: &MarketSnapshot,
+) -> Option<ExitReason> {
+    if round.exit_reason.is_some() {
+        return round.exit_reason;
+    }
+    if round.open_exposure() <= SHARE_EPSILON {
+        return None;
+    }
 
-        // Enforce both profit and total-cost filters.
-        let total_cost = leg1.entry_price + hedge_price;
-        if expected_profit < params.min_profit_usd || total_cost > params.sum_target {
+    let bid = snapshot.bid(leg1.side);
+    let stopped = params
+        .stop_loss_pct
+        .is_some_and(|pct| bid > 0.0 && bid <= leg1.entry_price * (1.0 - pct));
+    if stopped {
+        return Some(ExitReason::StopLoss);
+    }
+    let out_of_time = params
+        .exit_before_end_secs
+        .is_some_and(|secs| seconds_remaining(snapshot.ts) <= secs);
+    out_of_time.then_some(ExitReason::RoundEnd)
+}
+
+/// Unwind the open Leg 1 exposure with an immediate-or-cancel order.
+///
+/// The opposite ask is taken when the hedge locks in a loss within
+/// `emergency_hedge_max_loss_usd`; otherwise Leg 1 is sold back at its bid. The first exit
+/// goes out at once, canceling whatever the round has working; later ones wait for the
+/// previous order to finish.
+fn manage_exit(
+    params: &TwoLegParams,
+    leg1: &LegPosition,
+    round: &mut RoundInternal,
+    snapshot: &MarketSnapshot,
+    reason: ExitReason,
+) -> Option<TwoLegDecision> {
+    if round.exit_reason.is_none() {
+        round.exit_reason = Some(reason);
+        warn!(
+            target: "bot",
+            market = %snapshot.market_slug,
+            reason = ?reason,
+            open_shares = round.open_exposure(),
+            "exit rule fired; unwinding Leg 1"
+        );
+    } else {
+        let working = |order: &Option<LegOrder>| {
+            order
+                .as_ref()
+                .is_some_and(|o| matches!(o.status, LegStatus::Pending | LegStatus::Open))
+        };
+        if working(&round.exit) || working(&round.hedge) {
             return None;
         }
+    }
 
-        Some(TwoLegDecision::OpenLeg2 {
-            market_slug: snapshot.market_slug.clone(),
-            round_start: round.round_start,
-            side: hedge_side,
-            shares: leg1.shares,
-            limit_price: hedge_price,
-            expected_locked_profit: expected_profit,
-        })
+    let exposure = round.open_exposure();
+    if exposure <= SHARE_EPSILON {
+        return None;
     }
 
+    let hedge_side = match leg1.side {
+        LegSide::Up => LegSide::Down,
+        LegSide::Down => LegSide::Up,
+    };
+    let ask = snapshot.ask(hedge_side);
+    let hedge_shares = snapshot
+        .ask_size(hedge_side)
+        .map_or(exposure, |available| exposure.min(available));
+    let force_hedge = params.emergency_hedge_max_loss_usd.is_some_and(|max_loss| {
+        ask > 0.0
+            && hedge_shares > SHARE_EPSILON
+            && -locked_profit(leg1.entry_price, ask, hedge_shares, params.fee_rate) <= max_loss
+    });
+
+    let (side, shares, limit_price) = if force_hedge {
+        (hedge_side, hedge_shares, ask)
+    } else {
+        let shares = snapshot
+            .bid_size(leg1.side)
+            .map_or(exposure, |available| exposure.min(available));
+        (leg1.side, shares, snapshot.bid(leg1.side))
+    };
+    if limit_price <= 0.0 || shares <= SHARE_EPSILON {
+        return None;
+    }
+
+    let attempt = if force_hedge {
+        round.hedge_attempts += 1;
+        round.hedge = Some(LegOrder::new(side, limit_price, shares, round.hedge_attempts));
+        round.hedge_attempts
+    } else {
+        round.exit_attempts += 1;
+        round.exit = Some(LegOrder::new(side, limit_price, shares, round.exit_attempts));
+        round.exit_attempts
+    };
+
+    Some(TwoLegDecision::ExitLeg1 {
+        market_slug: snapshot.market_slug.clone(),
+        round_start: round.round_start,
+        side,
+        shares,
+        limit_price,
+        force_hedge,
+        reason,
+        attempt,
+    })
+}
+
+/// Place the hedge, or cancel and requote the working one when the quote has moved by at
+/// least `hedge_requote_threshold` or Leg 1 filled beyond what it covers.
+///
+/// An order still awaiting its acknowledgement is left alone.
+fn manage_hedge(
+    params: &TwoLegParams,
+    leg1: &LegPosition,
+    round: &mut RoundInternal,
+    snapshot: &MarketSnapshot,
+) -> Option<TwoLegDecision> {
+    let quote = quote_hedge(params, leg1, round, snapshot)?;
+
+    let replace = match &round.hedge {
+        Some(hedge) if hedge.status == LegStatus::Pending => return None,
+        Some(hedge) if hedge.status == LegStatus::Open => {
+            let moved = (quote.limit_price - hedge.limit_price).abs()
+                >= params.hedge_requote_threshold - SHARE_EPSILON;
+            let grown =
+                quote.shares - (hedge.requested_shares - hedge.filled_shares) > SHARE_EPSILON;
+            if !moved && !grown {
+                return None;
+            }
+            true
+        }
+        _ => false,
+    };
+
+    round.hedge_attempts += 1;
+    let attempt = round.hedge_attempts;
+    round.hedge = Some(LegOrder::new(
+        quote.side,
+        quote.limit_price,
+        quote.shares,
+        attempt,
+    ));
+
+    Some(TwoLegDecision::OpenLeg2 {
+        market_slug: snapshot.market_slug.clone(),
+        round_start: round.round_start,
+        side: quote.side,
+        shares: quote.shares,
+        limit_price: quote.limit_price,
+        expected_locked_profit: locked_profit(
+            leg1.entry_price,
+            quote.limit_price,
+            quote.shares,
+            params.fee_rate,
+        ),
+        attempt,
+        replace,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::strategy::OrderOutcome;
     use chrono::{TimeZone, Utc};
 
     fn ts(s: &str) -> DateTime<Utc> {
@@ -332,6 +1058,11 @@ mod tests {
             up_ask: price_up * 1.01,
             down_bid: price_down * 0.99,
             down_ask: price_down * 1.01,
+            up_bid_size: None,
+            up_ask_size: None,
+            down_bid_size: None,
+            down_ask_size: None,
+            depth: None,
         }
     }
 
@@ -345,9 +1076,40 @@ mod tests {
             risk_per_trade_pct: 2.0,
             fee_rate: 0.02,
             min_profit_usd: 0.10,
+            leg1_up_enabled: true,
+            leg1_down_enabled: true,
+            max_order_retries: 2,
+            hedge_requote_threshold: 0.01,
+            hedge_escalation_secs: 300,
+            hedge_taker_secs: 60,
+            hedge_max_sum: 1.0,
+            stop_loss_pct: None,
+            exit_before_end_secs: None,
+            emergency_hedge_max_loss_usd: None,
         }
     }
 
+    /// Fill every decision completely at its limit price, as the backtester does.
+    fn fill_all(engine: &mut TwoLegEngine, decisions: &[TwoLegDecision]) {
+        for decision in decisions {
+            let intent = decision.clone().into_intent(DEFAULT_STRATEGY_NAME);
+            engine.on_fill(&StrategyFill::from_intent(
+                &intent,
+                intent.limit_price,
+                intent.shares,
+                intent.round_start,
+            ));
+        }
+    }
+
+    fn update(decision: &TwoLegDecision, outcome: OrderOutcome, filled_shares: f64) -> OrderUpdate {
+        OrderUpdate::from_intent(
+            &decision.clone().into_intent(DEFAULT_STRATEGY_NAME),
+            outcome,
+            filled_shares,
+        )
+    }
+
     #[test]
     fn opens_leg1_on_crash() {
         let mut engine = TwoLegEngine::new(default_params());
@@ -377,7 +1139,8 @@ mod tests {
 
         // Crash → open Leg1.
         let s2 = snapshot(0.4, 0.6, "2024-01-01T12:01:00");
-        let _ = engine.on_snapshot(s2, 10_000.0);
+        let leg1 = engine.on_snapshot(s2, 10_000.0);
+        fill_all(&mut engine, &leg1);
 
         // Prices move such that total sum is low enough to lock profit.
         let s3 = snapshot(0.35, 0.35, "2024-01-01T12:05:00");
@@ -387,5 +1150,385 @@ mod tests {
             "expected Leg2 hedge decision"
         );
     }
-}
 
+    #[test]
+    fn caps_legs_to_displayed_liquidity() {
+        let mut params = default_params();
+        params.min_profit_usd = 0.0;
+        let mut engine = TwoLegEngine::new(params);
+
+        engine.on_snapshot(snapshot(0.6, 0.4, "2024-01-01T12:00:10"), 10_000.0);
+
+        let mut crash = snapshot(0.4, 0.6, "2024-01-01T12:01:00");
+        crash.up_ask_size = Some(4.0);
+        let decisions = engine.on_snapshot(crash, 10_000.0);
+        let leg1_shares = match decisions.as_slice() {
+            [TwoLegDecision::OpenLeg1 { shares, .. }] => *shares,
+            other => panic!("expected Leg1 open decision, got {other:?}"),
+        };
+        assert!((leg1_shares - 4.0).abs() < 1e-9);
+        fill_all(&mut engine, &decisions);
+
+        // Only part of the hedge is displayed: hedge that much and keep the round open.
+        let mut thin = snapshot(0.35, 0.35, "2024-01-01T12:05:00");
+        thin.down_ask_size = Some(1.5);
+        let decisions = engine.on_snapshot(thin, 10_000.0);
+        assert!(matches!(
+            decisions.as_slice(),
+            [TwoLegDecision::OpenLeg2 { shares, .. }] if (*shares - 1.5).abs() < 1e-9
+        ));
+        fill_all(&mut engine, &decisions);
+        assert_eq!(engine.active_unhedged_trades(), 1);
+
+        let decisions = engine.on_snapshot(snapshot(0.35, 0.35, "2024-01-01T12:05:01"), 10_000.0);
+        assert!(matches!(
+            decisions.as_slice(),
+            [TwoLegDecision::OpenLeg2 { shares, .. }] if (*shares - 2.5).abs() < 1e-9
+        ));
+        fill_all(&mut engine, &decisions);
+        assert_eq!(engine.active_unhedged_trades(), 0);
+    }
+
+    #[test]
+    fn opens_leg1_on_down_crash_and_hedges_with_up() {
+        let mut params = default_params();
+        params.min_profit_usd = 0.0;
+        let mut engine = TwoLegEngine::new(params);
+
+        engine.on_snapshot(snapshot(0.4, 0.6, "2024-01-01T12:00:10"), 10_000.0);
+
+        // DOWN crashes from 0.6 to 0.4.
+        let decisions = engine.on_snapshot(snapshot(0.6, 0.4, "2024-01-01T12:01:00"), 10_000.0);
+        assert!(matches!(
+            decisions.as_slice(),
+            [TwoLegDecision::OpenLeg1 { side: LegSide::Down, .. }]
+        ));
+        fill_all(&mut engine, &decisions);
+
+        let decisions = engine.on_snapshot(snapshot(0.35, 0.35, "2024-01-01T12:05:00"), 10_000.0);
+        assert!(matches!(
+            decisions.as_slice(),
+            [TwoLegDecision::OpenLeg2 { side: LegSide::Up, .. }]
+        ));
+    }
+
+    #[test]
+    fn disabled_side_is_not_traded() {
+        let mut params = default_params();
+        params.leg1_down_enabled = false;
+        let mut engine = TwoLegEngine::new(params);
+
+        engine.on_snapshot(snapshot(0.4, 0.6, "2024-01-01T12:00:10"), 10_000.0);
+        let decisions = engine.on_snapshot(snapshot(0.6, 0.4, "2024-01-01T12:01:00"), 10_000.0);
+        assert!(decisions.is_empty());
+    }
+
+    #[test]
+    fn strategy_trait_tags_intents_and_releases_rounds() {
+        let mut engine = TwoLegEngine::new(default_params()).with_name("crash_a");
+        let strategy: &mut dyn Strategy = &mut engine;
+
+        strategy.on_snapshot(&snapshot(0.6, 0.4, "2024-01-01T12:00:10"), 1000.0);
+        let intents = strategy.on_snapshot(&snapshot(0.4, 0.6, "2024-01-01T12:01:00"), 1000.0);
+        assert_eq!(intents.len(), 1);
+        assert_eq!(intents[0].strategy, "crash_a");
+        assert_eq!(intents[0].label, "leg1");
+        assert_eq!(intents[0].action, TradeAction::Buy);
+        assert_eq!(strategy.export_state().as_array().map(Vec::len), Some(1));
+
+        strategy.on_round_end("BTC_15m", ts("2024-01-01T12:00:00"));
+        assert_eq!(strategy.export_state(), serde_json::json!([]));
+    }
+
+    #[test]
+    fn legs_advance_only_on_execution_reports() {
+        let mut params = default_params();
+        params.min_profit_usd = 0.0;
+        let mut engine = TwoLegEngine::new(params);
+        let start = ts("2024-01-01T12:00:00");
+
+        engine.on_snapshot(snapshot(0.6, 0.4, "2024-01-01T12:00:10"), 10_000.0);
+        let leg1 = engine.on_snapshot(snapshot(0.4, 0.6, "2024-01-01T12:01:00"), 10_000.0);
+        assert!(matches!(
+            engine.state_for("BTC_15m", start),
+            Some(TwoLegState::Leg1Pending { status: LegStatus::Pending, .. })
+        ));
+
+        // No hedge while nothing is filled, however good the prices.
+        let early = snapshot(0.35, 0.35, "2024-01-01T12:01:30");
+        assert!(engine.on_snapshot(early, 10_000.0).is_empty());
+        engine.on_order_update(&update(&leg1[0], OrderOutcome::Accepted, 0.0));
+        assert!(matches!(
+            engine.state_for("BTC_15m", start),
+            Some(TwoLegState::Leg1Pending { status: LegStatus::Open, .. })
+        ));
+
+        fill_all(&mut engine, &leg1);
+        assert!(matches!(engine.state_for("BTC_15m", start), Some(TwoLegState::Leg1Open { .. })));
+
+        // One hedge order at a time: none while the first is still working.
+        let hedge = engine.on_snapshot(snapshot(0.35, 0.35, "2024-01-01T12:05:00"), 10_000.0);
+        assert_eq!(hedge.len(), 1);
+        let next = snapshot(0.35, 0.35, "2024-01-01T12:05:01");
+        assert!(engine.on_snapshot(next, 10_000.0).is_empty());
+
+        // The hedge is killed unfilled; it may be retried on the next snapshot.
+        engine.on_order_update(&update(&hedge[0], OrderOutcome::Canceled, 0.0));
+        assert_eq!(engine.active_unhedged_trades(), 1);
+        let retry = engine.on_snapshot(snapshot(0.35, 0.35, "2024-01-01T12:05:02"), 10_000.0);
+        assert_eq!(retry.len(), 1);
+        fill_all(&mut engine, &retry);
+        engine.on_order_update(&update(&retry[0], OrderOutcome::Filled, 10.0));
+
+        match engine.state_for("BTC_15m", start) {
+            Some(TwoLegState::Hedged { leg2_price, locked_profit, .. }) => {
+                assert!(leg2_price > 0.0);
+                assert!(locked_profit > 0.0);
+            }
+            other => panic!("expected hedged round, got {other:?}"),
+        }
+        assert_eq!(engine.active_unhedged_trades(), 0);
+    }
+
+    #[test]
+    fn failed_leg1_is_retried_then_abandoned() {
+        let mut params = default_params();
+        params.max_order_retries = 1;
+        let mut engine = TwoLegEngine::new(params);
+
+        engine.on_snapshot(snapshot(0.6, 0.4, "2024-01-01T12:00:10"), 10_000.0);
+        let first = engine.on_snapshot(snapshot(0.4, 0.6, "2024-01-01T12:01:00"), 10_000.0);
+        assert_eq!(first.len(), 1);
+        engine.on_order_update(&update(&first[0], OrderOutcome::Rejected, 0.0));
+        assert_eq!(engine.active_unhedged_trades(), 0);
+
+        let second = engine.on_snapshot(snapshot(0.4, 0.6, "2024-01-01T12:01:10"), 10_000.0);
+        assert_eq!(second.len(), 1);
+        engine.on_order_update(&update(&second[0], OrderOutcome::Canceled, 0.0));
+
+        assert!(engine.on_snapshot(snapshot(0.4, 0.6, "2024-01-01T12:01:20"), 10_000.0).is_empty());
+        assert!(matches!(
+            engine.state_for("BTC_15m", ts("2024-01-01T12:00:00")),
+            Some(TwoLegState::Abandoned { leg1: None, .. })
+        ));
+    }
+
+    #[test]
+    fn blocked_orders_are_retried_without_counting_as_failures() {
+        let mut params = default_params();
+        params.max_order_retries = 0;
+        let mut engine = TwoLegEngine::new(params);
+
+        engine.on_snapshot(snapshot(0.6, 0.4, "2024-01-01T12:00:10"), 10_000.0);
+        let mut attempts = Vec::new();
+        for at in ["2024-01-01T12:01:00", "2024-01-01T12:01:10"] {
+            let leg1 = engine.on_snapshot(snapshot(0.4, 0.6, at), 10_000.0);
+            assert_eq!(leg1.len(), 1);
+            attempts.push(leg1[0].clone().into_intent(DEFAULT_STRATEGY_NAME).attempt);
+            engine.on_order_update(&update(&leg1[0], OrderOutcome::Blocked, 0.0));
+        }
+        // Each resubmission is numbered, and so gets a client order ID of its own.
+        assert_eq!(attempts, [1, 2]);
+        assert!(matches!(
+            engine.state_for("BTC_15m", ts("2024-01-01T12:00:00")),
+            Some(TwoLegState::Idle)
+        ));
+    }
+
+    #[test]
+    fn abandoned_round_keeps_hedging_the_shares_it_holds() {
+        let mut params = default_params();
+        params.max_order_retries = 0;
+        let mut engine = engine_with_filled_leg1_using(params);
+
+        let first = engine.on_snapshot(snapshot(0.4, 0.6, "2024-01-01T12:02:00"), 10_000.0);
+        engine.on_order_update(&update(&first[0], OrderOutcome::Rejected, 0.0));
+        assert!(matches!(
+            engine.state_for("BTC_15m", ts("2024-01-01T12:00:00")),
+            Some(TwoLegState::Abandoned { leg1: Some(_), .. })
+        ));
+
+        let (_, attempt, _) =
+            hedge_of(&engine.on_snapshot(snapshot(0.4, 0.6, "2024-01-01T12:02:10"), 10_000.0));
+        assert_eq!(attempt, 2);
+    }
+
+    #[test]
+    fn partially_filled_leg1_keeps_the_filled_shares() {
+        let mut params = default_params();
+        params.min_profit_usd = 0.0;
+        let mut engine = TwoLegEngine::new(params);
+
+        engine.on_snapshot(snapshot(0.6, 0.4, "2024-01-01T12:00:10"), 10_000.0);
+        let leg1 = engine.on_snapshot(snapshot(0.4, 0.6, "2024-01-01T12:01:00"), 10_000.0);
+        let intent = leg1[0].clone().into_intent(DEFAULT_STRATEGY_NAME);
+        engine.on_fill(&StrategyFill::from_intent(&intent, 0.40, 3.0, intent.round_start));
+        engine.on_order_update(&update(&leg1[0], OrderOutcome::Canceled, 3.0));
+
+        let hedge = engine.on_snapshot(snapshot(0.35, 0.35, "2024-01-01T12:05:00"), 10_000.0);
+        assert!(matches!(
+            hedge.as_slice(),
+            [TwoLegDecision::OpenLeg2 { shares, .. }] if (*shares - 3.0).abs() < 1e-9
+        ));
+    }
+
+    /// Run a round to a filled Leg 1 on UP at 0.404 and return the engine.
+    fn engine_with_filled_leg1() -> TwoLegEngine {
+        engine_with_filled_leg1_using(default_params())
+    }
+
+    fn engine_with_filled_leg1_using(params: TwoLegParams) -> TwoLegEngine {
+        let mut engine = TwoLegEngine::new(params);
+        engine.on_snapshot(snapshot(0.6, 0.4, "2024-01-01T12:00:10"), 10_000.0);
+        let leg1 = engine.on_snapshot(snapshot(0.4, 0.6, "2024-01-01T12:01:00"), 10_000.0);
+        fill_all(&mut engine, &leg1);
+        engine
+    }
+
+    fn hedge_of(decisions: &[TwoLegDecision]) -> (f64, u32, bool) {
+        match decisions {
+            [TwoLegDecision::OpenLeg2 {
+                limit_price,
+                attempt,
+                replace,
+                ..
+            }] => (*limit_price, *attempt, *replace),
+            other => panic!("expected one hedge decision, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn hedge_rests_then_requotes_to_take_the_ask() {
+        let mut engine = engine_with_filled_leg1();
+
+        // The ask is over budget: rest a maker bid at the most the pair may cost (0.95 - 0.404),
+        // floored to the tick so the venue can't round it up to 0.55.
+        let first = engine.on_snapshot(snapshot(0.4, 0.6, "2024-01-01T12:02:00"), 10_000.0);
+        let (price, attempt, replace) = hedge_of(&first);
+        assert!((price - 0.54).abs() < 1e-9);
+        assert_eq!((attempt, replace), (1, false));
+        engine.on_order_update(&update(&first[0], OrderOutcome::Accepted, 0.0));
+
+        // Nothing moved: keep the working order.
+        let same = snapshot(0.4, 0.6, "2024-01-01T12:03:00");
+        assert!(engine.on_snapshot(same, 10_000.0).is_empty());
+
+        // The opposite ask drops into budget: cancel/replace to take it.
+        let second = engine.on_snapshot(snapshot(0.45, 0.5, "2024-01-01T12:04:00"), 10_000.0);
+        let (price, attempt, replace) = hedge_of(&second);
+        assert!((price - 0.505).abs() < 1e-9);
+        assert_eq!((attempt, replace), (2, true));
+
+        // The replaced order's cancellation is not a failure of the working hedge.
+        engine.on_order_update(&update(&first[0], OrderOutcome::Canceled, 0.0));
+        fill_all(&mut engine, &second);
+        assert!(matches!(
+            engine.state_for("BTC_15m", ts("2024-01-01T12:00:00")),
+            Some(TwoLegState::Hedged { .. })
+        ));
+    }
+
+    #[test]
+    fn hedge_escalates_toward_taker_near_round_end() {
+        let mut engine = engine_with_filled_leg1();
+
+        let early = engine.on_snapshot(snapshot(0.4, 0.6, "2024-01-01T12:02:00"), 10_000.0);
+        engine.on_order_update(&update(&early[0], OrderOutcome::Accepted, 0.0));
+
+        // Two minutes left: three quarters escalated, so the cap is 0.95 + 0.75 * 0.05 - 0.404.
+        let late = engine.on_snapshot(snapshot(0.4, 0.6, "2024-01-01T12:13:00"), 10_000.0);
+        let (price, attempt, replace) = hedge_of(&late);
+        assert!((price - 0.58).abs() < 1e-9);
+        assert_eq!((attempt, replace), (2, true));
+        engine.on_order_update(&update(&late[0], OrderOutcome::Accepted, 0.0));
+
+        // Fully escalated: an ask that was never profitable is taken up to `hedge_max_sum`.
+        let last = engine.on_snapshot(snapshot(0.4, 0.59, "2024-01-01T12:14:10"), 10_000.0);
+        let (price, attempt, _) = hedge_of(&last);
+        assert!((price - 0.59 * 1.01).abs() < 1e-9);
+        assert_eq!(attempt, 3);
+    }
+
+    fn exit_of(decisions: &[TwoLegDecision]) -> (LegSide, f64, f64, bool, u32) {
+        match decisions {
+            [TwoLegDecision::ExitLeg1 {
+                side,
+                shares,
+                limit_price,
+                force_hedge,
+                attempt,
+                ..
+            }] => (*side, *shares, *limit_price, *force_hedge, *attempt),
+            other => panic!("expected one exit decision, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn stop_loss_sells_leg1_back_until_flat() {
+        let mut params = default_params();
+        params.stop_loss_pct = Some(0.2);
+        let mut engine = engine_with_filled_leg1_using(params);
+        let start = ts("2024-01-01T12:00:00");
+        let held = match engine.state_for("BTC_15m", start) {
+            Some(TwoLegState::Leg1Open { leg1, .. }) => leg1.shares,
+            other => panic!("expected open Leg 1, got {other:?}"),
+        };
+
+        // UP's bid falls from 0.404 entry to 0.297: more than 20% adverse.
+        let first = engine.on_snapshot(snapshot(0.3, 0.75, "2024-01-01T12:02:00"), 10_000.0);
+        let (side, shares, price, force_hedge, attempt) = exit_of(&first);
+        assert_eq!((side, force_hedge, attempt), (LegSide::Up, false, 1));
+        assert!((shares - held).abs() < 1e-9);
+        assert!((price - 0.297).abs() < 1e-9);
+
+        let intent = first[0].clone().into_intent(DEFAULT_STRATEGY_NAME);
+        assert_eq!(intent.action, TradeAction::Sell);
+        assert_eq!(intent.label, "exit");
+        assert!(intent.immediate_or_cancel);
+        assert!(intent.replaces.contains(&"leg2".to_string()));
+
+        // Half sells before the rest is killed; the remainder goes out on the next snapshot.
+        engine.on_fill(&StrategyFill::from_intent(&intent, 0.297, held / 2.0, intent.round_start));
+        engine.on_order_update(&update(&first[0], OrderOutcome::Canceled, held / 2.0));
+        assert!(matches!(
+            engine.state_for("BTC_15m", start),
+            Some(TwoLegState::Exited { reason: ExitReason::StopLoss, open_shares, .. })
+                if (open_shares - held / 2.0).abs() < 1e-9
+        ));
+
+        // The rule stays in force even if the price recovers.
+        let second = engine.on_snapshot(snapshot(0.4, 0.6, "2024-01-01T12:02:05"), 10_000.0);
+        let (_, shares, _, _, attempt) = exit_of(&second);
+        assert!((shares - held / 2.0).abs() < 1e-9);
+        assert_eq!(attempt, 2);
+        fill_all(&mut engine, &second);
+        assert_eq!(engine.active_unhedged_trades(), 0);
+        let next = snapshot(0.4, 0.6, "2024-01-01T12:02:10");
+        assert!(engine.on_snapshot(next, 10_000.0).is_empty());
+    }
+
+    #[test]
+    fn round_end_exit_forces_hedge_within_loss_cap() {
+        let mut params = default_params();
+        params.exit_before_end_secs = Some(30);
+        params.emergency_hedge_max_loss_usd = Some(1.0);
+
+        // Hedging at 0.6262 loses about 0.05 per share: within the cap, so force the hedge.
+        let mut engine = engine_with_filled_leg1_using(params.clone());
+        let late = engine.on_snapshot(snapshot(0.4, 0.62, "2024-01-01T12:14:40"), 10_000.0);
+        let (side, _, price, force_hedge, _) = exit_of(&late);
+        assert_eq!((side, force_hedge), (LegSide::Down, true));
+        assert!((price - 0.6262).abs() < 1e-9);
+        let intent = late[0].clone().into_intent(DEFAULT_STRATEGY_NAME);
+        assert_eq!((intent.action, intent.label.as_str()), (TradeAction::Buy, "leg2"));
+        fill_all(&mut engine, &late);
+        assert_eq!(engine.active_unhedged_trades(), 0);
+
+        // At 0.808 the hedge would lose far more than the cap: sell Leg 1 instead.
+        let mut engine = engine_with_filled_leg1_using(params);
+        let late = engine.on_snapshot(snapshot(0.4, 0.8, "2024-01-01T12:14:40"), 10_000.0);
+        let (side, _, price, force_hedge, _) = exit_of(&late);
+        assert_eq!((side, force_hedge), (LegSide::Up, false));
+        assert!((price - 0.396).abs() < 1e-9);
+    }
+}
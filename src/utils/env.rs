@@ -0,0 +1,133 @@
+//! `${VAR}` / `${VAR:-default}` expansion of environment variables in config values.
+
+use anyhow::Context;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum EnvError {
+    #[error("environment variable {0} is not set")]
+    Missing(String),
+
+    #[error("unterminated `${{` placeholder")]
+    Unterminated,
+}
+
+/// Expand placeholders in `input`, reading variables through `lookup`.
+///
+/// `${VAR}` requires `VAR` to be set; `${VAR:-default}` falls back to `default` when it is
+/// unset or empty. A `$` not followed by `{` is kept as is, and `$${` yields a literal `${`.
+pub fn expand_with(
+    input: &str,
+    lookup: &impl Fn(&str) -> Option<String>,
+) -> Result<String, EnvError> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        if let Some(escaped) = after.strip_prefix("${") {
+            out.push_str("${");
+            rest = escaped;
+        } else if let Some(body) = after.strip_prefix('{') {
+            let end = body.find('}').ok_or(EnvError::Unterminated)?;
+            let placeholder = &body[..end];
+            let value = match placeholder.split_once(":-") {
+                Some((var, default)) => lookup(var)
+                    .filter(|v| !v.is_empty())
+                    .unwrap_or_else(|| default.to_string()),
+                None => lookup(placeholder)
+                    .ok_or_else(|| EnvError::Missing(placeholder.to_string()))?,
+            };
+            out.push_str(&value);
+            rest = &body[end + 1..];
+        } else {
+            out.push('$');
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Expand placeholders in every string of a parsed TOML document from the process
+/// environment; errors name the offending field.
+pub fn interpolate_toml(value: &mut toml::Value) -> anyhow::Result<()> {
+    interpolate_with(value, "", &|var| std::env::var(var).ok())
+}
+
+fn interpolate_with(
+    value: &mut toml::Value,
+    path: &str,
+    lookup: &impl Fn(&str) -> Option<String>,
+) -> anyhow::Result<()> {
+    match value {
+        toml::Value::String(s) => {
+            *s = expand_with(s, lookup).with_context(|| format!("config field {path}"))?;
+        }
+        toml::Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                interpolate_with(item, &format!("{path}[{i}]"), lookup)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            for (key, item) in table.iter_mut() {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                interpolate_with(item, &path, lookup)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(var: &str) -> Option<String> {
+        match var {
+            "KEY" => Some("abc".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expands_variables_and_defaults() {
+        assert_eq!(expand_with("${KEY}", &lookup).unwrap(), "abc");
+        assert_eq!(expand_with("x-${KEY}-${KEY}", &lookup).unwrap(), "x-abc-abc");
+        assert_eq!(expand_with("${UNSET:-redis://localhost/}", &lookup).unwrap(), "redis://localhost/");
+        assert_eq!(expand_with("${EMPTY:-fallback}", &lookup).unwrap(), "fallback");
+        assert_eq!(expand_with("${UNSET:-}", &lookup).unwrap(), "");
+        assert_eq!(expand_with("pa$s $${KEY}", &lookup).unwrap(), "pa$s ${KEY}");
+    }
+
+    #[test]
+    fn reports_missing_variables() {
+        assert_eq!(
+            expand_with("${UNSET}", &lookup),
+            Err(EnvError::Missing("UNSET".to_string()))
+        );
+        assert_eq!(expand_with("${KEY", &lookup), Err(EnvError::Unterminated));
+
+        let mut doc: toml::Value = toml::from_str(
+            r#"
+            [api]
+            api_key = "${KEY}"
+            api_secret = "${UNSET}"
+            "#,
+        )
+        .unwrap();
+        let err = interpolate_with(&mut doc, "", &lookup).unwrap_err();
+        assert_eq!(err.to_string(), "config field api.api_secret");
+        assert_eq!(
+            err.root_cause().to_string(),
+            "environment variable UNSET is not set"
+        );
+        assert_eq!(doc["api"]["api_key"].as_str(), Some("abc"));
+    }
+}
@@ -15,6 +15,15 @@ pub fn locked_profit(
     gross - fees
 }
 
+/// Highest combined per-share price of the two legs that still locks in `min_profit` on
+/// `shares` after fees; the inverse of [`locked_profit`].
+pub fn max_pair_cost(shares: f64, fee_rate: f64, min_profit: f64) -> f64 {
+    if shares <= 0.0 {
+        return 0.0;
+    }
+    (1.0 - min_profit / shares) / (1.0 + fee_rate)
+}
+
 /// Simple Kelly fraction for a binary bet.
 ///
 /// `p` is win probability, `b` is net odds (e.g. b = (1/price) - 1).
@@ -60,6 +69,13 @@ mod tests {
         assert!(p > 0.0);
     }
 
+    #[test]
+    fn test_max_pair_cost_inverts_locked_profit() {
+        let sum = max_pair_cost(20.0, 0.02, 1.5);
+        let p = locked_profit(0.4, sum - 0.4, 20.0, 0.02);
+        assert!((p - 1.5).abs() < 1e-9);
+    }
+
     #[test]
     fn test_kelly_basic() {
         let f = kelly_fraction(0.55, 1.0);
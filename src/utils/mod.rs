@@ -1,3 +1,4 @@
+pub mod env;
 pub mod math;
 pub mod time;
 
@@ -16,6 +16,11 @@ fn snapshot(price_up: f64, price_down: f64, ts_str: &str, slug: &str) -> MarketS
         up_ask: price_up * 1.01,
         down_bid: price_down * 0.99,
         down_ask: price_down * 1.01,
+        up_bid_size: None,
+        up_ask_size: None,
+        down_bid_size: None,
+        down_ask_size: None,
+        depth: None,
     }
 }
 
@@ -29,6 +34,16 @@ fn bot_cfg() -> BotConfig {
         risk_per_trade_pct: 2.0,
         fee_rate: 0.02,
         min_profit_usd: 0.0,
+        leg1_up_enabled: true,
+        leg1_down_enabled: true,
+        max_order_retries: 2,
+        hedge_requote_threshold: 0.01,
+        hedge_escalation_secs: 300,
+        hedge_taker_secs: 60,
+        hedge_max_sum: 1.0,
+        stop_loss_pct: None,
+        exit_before_end_secs: None,
+        emergency_hedge_max_loss_usd: None,
     }
 }
 